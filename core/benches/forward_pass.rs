@@ -0,0 +1,80 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use neat_core::{Genome, Network};
+
+/// Builds a layered genome of 200 nodes where every node feeds 8 nodes of the next layer
+fn layered_genome() -> Genome {
+    let inputs = 10;
+    let outputs = 10;
+    let mut builder = Genome::builder(inputs, outputs);
+
+    let mut layers: Vec<Vec<usize>> = vec![(0..inputs).collect()];
+    for _ in 0..4 {
+        layers.push((0..45).map(|_| builder.add_hidden_node()).collect());
+    }
+    layers.push((inputs..inputs + outputs).collect());
+
+    layers.windows(2).for_each(|pair| {
+        let (from_layer, to_layer) = (&pair[0], &pair[1]);
+
+        from_layer.iter().enumerate().for_each(|(i, from)| {
+            (0..8).for_each(|j| {
+                let to = to_layer[(i * 7 + j) % to_layer.len()];
+                let weight = ((i + j) % 10) as f64 / 10. - 0.5;
+
+                builder.connect(*from, to, weight);
+            });
+        });
+    });
+
+    builder.build().unwrap()
+}
+
+/// Builds a genome where every input feeds every hidden node and every hidden node feeds every
+/// output
+fn dense_genome() -> Genome {
+    let inputs = 10;
+    let outputs = 10;
+    let mut builder = Genome::builder(inputs, outputs);
+
+    (0..100).for_each(|i| {
+        let hidden = builder.add_hidden_node();
+        let weight = (i % 10) as f64 / 10. - 0.5;
+
+        (0..inputs).for_each(|input| {
+            builder.connect(input, hidden, weight);
+        });
+        (inputs..inputs + outputs).for_each(|output| {
+            builder.connect(hidden, output, weight);
+        });
+    });
+
+    builder.build().unwrap()
+}
+
+fn forward_pass(c: &mut Criterion) {
+    let genome = layered_genome();
+
+    let network_f64: Network<f64> = Network::from_genome(&genome);
+    let inputs_f64: Vec<f64> = (0..10).map(|i| i as f64 / 10.).collect();
+    c.bench_function("forward 200 nodes f64", |b| {
+        b.iter(|| network_f64.forward(black_box(&inputs_f64)))
+    });
+
+    let network_f32: Network<f32> = Network::from_genome(&genome);
+    let inputs_f32: Vec<f32> = (0..10).map(|i| i as f32 / 10.).collect();
+    c.bench_function("forward 200 nodes f32", |b| {
+        b.iter(|| network_f32.forward(black_box(&inputs_f32)))
+    });
+}
+
+fn dense_forward_pass(c: &mut Criterion) {
+    let network = Network::from(&dense_genome());
+    let inputs: Vec<f64> = (0..10).map(|i| i as f64 / 10.).collect();
+
+    c.bench_function("forward dense 2000 connections", |b| {
+        b.iter(|| network.forward(black_box(&inputs)))
+    });
+}
+
+criterion_group!(benches, forward_pass, dense_forward_pass);
+criterion_main!(benches);
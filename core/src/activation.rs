@@ -1,9 +1,10 @@
+use num_traits::Float;
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
 
-#[derive(Debug, Clone, PartialEq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(
-    feature = "network-serde",
+    any(feature = "network-serde", feature = "config-toml"),
     derive(serde::Serialize, serde::Deserialize)
 )]
 pub enum ActivationKind {
@@ -20,11 +21,39 @@ pub enum ActivationKind {
     Bipolar,
     Inverse,
     SELU,
+    Swish,
+    Mish,
+    GELU,
+}
+
+impl ActivationKind {
+    /// Every activation evolution can pick, which is all of them except the one of input nodes
+    pub fn evolvable() -> Vec<ActivationKind> {
+        use ActivationKind::*;
+
+        vec![
+            Tanh,
+            Relu,
+            Step,
+            Logistic,
+            Identity,
+            Softsign,
+            Sinusoid,
+            Gaussian,
+            BentIdentity,
+            Bipolar,
+            Inverse,
+            SELU,
+            Swish,
+            Mish,
+            GELU,
+        ]
+    }
 }
 
 impl Distribution<ActivationKind> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> ActivationKind {
-        match rng.gen_range(0, 12) {
+        match rng.gen_range(0, 15) {
             0 => ActivationKind::Tanh,
             1 => ActivationKind::Relu,
             2 => ActivationKind::Step,
@@ -35,51 +64,101 @@ impl Distribution<ActivationKind> for Standard {
             7 => ActivationKind::Gaussian,
             8 => ActivationKind::BentIdentity,
             9 => ActivationKind::Bipolar,
-            10 => ActivationKind::SELU,
-            _ => ActivationKind::Inverse,
+            10 => ActivationKind::Inverse,
+            11 => ActivationKind::SELU,
+            12 => ActivationKind::Swish,
+            13 => ActivationKind::Mish,
+            14 => ActivationKind::GELU,
+            _ => unreachable!(),
         }
     }
 }
 
-pub fn activate(x: f64, kind: &ActivationKind) -> f64 {
+pub fn activate<T: Float>(x: T, kind: &ActivationKind) -> T {
+    let k = |value: f64| T::from(value).unwrap();
+    let zero = T::zero();
+    let one = T::one();
+
     match kind {
         ActivationKind::Tanh => x.tanh(),
         ActivationKind::Relu => {
-            if x > 0. {
+            if x > zero {
                 x
             } else {
-                0.01 * x
+                k(0.01) * x
             }
         }
         ActivationKind::Step => {
-            if x > 0. {
-                1.
+            if x > zero {
+                one
             } else {
-                0.
+                zero
             }
         }
-        ActivationKind::Logistic => 1. / (1. + (-x).exp()),
+        ActivationKind::Logistic => one / (one + (-x).exp()),
         ActivationKind::Identity => x,
-        ActivationKind::Softsign => x / (1. + x.abs()),
+        ActivationKind::Softsign => x / (one + x.abs()),
         ActivationKind::Sinusoid => x.sin(),
         ActivationKind::Gaussian => (-x.powi(2)).exp(),
-        ActivationKind::BentIdentity => (((x.powi(2) + 1.).sqrt() - 1.) / 2.) + x,
+        ActivationKind::BentIdentity => (((x.powi(2) + one).sqrt() - one) / k(2.)) + x,
         ActivationKind::Bipolar => {
-            if x > 0. {
-                1.
+            if x > zero {
+                one
             } else {
-                -1.
+                -one
             }
         }
-        ActivationKind::Inverse => 1. - x,
+        ActivationKind::Inverse => one - x,
         ActivationKind::SELU => {
-            let alpha = 1.6732632423543772;
-            let scale = 1.05070098735548;
+            let alpha = k(1.6732632423543772);
+            let scale = k(1.05070098735548);
 
-            let fx = if x > 0. { x } else { alpha * x.exp() - alpha };
+            let fx = if x > zero { x } else { alpha * x.exp() - alpha };
 
             fx * scale
         }
+        ActivationKind::Swish => x / (one + (-x).exp()),
+        ActivationKind::Mish => x * (one + x.exp()).ln().tanh(),
+        ActivationKind::GELU => {
+            let c = k((2. / std::f64::consts::PI).sqrt());
+
+            k(0.5) * x * (one + (c * (x + k(0.044715) * x.powi(3))).tanh())
+        }
         _ => panic!("Unknown activation function"),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::thread_rng;
+    use std::collections::HashMap;
+
+    #[test]
+    fn every_activation_can_be_sampled() {
+        let mut rng = thread_rng();
+        let mut counts: HashMap<ActivationKind, usize> = HashMap::new();
+
+        for _ in 0..10_000 {
+            *counts.entry(rng.gen()).or_insert(0) += 1;
+        }
+
+        assert!(!counts.contains_key(&ActivationKind::Input));
+        assert_eq!(counts.len(), 15);
+        assert!(counts.values().all(|&count| count > 500));
+    }
+
+    #[test]
+    fn smooth_activations_known_values() {
+        assert_eq!(activate(0., &ActivationKind::Swish), 0.);
+        assert_eq!(activate(0., &ActivationKind::Mish), 0.);
+        assert_eq!(activate(0., &ActivationKind::GELU), 0.);
+
+        assert!((activate(1., &ActivationKind::Swish) - 0.7310585786).abs() < 1e-9);
+        assert!((activate(1., &ActivationKind::Mish) - 0.8650983882).abs() < 1e-9);
+        assert!((activate(1., &ActivationKind::GELU) - 0.8411919906).abs() < 1e-9);
+
+        assert!(activate(-10., &ActivationKind::Swish).abs() < 1e-3);
+        assert!((activate(10., &ActivationKind::GELU) - 10.).abs() < 1e-9);
+    }
+}
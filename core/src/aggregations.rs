@@ -1,10 +1,17 @@
+use num_traits::Float;
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
 
-pub fn aggregate(kind: &Aggregation, components: &[f64]) -> f64 {
+/// Combines the inputs of a node, a node without any inputs always aggregates to zero so it
+/// outputs the activation of its bias whatever the aggregation
+pub fn aggregate<T: Float>(kind: &Aggregation, components: &[T]) -> T {
     use Aggregation::*;
 
-    let func: fn(components: &[f64]) -> f64 = match kind {
+    if components.is_empty() {
+        return T::zero();
+    }
+
+    let func: fn(components: &[T]) -> T = match kind {
         Product => product,
         Sum => sum,
         Max => max,
@@ -12,6 +19,9 @@ pub fn aggregate(kind: &Aggregation, components: &[f64]) -> f64 {
         MaxAbs => maxabs,
         Median => median,
         Mean => mean,
+        WeightedSum => sum,
+        Variance => variance,
+        StdDev => std_dev,
     };
 
     func(components)
@@ -19,7 +29,7 @@ pub fn aggregate(kind: &Aggregation, components: &[f64]) -> f64 {
 
 #[derive(Debug, Clone, PartialEq, Hash)]
 #[cfg_attr(
-    feature = "network-serde",
+    any(feature = "network-serde", feature = "config-toml"),
     derive(serde::Serialize, serde::Deserialize)
 )]
 pub enum Aggregation {
@@ -30,68 +40,91 @@ pub enum Aggregation {
     MaxAbs,
     Median,
     Mean,
+    /// Sums the inputs like `Sum`, the weighting itself comes from the connection weights
+    WeightedSum,
+    /// The population variance of the inputs, lets a node react to how spread out they are
+    Variance,
+    /// The population standard deviation of the inputs
+    StdDev,
+}
+
+impl Aggregation {
+    /// Every aggregation evolution can pick
+    pub fn evolvable() -> Vec<Aggregation> {
+        use Aggregation::*;
+
+        vec![
+            Product,
+            Sum,
+            Max,
+            Min,
+            MaxAbs,
+            Median,
+            Mean,
+            WeightedSum,
+            Variance,
+            StdDev,
+        ]
+    }
 }
 
 impl Distribution<Aggregation> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Aggregation {
         use Aggregation::*;
 
-        match rng.gen_range(0, 7) {
+        match rng.gen_range(0, 10) {
             0 => Product,
             1 => Sum,
             2 => Max,
             3 => Min,
             4 => MaxAbs,
             5 => Median,
-            _ => Mean,
+            6 => Mean,
+            7 => WeightedSum,
+            8 => Variance,
+            _ => StdDev,
         }
     }
 }
 
-fn product(components: &[f64]) -> f64 {
+fn product<T: Float>(components: &[T]) -> T {
     components
         .iter()
-        .fold(1., |result, current| result * current)
+        .fold(T::one(), |result, current| result * *current)
 }
 
-fn sum(components: &[f64]) -> f64 {
-    components.iter().sum()
+fn sum<T: Float>(components: &[T]) -> T {
+    components
+        .iter()
+        .fold(T::zero(), |result, current| result + *current)
 }
 
-fn max(components: &[f64]) -> f64 {
+fn max<T: Float>(components: &[T]) -> T {
     components.iter().fold(
-        f64::MIN,
+        T::min_value(),
         |max, current| if *current > max { *current } else { max },
     )
 }
 
-fn min(components: &[f64]) -> f64 {
+fn min<T: Float>(components: &[T]) -> T {
     components.iter().fold(
-        f64::MAX,
+        T::max_value(),
         |min, current| if *current < min { *current } else { min },
     )
 }
 
-fn maxabs(components: &[f64]) -> f64 {
-    let abs_components: Vec<f64> = components.iter().map(|component| component.abs()).collect();
+fn maxabs<T: Float>(components: &[T]) -> T {
+    let abs_components: Vec<T> = components.iter().map(|component| component.abs()).collect();
     max(&abs_components)
 }
 
-fn median(components: &[f64]) -> f64 {
-    use std::cmp::Ordering;
-
+fn median<T: Float>(components: &[T]) -> T {
     if components.is_empty() {
-        return 0.;
+        return T::zero();
     }
 
     let mut sorted = components.to_vec();
-    sorted.sort_by(|a, b| {
-        if a < b {
-            Ordering::Less
-        } else {
-            Ordering::Greater
-        }
-    });
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
 
     let length = sorted.len();
     let is_length_even = length % 2 == 0;
@@ -104,9 +137,22 @@ fn median(components: &[f64]) -> f64 {
     *sorted.get(median_index).unwrap()
 }
 
-fn mean(components: &[f64]) -> f64 {
-    let sum: f64 = components.iter().sum();
-    sum / components.len() as f64
+fn mean<T: Float>(components: &[T]) -> T {
+    sum(components) / T::from(components.len()).unwrap()
+}
+
+fn variance<T: Float>(components: &[T]) -> T {
+    let mean = mean(components);
+    let squared_deviations: Vec<T> = components
+        .iter()
+        .map(|component| (*component - mean).powi(2))
+        .collect();
+
+    sum(&squared_deviations) / T::from(components.len()).unwrap()
+}
+
+fn std_dev<T: Float>(components: &[T]) -> T {
+    variance(components).sqrt()
 }
 
 #[cfg(test)]
@@ -161,4 +207,49 @@ mod tests {
 
         assert!((mean(&components) - 2.5).abs() < f64::EPSILON);
     }
+
+    #[test]
+    fn no_components_aggregate_to_zero() {
+        use Aggregation::*;
+
+        let kinds = [
+            Product,
+            Sum,
+            Max,
+            Min,
+            MaxAbs,
+            Median,
+            Mean,
+            WeightedSum,
+            Variance,
+            StdDev,
+        ];
+
+        kinds.iter().for_each(|kind| {
+            assert_eq!(aggregate::<f64>(kind, &[]), 0., "{:?}", kind);
+            assert_eq!(aggregate::<f32>(kind, &[]), 0., "{:?}", kind);
+        });
+    }
+
+    #[test]
+    fn weighted_sum_works() {
+        let components = vec![1., 2., 3., 4.];
+
+        assert!((aggregate(&Aggregation::WeightedSum, &components) - 10.).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn variance_works() {
+        let components = vec![2., 4., 4., 4., 5., 5., 7., 9.];
+
+        assert!((variance(&components) - 4.).abs() < f64::EPSILON);
+        assert!(variance(&[3., 3., 3.]).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn std_dev_works() {
+        let components = vec![2., 4., 4., 4., 5., 5., 7., 9.];
+
+        assert!((std_dev(&components) - 2.).abs() < f64::EPSILON);
+    }
 }
@@ -1,22 +1,43 @@
-use crate::genome::connection::ConnectionGene;
+use num_traits::Float;
 
-#[derive(Debug)]
+use crate::genome::innovation::topology_innovation;
+use crate::genome::ConnectionGene;
+
+#[derive(Debug, Clone)]
 #[cfg_attr(
     feature = "network-serde",
     derive(serde::Serialize, serde::Deserialize)
 )]
-pub struct Connection {
+pub struct Connection<T = f64> {
     pub from: usize,
     pub to: usize,
-    pub weight: f64,
+    pub weight: T,
+    pub recurrent: bool,
 }
 
-impl From<&ConnectionGene> for Connection {
+impl<T: Float> From<&ConnectionGene> for Connection<T> {
     fn from(g: &ConnectionGene) -> Self {
         Connection {
             from: g.from,
             to: g.to,
-            weight: g.weight,
+            weight: T::from(g.weight).unwrap(),
+            recurrent: g.recurrent,
+        }
+    }
+}
+
+/// The connection comes back enabled as networks only keep enabled connections, it gets the
+/// innovation number of the same connection in a starting topology
+impl<T: Float> From<&Connection<T>> for ConnectionGene {
+    fn from(c: &Connection<T>) -> Self {
+        ConnectionGene {
+            from: c.from,
+            to: c.to,
+            weight: c.weight.to_f64().unwrap(),
+            disabled: false,
+            recurrent: c.recurrent,
+            innovation: topology_innovation(c.from, c.to, c.recurrent),
+            frozen: false,
         }
     }
 }
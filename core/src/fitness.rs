@@ -0,0 +1,106 @@
+use crate::network::Network;
+
+/// Wraps a fitness function of a single episode into one that runs `episodes` episodes and
+/// averages their fitness, that evens out lucky and unlucky episodes of random environments
+pub fn average_over<F>(
+    episodes: usize,
+    episode_fitness: F,
+) -> impl Fn(&mut Network) -> f64 + Sync + Send
+where
+    F: Fn(&mut Network) -> f64 + Sync + Send,
+{
+    assert!(episodes > 0, "At least one episode has to be run");
+
+    move |network| run_episodes(network, episodes, &episode_fitness).sum::<f64>() / episodes as f64
+}
+
+/// Wraps a fitness function of a single episode into one that runs `episodes` episodes and keeps
+/// the worst fitness, that rewards networks which do well every time
+pub fn min_over<F>(
+    episodes: usize,
+    episode_fitness: F,
+) -> impl Fn(&mut Network) -> f64 + Sync + Send
+where
+    F: Fn(&mut Network) -> f64 + Sync + Send,
+{
+    assert!(episodes > 0, "At least one episode has to be run");
+
+    move |network| run_episodes(network, episodes, &episode_fitness).fold(f64::MAX, f64::min)
+}
+
+/// Every episode starts from a reset network so recurrent connections don't carry values over
+fn run_episodes<'a, F>(
+    network: &'a mut Network,
+    episodes: usize,
+    episode_fitness: &'a F,
+) -> impl Iterator<Item = f64> + 'a
+where
+    F: Fn(&mut Network) -> f64,
+{
+    (0..episodes).map(move |_| {
+        network.reset();
+        episode_fitness(network)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Configuration, Genome, NEAT};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn average_over_averages_episodes() {
+        let episode = AtomicUsize::new(0);
+        let fitness = average_over(4, |_| episode.fetch_add(1, Ordering::SeqCst) as f64);
+
+        assert!((fitness(&mut Network::from(&Genome::new(1, 1))) - 1.5).abs() < f64::EPSILON);
+        assert_eq!(episode.load(Ordering::SeqCst), 4);
+    }
+
+    #[test]
+    fn min_over_keeps_the_worst_episode() {
+        let episode = AtomicUsize::new(0);
+        let fitness = min_over(3, |_| {
+            let i = episode.fetch_add(1, Ordering::SeqCst);
+
+            [5., -2., 3.][i]
+        });
+
+        assert!((fitness(&mut Network::from(&Genome::new(1, 1))) + 2.).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn episodes_start_from_a_reset_network() {
+        let fitness = average_over(3, |network| {
+            let stale = network.nodes.iter().any(|n| n.value.is_some());
+            network.forward_pass(vec![1.]);
+
+            if stale {
+                0.
+            } else {
+                1.
+            }
+        });
+
+        assert!((fitness(&mut Network::from(&Genome::new(1, 1))) - 1.).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn wrapped_fitness_drives_evolution() {
+        let mut system = NEAT::new(
+            1,
+            1,
+            average_over(2, |network| network.forward_pass(vec![1.])[0]),
+        );
+        system.set_configuration(Configuration {
+            population_size: 10,
+            max_generations: 2,
+            ..Default::default()
+        });
+
+        let (_, fitness) = system.start();
+
+        assert!(fitness.is_finite());
+    }
+}
@@ -0,0 +1,96 @@
+use super::{ConnectionGene, Genome, GenomeError, NodeGene};
+use crate::node::NodeKind;
+
+/// Builds a genome by hand, starting from unconnected input and output nodes
+pub struct GenomeBuilder {
+    genome: Genome,
+}
+
+impl GenomeBuilder {
+    pub(super) fn new(inputs: usize, outputs: usize) -> Self {
+        let mut genome = Genome::empty(inputs, outputs);
+
+        (0..inputs).for_each(|_| genome.node_genes.push(NodeGene::new(NodeKind::Input)));
+        (0..outputs).for_each(|_| genome.node_genes.push(NodeGene::new(NodeKind::Output)));
+
+        GenomeBuilder { genome }
+    }
+
+    /// Adds a hidden node with random properties and returns its index
+    pub fn add_hidden_node(&mut self) -> usize {
+        self.genome.add_node()
+    }
+
+    /// Adds a constant node with the given value and returns its index
+    pub fn add_constant_node(&mut self, value: f64) -> usize {
+        let index = self.genome.add_constant_node();
+        self.genome.node_mut(index).unwrap().bias = value;
+
+        index
+    }
+
+    /// Adds a feed forward connection with the given weight
+    pub fn connect(&mut self, from: usize, to: usize, weight: f64) -> &mut Self {
+        let mut connection = ConnectionGene::new(from, to);
+        connection.weight = weight;
+
+        self.genome.connection_genes.push(connection);
+
+        self
+    }
+
+    /// Checks the connections and returns the genome if they form a valid network
+    pub fn build(self) -> Result<Genome, GenomeError> {
+        match self.genome.validate() {
+            Ok(()) => Ok(self.genome),
+            Err(mut errors) => Err(errors.remove(0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_builds_connected_genome() {
+        let mut builder = Genome::builder(2, 1);
+        let hidden = builder.add_hidden_node();
+        builder.connect(0, hidden, 0.5).connect(hidden, 2, -1.);
+
+        let genome = builder.build().unwrap();
+
+        assert_eq!(genome.input_count(), 2);
+        assert_eq!(genome.output_count(), 1);
+        assert_eq!(genome.nodes().len(), 4);
+        assert_eq!(genome.connections().len(), 2);
+        assert!((genome.connections()[1].weight + 1.).abs() < f64::EPSILON);
+        assert!(genome.node_order().is_some());
+    }
+
+    #[test]
+    fn builder_rejects_invalid_genomes() {
+        let mut builder = Genome::builder(1, 1);
+        let a = builder.add_hidden_node();
+        let b = builder.add_hidden_node();
+        builder.connect(a, b, 1.).connect(b, a, 1.);
+        assert_eq!(builder.build(), Err(GenomeError::Cycle));
+
+        let mut builder = Genome::builder(1, 1);
+        builder.connect(0, 5, 1.);
+        assert_eq!(
+            builder.build(),
+            Err(GenomeError::NodeOutOfRange {
+                connection: 0,
+                node: 5
+            })
+        );
+
+        let mut builder = Genome::builder(1, 1);
+        builder.connect(1, 0, 1.);
+        assert_eq!(
+            builder.build(),
+            Err(GenomeError::ConnectionToInput { connection: 0 })
+        );
+    }
+}
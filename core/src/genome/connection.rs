@@ -1,32 +1,52 @@
-use rand::random;
+use rand::{thread_rng, Rng};
 use std::hash::{Hash, Hasher};
 
+use super::innovation::topology_innovation;
+
 #[derive(Debug, Clone)]
 pub struct ConnectionGene {
     pub from: usize,
     pub to: usize,
     pub weight: f64,
     pub disabled: bool,
+    /// Recurrent connections carry the value their source had on the previous forward pass
+    pub recurrent: bool,
+    /// Historical marking used to line up genes of different genomes
+    pub innovation: usize,
+    /// Frozen connections keep their weight and stay enabled through mutations
+    pub frozen: bool,
 }
 
 impl ConnectionGene {
     pub fn new(from: usize, to: usize) -> Self {
+        ConnectionGene::new_with_rng(from, to, &mut thread_rng())
+    }
+
+    /// Creates a connection of a starting topology, it shares the innovation number with the same
+    /// connection in other genomes
+    pub fn new_with_rng<R: Rng + ?Sized>(from: usize, to: usize, rng: &mut R) -> Self {
+        ConnectionGene::with_innovation(from, to, topology_innovation(from, to, false), rng)
+    }
+
+    pub(super) fn with_innovation<R: Rng + ?Sized>(
+        from: usize,
+        to: usize,
+        innovation: usize,
+        rng: &mut R,
+    ) -> Self {
         ConnectionGene {
             from,
             to,
-            weight: random::<f64>() * 2. - 1.,
+            weight: rng.gen::<f64>() * 2. - 1.,
             disabled: false,
+            recurrent: false,
+            innovation,
+            frozen: false,
         }
     }
 
     pub fn innovation_number(&self) -> usize {
-        let a = self.from;
-        let b = self.to;
-
-        let first_part = (a + b) * (a + b + 1);
-        let second_part = b;
-
-        first_part.checked_div(2).unwrap() + second_part
+        self.innovation
     }
 }
 
@@ -35,6 +55,7 @@ impl PartialEq for ConnectionGene {
         self.from == other.from
             && self.to == other.to
             && self.disabled == other.disabled
+            && self.recurrent == other.recurrent
             && (self.weight - other.weight).abs() < f64::EPSILON
     }
 }
@@ -46,6 +67,7 @@ impl Hash for ConnectionGene {
         self.from.hash(state);
         self.to.hash(state);
         self.disabled.hash(state);
+        self.recurrent.hash(state);
         self.weight.to_bits().hash(state);
     }
 }
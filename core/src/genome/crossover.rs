@@ -1,8 +1,22 @@
-use rand::random;
+use rand::{thread_rng, Rng};
+use std::collections::HashSet;
 
-use super::{ConnectionGene, Genome, NodeGene};
+use super::{random_id, ConnectionGene, Genome, NodeGene};
+use crate::node::NodeKind;
+use crate::Configuration;
 
 pub fn crossover(a: (&Genome, f64), b: (&Genome, f64)) -> Option<Genome> {
+    crossover_with_rng(a, b, &Default::default(), &mut thread_rng())
+}
+
+/// Matching genes come from either parent at random, disjoint and excess genes come from the
+/// fitter parent or from both when they are equally fit
+pub fn crossover_with_rng<R: Rng + ?Sized>(
+    a: (&Genome, f64),
+    b: (&Genome, f64),
+    configuration: &Configuration,
+    rng: &mut R,
+) -> Option<Genome> {
     if (a.0.inputs != b.0.inputs) || (a.0.outputs != b.0.outputs) {
         return None;
     }
@@ -19,69 +33,106 @@ pub fn crossover(a: (&Genome, f64), b: (&Genome, f64)) -> Option<Genome> {
         std::mem::swap(&mut fitness_a, &mut fitness_b);
     }
 
+    let equally_fit = (fitness_a - fitness_b).abs() < f64::EPSILON;
+
     let mut child = Genome::empty(parent_a.inputs, parent_a.outputs);
+    child.id = random_id(rng);
+    child.bias_input = parent_a.bias_input;
 
-    let child_connection_genes: Vec<ConnectionGene> = parent_a
+    let mut child_connection_genes: Vec<ConnectionGene> = parent_a
         .connection_genes
         .iter()
         .map(|connection| {
-            let maybe_counterpart_connection = parent_b
-                .connection_genes
-                .iter()
-                .find(|cb| cb.innovation_number() == connection.innovation_number());
+            let maybe_counterpart_connection = find_counterpart(connection, &parent_b);
 
-            // Chooses connection from one of the parents
-            let chosen_connection =
-                if let Some(counterpart_connection) = maybe_counterpart_connection {
-                    if random::<f64>() < 0.5 {
-                        connection
-                    } else {
-                        counterpart_connection
-                    }
+            if let Some(counterpart_connection) = maybe_counterpart_connection {
+                // Chooses connection from one of the parents, frozen copies are kept as they are
+                let chosen_connection = if connection.frozen {
+                    connection
+                } else if counterpart_connection.frozen || rng.gen::<f64>() >= 0.5 {
+                    counterpart_connection
                 } else {
                     connection
                 };
 
-            /*
-             * Chooses will the new connection be disabled
-             * - disabled in both parents, 75% chance it will be disabled
-             * - enabled in both parents, it will be enabled
-             * - disabled in one parent, 50% chance it will stay disabled
-             */
-            let new_disabled = if let Some(counterpart_connection) = maybe_counterpart_connection {
-                match (connection.disabled, counterpart_connection.disabled) {
-                    (true, true) => random::<f64>() < 0.75,
-                    (false, false) => false,
-                    _ => random::<f64>() < 0.5,
-                }
-            } else {
-                connection.disabled
-            };
+                // A connection disabled in either parent has a chance to stay disabled
+                let new_disabled = if chosen_connection.frozen {
+                    chosen_connection.disabled
+                } else if connection.disabled || counterpart_connection.disabled {
+                    rng.gen::<f64>() < configuration.inherit_disabled_prob
+                } else {
+                    false
+                };
 
-            let mut new_connection = chosen_connection.clone();
-            new_connection.disabled = new_disabled;
+                let mut new_connection = chosen_connection.clone();
+                new_connection.disabled = new_disabled;
 
-            new_connection
+                new_connection
+            } else {
+                connection.clone()
+            }
         })
         .collect();
 
-    let required_node_count = 1 + child_connection_genes
+    if equally_fit {
+        parent_b
+            .connection_genes
+            .iter()
+            .filter(|connection| find_counterpart(connection, &parent_a).is_none())
+            .for_each(|connection| {
+                // Genes of the other parent can't wire nodes against their kind or form cycles
+                let from_output = matches!(
+                    node_kind(connection.from, &parent_a, &parent_b),
+                    Some(NodeKind::Output)
+                );
+                let to_input = matches!(
+                    node_kind(connection.to, &parent_a, &parent_b),
+                    Some(NodeKind::Input) | Some(NodeKind::Constant)
+                );
+                let forms_cycle = !connection.recurrent
+                    && is_reachable(&child_connection_genes, connection.to, connection.from);
+                // The same connection could have appeared independently in both parents
+                let is_duplicate = child_connection_genes.iter().any(|c| {
+                    (c.from == connection.from
+                        && c.to == connection.to
+                        && c.recurrent == connection.recurrent)
+                        || c.innovation_number() == connection.innovation_number()
+                });
+
+                if !from_output && !to_input && !forms_cycle && !is_duplicate {
+                    child_connection_genes.push(connection.clone());
+                }
+            });
+    }
+
+    // Connections to nodes that neither parent has would leave the child with dangling references
+    let available_node_count = usize::max(parent_a.node_genes.len(), parent_b.node_genes.len());
+    child_connection_genes.retain(|c| c.from < available_node_count && c.to < available_node_count);
+
+    // Inputs and outputs are kept even when no connection references them
+    let required_node_count = child_connection_genes
         .iter()
-        .fold(0, |max, c| usize::max(usize::max(max, c.from), c.to));
+        .fold(parent_a.inputs + parent_a.outputs, |max, c| {
+            usize::max(max, usize::max(c.from, c.to) + 1)
+        });
 
     let child_node_genes: Vec<NodeGene> = (0..required_node_count)
-        .map(
+        .filter_map(
             |i| match (parent_a.node_genes.get(i), parent_b.node_genes.get(i)) {
-                (Some(a), Some(b)) => {
-                    if random::<f64>() < 0.5 {
-                        a
+                // Nodes of different kinds are never mixed, the fitter parent wins
+                (Some(a), Some(b)) if a.kind == b.kind => {
+                    if a.frozen {
+                        Some(a)
+                    } else if b.frozen {
+                        Some(b)
+                    } else if rng.gen::<f64>() < 0.5 {
+                        Some(a)
                     } else {
-                        b
+                        Some(b)
                     }
                 }
-                (Some(a), None) => a,
-                (None, Some(b)) => b,
-                _ => panic!("Node selection out of bounds"),
+                (Some(a), _) => Some(a),
+                (None, b) => b,
             },
         )
         .cloned()
@@ -93,6 +144,49 @@ pub fn crossover(a: (&Genome, f64), b: (&Genome, f64)) -> Option<Genome> {
     child.node_order().and(Some(child))
 }
 
+/// Returns the kind the node at the index will have in the child
+fn node_kind(index: usize, a: &Genome, b: &Genome) -> Option<NodeKind> {
+    a.node_genes
+        .get(index)
+        .or_else(|| b.node_genes.get(index))
+        .map(|n| n.kind.clone())
+}
+
+/// Checks if the target can be reached from the source through feed forward connections,
+/// disabled ones included as they can be enabled again later
+pub(super) fn is_reachable(connections: &[ConnectionGene], source: usize, target: usize) -> bool {
+    let mut visited: HashSet<usize> = HashSet::new();
+    let mut to_visit: Vec<usize> = vec![source];
+
+    while let Some(i) = to_visit.pop() {
+        if i == target {
+            return true;
+        }
+
+        if visited.insert(i) {
+            connections
+                .iter()
+                .filter(|c| c.from == i && !c.recurrent)
+                .for_each(|c| to_visit.push(c.to));
+        }
+    }
+
+    false
+}
+
+/// Compaction renumbers nodes but keeps innovations, so matching genes have to join the same
+/// nodes as well
+fn find_counterpart<'a>(
+    connection: &ConnectionGene,
+    other: &'a Genome,
+) -> Option<&'a ConnectionGene> {
+    other.connection_genes.iter().find(|c| {
+        c.innovation_number() == connection.innovation_number()
+            && c.from == connection.from
+            && c.to == connection.to
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,4 +217,119 @@ mod tests {
         let maybe_child = crossover((&a, 1.), (&b, 2.));
         assert!(maybe_child.is_none());
     }
+
+    fn parents() -> (Genome, Genome) {
+        let mut builder = Genome::builder(2, 1);
+        let hidden = builder.add_hidden_node();
+        builder
+            .connect(0, 2, 0.1)
+            .connect(0, hidden, 0.1)
+            .connect(hidden, 2, 0.1);
+        let a = builder.build().unwrap();
+
+        let mut builder = Genome::builder(2, 1);
+        builder.connect(0, 2, 0.2).connect(1, 2, 0.2);
+        let b = builder.build().unwrap();
+
+        (a, b)
+    }
+
+    fn has_connection(g: &Genome, from: usize, to: usize) -> bool {
+        g.connections().iter().any(|c| c.from == from && c.to == to)
+    }
+
+    #[test]
+    fn disjoint_genes_come_from_the_fitter_parent() {
+        let (a, b) = parents();
+
+        for _ in 0..10 {
+            let child = crossover((&a, 1.), (&b, 2.)).unwrap();
+
+            assert!(has_connection(&child, 0, 2));
+            assert!(has_connection(&child, 1, 2));
+            assert!(!has_connection(&child, 0, 3));
+            assert_eq!(child.connections().len(), 2);
+        }
+    }
+
+    #[test]
+    fn equally_fit_parents_both_pass_disjoint_genes() {
+        let (a, b) = parents();
+
+        let child = crossover((&a, 1.), (&b, 1.)).unwrap();
+
+        assert!(has_connection(&child, 0, 2));
+        assert!(has_connection(&child, 1, 2));
+        assert!(has_connection(&child, 0, 3));
+        assert!(has_connection(&child, 3, 2));
+        assert_eq!(child.connections().len(), 4);
+    }
+
+    #[test]
+    fn disabled_genes_follow_the_configured_chance() {
+        let (a, mut b) = parents();
+        b.connection_genes[0].disabled = true;
+
+        let child_with = |inherit_disabled_prob: f64| {
+            let configuration = Configuration {
+                inherit_disabled_prob,
+                ..Default::default()
+            };
+
+            crossover_with_rng((&a, 1.), (&b, 2.), &configuration, &mut thread_rng()).unwrap()
+        };
+
+        let is_disabled = |g: &Genome| {
+            g.connections()
+                .iter()
+                .find(|c| c.from == 0 && c.to == 2)
+                .unwrap()
+                .disabled
+        };
+
+        assert!(is_disabled(&child_with(1.)));
+        assert!(!is_disabled(&child_with(0.)));
+    }
+
+    #[test]
+    fn connections_to_missing_nodes_are_dropped() {
+        let (mut a, b) = parents();
+        a.connection_genes.push(ConnectionGene::new(3, 10));
+
+        let child = crossover((&a, 2.), (&b, 1.)).unwrap();
+
+        assert!(!has_connection(&child, 3, 10));
+        assert_eq!(child.nodes().len(), 4);
+    }
+
+    #[test]
+    fn frozen_genes_are_inherited_as_they_are() {
+        let mut seed = Genome::decode("I1 O1 H1 | 0-2:0.5 2-1:-0.5 | 2:Tanh:0.3").unwrap();
+        seed.freeze_connection(0, 2).unwrap();
+        seed.freeze_connection(2, 1).unwrap();
+        seed.freeze_node(2).unwrap();
+        let mutated = Genome::decode("I1 O1 H1 | 0-2:1* 2-1:1 | 2:Relu:-1").unwrap();
+
+        for _ in 0..10 {
+            let child = crossover((&seed, 1.), (&mutated, 2.)).unwrap();
+
+            assert_eq!(child.connections(), seed.connections());
+            assert_eq!(child.nodes()[2], seed.nodes()[2]);
+        }
+    }
+
+    #[test]
+    fn matching_genes_join_the_same_nodes() {
+        // Compaction renumbers nodes so the same innovation can end up between other nodes
+        let a = Genome::decode("I1 O1 H1 | 0-1:1 0-2:1 2-1:1").unwrap();
+        let mut b = Genome::decode("I1 O1 | 0-1:0.5").unwrap();
+        b.connection_genes[0].innovation = a.connection_genes[2].innovation;
+
+        for _ in 0..10 {
+            let child = crossover((&a, 1.), (&b, 1.)).unwrap();
+
+            assert!(child.validate().is_ok());
+            assert_eq!(child.connections().len(), 3);
+        }
+    }
 }
@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use super::{ConnectionGene, Genome};
+use crate::activation::ActivationKind;
+use crate::aggregations::Aggregation;
+
+/// Connection identified by the nodes it connects
+pub type ConnectionKey = (usize, usize);
+
+/// Every gene that differs between two genomes, changes go from the first genome to the second
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GenomeDiff {
+    /// Connections only the second genome has
+    pub added_connections: Vec<ConnectionKey>,
+    /// Connections only the first genome has
+    pub removed_connections: Vec<ConnectionKey>,
+    /// Connections enabled in the first genome and disabled in the second one
+    pub disabled_connections: Vec<ConnectionKey>,
+    /// Connections disabled in the first genome and enabled in the second one
+    pub enabled_connections: Vec<ConnectionKey>,
+    /// Connections of both genomes with different weights, as the old and the new weight
+    pub changed_weights: Vec<(ConnectionKey, f64, f64)>,
+    /// Indexes of nodes only the second genome has
+    pub added_nodes: Vec<usize>,
+    /// Indexes of nodes only the first genome has
+    pub removed_nodes: Vec<usize>,
+    pub changed_nodes: Vec<NodeChange>,
+}
+
+/// The old and the new value of every changed property of a node present in both genomes
+#[derive(Debug, Clone, PartialEq)]
+pub struct NodeChange {
+    pub index: usize,
+    pub bias: Option<(f64, f64)>,
+    pub activation: Option<(ActivationKind, ActivationKind)>,
+    pub aggregation: Option<(Aggregation, Aggregation)>,
+}
+
+impl GenomeDiff {
+    pub fn is_empty(&self) -> bool {
+        *self == GenomeDiff::default()
+    }
+}
+
+/// Connections by their nodes, an enabled connection wins over disabled duplicates
+fn connections_by_key(genome: &Genome) -> BTreeMap<ConnectionKey, &ConnectionGene> {
+    let mut connections: BTreeMap<ConnectionKey, &ConnectionGene> = BTreeMap::new();
+
+    for connection in genome.connections() {
+        let key = (connection.from, connection.to);
+
+        match connections.get(&key) {
+            Some(existing) if !existing.disabled => {}
+            _ => {
+                connections.insert(key, connection);
+            }
+        }
+    }
+
+    connections
+}
+
+fn changed<T: Clone + PartialEq>(old: &T, new: &T) -> Option<(T, T)> {
+    if old == new {
+        None
+    } else {
+        Some((old.clone(), new.clone()))
+    }
+}
+
+pub fn diff(first: &Genome, second: &Genome) -> GenomeDiff {
+    let mut result = GenomeDiff::default();
+
+    let first_connections = connections_by_key(first);
+    let second_connections = connections_by_key(second);
+
+    for (key, connection) in &first_connections {
+        match second_connections.get(key) {
+            None => result.removed_connections.push(*key),
+            Some(other) => {
+                if !connection.disabled && other.disabled {
+                    result.disabled_connections.push(*key);
+                } else if connection.disabled && !other.disabled {
+                    result.enabled_connections.push(*key);
+                }
+
+                if connection.weight != other.weight {
+                    result
+                        .changed_weights
+                        .push((*key, connection.weight, other.weight));
+                }
+            }
+        }
+    }
+
+    result.added_connections = second_connections
+        .keys()
+        .filter(|key| !first_connections.contains_key(key))
+        .cloned()
+        .collect();
+
+    let common_nodes = usize::min(first.nodes().len(), second.nodes().len());
+
+    result.removed_nodes = (common_nodes..first.nodes().len()).collect();
+    result.added_nodes = (common_nodes..second.nodes().len()).collect();
+    result.changed_nodes = first
+        .nodes()
+        .iter()
+        .zip(second.nodes())
+        .enumerate()
+        .map(|(index, (old, new))| NodeChange {
+            index,
+            bias: changed(&old.bias, &new.bias),
+            activation: changed(&old.activation, &new.activation),
+            aggregation: changed(&old.aggregation, &new.aggregation),
+        })
+        .filter(|change| {
+            change.bias.is_some() || change.activation.is_some() || change.aggregation.is_some()
+        })
+        .collect();
+
+    result
+}
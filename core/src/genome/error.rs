@@ -0,0 +1,108 @@
+use std::fmt;
+
+/// A structural problem of a genome
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenomeError {
+    /// The connection at the given index references a node that doesn't exist
+    NodeOutOfRange { connection: usize, node: usize },
+    /// The enabled connection at the given index targets an input node
+    ConnectionToInput { connection: usize },
+    /// The enabled connection at the given index targets a constant node
+    ConnectionToConstant { connection: usize },
+    /// The enabled feed forward connection at the given index originates from an output node
+    ConnectionFromOutput { connection: usize },
+    /// The enabled feed forward connections form a cycle
+    Cycle,
+    /// There is more than one enabled connection between the same nodes
+    DuplicateConnection { from: usize, to: usize },
+    /// The number of input nodes doesn't match the input count of the genome
+    InputCountMismatch { expected: usize, found: usize },
+    /// The number of output nodes doesn't match the output count of the genome
+    OutputCountMismatch { expected: usize, found: usize },
+    /// A connection from the first node to the second one can't be added, it would target an
+    /// input or a constant, originate from an output or form a cycle, recurrent connections can't
+    /// be added where a feed forward one would do
+    CannotConnect { from: usize, to: usize },
+    /// There is no connection from the first node to the second one
+    NoSuchConnection { from: usize, to: usize },
+    /// There is no node at the given index
+    NoSuchNode(usize),
+}
+
+impl fmt::Display for GenomeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenomeError::NodeOutOfRange { connection, node } => write!(
+                f,
+                "connection {} references node {} which doesn't exist",
+                connection, node
+            ),
+            GenomeError::ConnectionToInput { connection } => {
+                write!(f, "connection {} targets an input node", connection)
+            }
+            GenomeError::ConnectionToConstant { connection } => {
+                write!(f, "connection {} targets a constant node", connection)
+            }
+            GenomeError::ConnectionFromOutput { connection } => {
+                write!(
+                    f,
+                    "connection {} originates from an output node",
+                    connection
+                )
+            }
+            GenomeError::Cycle => write!(f, "feed forward connections form a cycle"),
+            GenomeError::DuplicateConnection { from, to } => write!(
+                f,
+                "there is more than one enabled connection from {} to {}",
+                from, to
+            ),
+            GenomeError::InputCountMismatch { expected, found } => {
+                write!(f, "expected {} input nodes but found {}", expected, found)
+            }
+            GenomeError::OutputCountMismatch { expected, found } => {
+                write!(f, "expected {} output nodes but found {}", expected, found)
+            }
+            GenomeError::CannotConnect { from, to } => {
+                write!(f, "node {} can't be connected to node {}", from, to)
+            }
+            GenomeError::NoSuchConnection { from, to } => {
+                write!(f, "there is no connection from {} to {}", from, to)
+            }
+            GenomeError::NoSuchNode(index) => write!(f, "there is no node {}", index),
+        }
+    }
+}
+
+impl std::error::Error for GenomeError {}
+
+/// A genome text `Genome::decode` can't read
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// There are no input or no output nodes in the node counts
+    MissingCounts,
+    /// The token isn't a node count, a connection or a node the way the format writes them
+    InvalidToken(String),
+    /// The node listed with its activation and bias doesn't exist or is an input node
+    UnknownNode(usize),
+    /// The text describes a genome that isn't valid
+    Invalid(Vec<GenomeError>),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::MissingCounts => write!(f, "the input and output node counts are missing"),
+            ParseError::InvalidToken(token) => write!(f, "can't read '{}'", token),
+            ParseError::UnknownNode(index) => {
+                write!(f, "node {} doesn't exist or is an input node", index)
+            }
+            ParseError::Invalid(errors) => {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+
+                write!(f, "the genome isn't valid, {}", errors.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
@@ -0,0 +1,138 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::Genome;
+
+/// Returns the innovation number a connection of a starting or hand built topology gets outside
+/// of an evolution, it only depends on the connection's nodes so genomes built apart from each
+/// other still line up in crossover and distance
+pub fn topology_innovation(from: usize, to: usize, recurrent: bool) -> usize {
+    let pair = (from + to) * (from + to + 1) / 2 + to;
+
+    pair * 2 + recurrent as usize
+}
+
+/// Hands out the innovation numbers of one evolution, each `NEAT` run has its own
+#[derive(Debug, Default)]
+pub struct Innovations {
+    next: usize,
+    /// Innovation numbers of connections that are part of the starting population
+    topology: BTreeMap<(usize, usize, bool), usize>,
+}
+
+impl Innovations {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Returns an innovation number no other connection of the evolution has received so far
+    pub fn next_innovation(&mut self) -> usize {
+        self.next += 1;
+
+        self.next - 1
+    }
+
+    /// Numbers the connections of a genome joining the evolution, every genome uses the same
+    /// number for the same connection of its starting topology
+    pub fn register(&mut self, genome: &mut Genome) {
+        genome.connection_genes.iter_mut().for_each(|connection| {
+            let key = (connection.from, connection.to, connection.recurrent);
+            connection.innovation = match self.topology.get(&key) {
+                Some(innovation) => *innovation,
+                None => {
+                    let innovation = self.next_innovation();
+                    self.topology.insert(key, innovation);
+
+                    innovation
+                }
+            };
+        });
+    }
+
+    /// Gives new innovation numbers to the connections structural mutations added to one
+    /// generation's offspring, those come after the `known` count of connections of each genome.
+    /// Identical connections that appeared in different genomes share the number
+    #[cfg_attr(not(feature = "training"), allow(dead_code))]
+    pub(crate) fn number_offspring(&mut self, genomes: &mut [Genome], known: &[usize]) {
+        let mut generation_innovations: HashMap<(usize, usize, bool), usize> = HashMap::new();
+
+        genomes
+            .iter_mut()
+            .zip(known)
+            .flat_map(|(genome, known)| genome.connection_genes.iter_mut().skip(*known))
+            .for_each(|connection| {
+                let key = (connection.from, connection.to, connection.recurrent);
+                connection.innovation = match generation_innovations.get(&key) {
+                    Some(innovation) => *innovation,
+                    None => {
+                        let innovation = self.next_innovation();
+                        generation_innovations.insert(key, innovation);
+
+                        innovation
+                    }
+                };
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mutations::MutationKind;
+
+    #[test]
+    fn mutations_get_new_innovations() {
+        let mut g = Genome::new(2, 1);
+        let initial: Vec<usize> = g.connections().iter().map(|c| c.innovation).collect();
+
+        g.mutate(&MutationKind::AddNode);
+
+        let added: Vec<usize> = g
+            .connections()
+            .iter()
+            .map(|c| c.innovation)
+            .filter(|i| !initial.contains(i))
+            .collect();
+
+        assert_eq!(added.len(), 2);
+        assert!(added.iter().all(|i| *i > *initial.iter().max().unwrap()));
+    }
+
+    #[test]
+    fn same_mutation_in_one_generation_is_unified() {
+        let mut innovations = Innovations::new();
+        let mut genomes = vec![Genome::new(2, 1), Genome::new(2, 1)];
+        genomes.iter_mut().for_each(|g| innovations.register(g));
+        let known: Vec<usize> = genomes.iter().map(|g| g.connections().len()).collect();
+
+        genomes.iter_mut().for_each(|g| {
+            let hidden = g.add_node();
+            g.add_connection(0, hidden).unwrap();
+        });
+        genomes[1].add_connection(1, 3).unwrap();
+
+        innovations.number_offspring(&mut genomes, &known);
+
+        let added = |g: &Genome, i: usize| g.connections()[i].innovation;
+        assert_eq!(added(&genomes[0], 2), added(&genomes[1], 2));
+        assert!(added(&genomes[0], 2) > added(&genomes[0], 1));
+        assert_ne!(added(&genomes[1], 3), added(&genomes[1], 2));
+    }
+
+    #[test]
+    fn evolutions_number_connections_independently() {
+        let mut first = Innovations::new();
+        let mut second = Innovations::new();
+
+        let mut a = Genome::new(2, 1);
+        let mut b = Genome::new(3, 2);
+        first.register(&mut b);
+        first.register(&mut a);
+        let numbered_first: Vec<usize> = a.connections().iter().map(|c| c.innovation).collect();
+
+        second.register(&mut a);
+        let numbered_second: Vec<usize> = a.connections().iter().map(|c| c.innovation).collect();
+
+        assert_eq!(numbered_first, vec![6, 7]);
+        assert_eq!(numbered_second, vec![0, 1]);
+    }
+}
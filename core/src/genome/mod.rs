@@ -1,15 +1,29 @@
+use num_traits::Float;
+use rand::{thread_rng, Rng};
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 use uuid::Uuid;
 
+use crate::activation::ActivationKind;
 use crate::mutations::MutationKind;
+use crate::network::Network;
 use crate::node::NodeKind;
+use crate::{Configuration, InitialConnectivity};
+pub use builder::GenomeBuilder;
 pub use connection::ConnectionGene;
 pub use crossover::*;
+pub use diff::{ConnectionKey, GenomeDiff, NodeChange};
+pub use error::{GenomeError, ParseError};
 pub use node::NodeGene;
 
-pub mod connection;
+mod builder;
+mod connection;
 pub mod crossover;
-pub mod node;
+mod diff;
+mod error;
+pub mod innovation;
+mod node;
+mod text;
 
 pub type GenomeId = Uuid;
 
@@ -20,39 +34,120 @@ pub struct Genome {
     outputs: usize,
     connection_genes: Vec<ConnectionGene>,
     node_genes: Vec<NodeGene>,
+    bias_input: bool,
 }
 
 impl Genome {
     pub fn new(inputs: usize, outputs: usize) -> Self {
-        let mut node_genes = vec![];
+        Genome::new_with_rng(inputs, outputs, &mut thread_rng())
+    }
 
-        (0..inputs).for_each(|_| node_genes.push(NodeGene::new(NodeKind::Input)));
-        (0..outputs).for_each(|_| node_genes.push(NodeGene::new(NodeKind::Output)));
+    pub fn new_with_rng<R: Rng + ?Sized>(inputs: usize, outputs: usize, rng: &mut R) -> Self {
+        Genome::new_with_connectivity(inputs, outputs, &InitialConnectivity::FullyConnected, rng)
+    }
 
+    /// Creates a genome of input and output nodes connected as the connectivity describes
+    pub fn new_with_connectivity<R: Rng + ?Sized>(
+        inputs: usize,
+        outputs: usize,
+        connectivity: &InitialConnectivity,
+        rng: &mut R,
+    ) -> Self {
+        let node_genes = (0..inputs)
+            .map(|_| NodeKind::Input)
+            .chain((0..outputs).map(|_| NodeKind::Output))
+            .map(|kind| NodeGene::new_with_rng(kind, rng))
+            .collect();
+
+        Genome::new_with_nodes(inputs, outputs, node_genes, connectivity, rng)
+    }
+
+    /// Creates a starting genome the way the configuration describes, the bias input is added
+    /// after the given inputs
+    pub fn new_with_configuration<R: Rng + ?Sized>(
+        inputs: usize,
+        outputs: usize,
+        configuration: &Configuration,
+        rng: &mut R,
+    ) -> Self {
+        let inputs = inputs + configuration.add_bias_input as usize;
+        let node_genes = (0..inputs)
+            .map(|_| NodeKind::Input)
+            .chain((0..outputs).map(|_| NodeKind::Output))
+            .map(|kind| NodeGene::new_with_configuration(kind, configuration, rng))
+            .collect();
+
+        let mut genome = Genome::new_with_nodes(
+            inputs,
+            outputs,
+            node_genes,
+            &configuration.initial_connectivity,
+            rng,
+        );
+        genome.bias_input = configuration.add_bias_input;
+
+        genome
+    }
+
+    fn new_with_nodes<R: Rng + ?Sized>(
+        inputs: usize,
+        outputs: usize,
+        node_genes: Vec<NodeGene>,
+        connectivity: &InitialConnectivity,
+        rng: &mut R,
+    ) -> Self {
         let connection_genes: Vec<ConnectionGene> = (0..inputs)
             .flat_map(|i| {
                 (inputs..inputs + outputs)
-                    .map(|o| ConnectionGene::new(i, o))
+                    .filter(|_| match connectivity {
+                        InitialConnectivity::FullyConnected => true,
+                        InitialConnectivity::Unconnected => false,
+                        InitialConnectivity::PartialRandom(probability) => {
+                            rng.gen::<f64>() < *probability
+                        }
+                    })
+                    .collect::<Vec<usize>>()
+                    .into_iter()
+                    .map(|o| ConnectionGene::new_with_rng(i, o, rng))
                     .collect::<Vec<ConnectionGene>>()
             })
             .collect();
 
         Genome {
-            id: Uuid::new_v4(),
+            id: random_id(rng),
             inputs,
             outputs,
             connection_genes,
             node_genes,
+            bias_input: false,
         }
     }
 
+    /// Starts building a genome by hand from unconnected input and output nodes
+    pub fn builder(inputs: usize, outputs: usize) -> GenomeBuilder {
+        GenomeBuilder::new(inputs, outputs)
+    }
+
+    /// Rebuilds a genome from a network so an exported network can keep evolving, disabled
+    /// connections aren't part of a network so they are lost
+    pub fn from_network<T: Float>(network: &Network<T>) -> Self {
+        let mut genome = Genome::empty(network.input_count, network.output_count);
+
+        genome.node_genes = network.nodes.iter().map(From::from).collect();
+        genome.connection_genes = network.connections().iter().map(From::from).collect();
+        genome.bias_input = network.bias_input;
+
+        genome
+    }
+
     fn empty(inputs: usize, outputs: usize) -> Self {
         Genome {
-            id: Uuid::new_v4(),
+            id: random_id(&mut thread_rng()),
             inputs,
             outputs,
             connection_genes: vec![],
             node_genes: vec![],
+            bias_input: false,
         }
     }
 
@@ -67,6 +162,12 @@ impl Genome {
         self.id
     }
 
+    /// Gives the genome a new id so copies of it can live in the same population
+    #[cfg_attr(not(feature = "training"), allow(dead_code))]
+    pub(crate) fn renew_id<R: Rng + ?Sized>(&mut self, rng: &mut R) {
+        self.id = random_id(rng);
+    }
+
     pub fn input_count(&self) -> usize {
         self.inputs
     }
@@ -75,6 +176,11 @@ impl Genome {
         self.outputs
     }
 
+    /// Checks if the last input is the bias input, `input_count` includes it
+    pub fn has_bias_input(&self) -> bool {
+        self.bias_input
+    }
+
     pub fn nodes(&self) -> &[NodeGene] {
         &self.node_genes
     }
@@ -87,6 +193,23 @@ impl Genome {
         &self.connection_genes
     }
 
+    /// Number of connection genes that are not disabled
+    pub fn enabled_connection_count(&self) -> usize {
+        self.connection_genes.iter().filter(|c| !c.disabled).count()
+    }
+
+    pub fn hidden_node_count(&self) -> usize {
+        self.node_genes
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Hidden))
+            .count()
+    }
+
+    /// Size of the genome as the number of nodes plus the number of enabled connections
+    pub fn complexity(&self) -> usize {
+        self.node_genes.len() + self.enabled_connection_count()
+    }
+
     pub fn connection_mut(&mut self, index: usize) -> Option<&mut ConnectionGene> {
         self.connection_genes.get_mut(index)
     }
@@ -95,10 +218,11 @@ impl Genome {
         &self,
         additional_connections: Option<Vec<ConnectionGene>>,
     ) -> Option<Vec<usize>> {
+        // Recurrent connections read values from the previous pass so they don't affect the order
         let mut connections: Vec<ConnectionGene> = self
             .connection_genes
             .iter()
-            .filter(|c| !c.disabled)
+            .filter(|c| !c.disabled && !c.recurrent)
             .cloned()
             .collect();
 
@@ -106,10 +230,6 @@ impl Genome {
             connections.append(&mut conns);
         }
 
-        if connections.is_empty() {
-            return None;
-        }
-
         let mut visited: Vec<usize> = vec![];
 
         // Input nodes are automatically visited as they get their values from inputs
@@ -163,22 +283,35 @@ impl Genome {
         self.calculate_node_order(Some(additional_connections))
     }
 
-    fn calculate_node_distance_from_inputs(&self) -> HashMap<usize, usize> {
-        // Inputs are immediately added with distance of 0
+    /// Number of layers between the inputs and the furthest node, disabled and recurrent
+    /// connections excluded
+    pub fn depth(&self) -> usize {
+        let edges: Vec<(usize, usize)> = self
+            .connection_genes
+            .iter()
+            .filter(|c| !c.disabled && !c.recurrent)
+            .map(|c| (c.from, c.to))
+            .collect();
+
+        feed_forward_depth(self.node_genes.iter().map(|n| &n.kind), &edges)
+    }
+
+    pub(crate) fn calculate_node_distance_from_inputs(&self) -> HashMap<usize, usize> {
+        // Inputs and constants are immediately added with distance of 0
         let mut distances: HashMap<usize, usize> = self
             .nodes()
             .iter()
             .enumerate()
-            .filter(|(_, n)| matches!(n.kind, NodeKind::Input))
+            .filter(|(_, n)| matches!(n.kind, NodeKind::Input | NodeKind::Constant))
             .map(|(i, _)| (i, 0))
             .collect();
 
-        // Inputs need to be visited first
+        // Inputs and constants need to be visited first
         let mut to_visit: VecDeque<usize> = self
             .nodes()
             .iter()
             .enumerate()
-            .filter(|(_, n)| matches!(n.kind, NodeKind::Input))
+            .filter(|(_, n)| matches!(n.kind, NodeKind::Input | NodeKind::Constant))
             .map(|(i, _)| i)
             .collect();
 
@@ -187,7 +320,7 @@ impl Genome {
 
             self.connections()
                 .iter()
-                .filter(|c| c.from == i)
+                .filter(|c| c.from == i && !c.recurrent)
                 .for_each(|c| {
                     let node_index = c.to;
                     let potential_distance = source_distance + 1;
@@ -216,10 +349,11 @@ impl Genome {
     fn is_projecting_directly(&self, source: usize, target: usize) -> bool {
         self.connection_genes
             .iter()
-            .filter(|c| !c.disabled)
+            .filter(|c| !c.disabled && !c.recurrent)
             .any(|c| c.from == source && c.to == target)
     }
 
+    #[cfg(test)]
     fn is_projected_directly(&self, target: usize, source: usize) -> bool {
         self.is_projecting_directly(source, target)
     }
@@ -239,7 +373,9 @@ impl Genome {
             } else {
                 self.connection_genes
                     .iter()
-                    .filter(|c| c.from == i && !c.disabled && !visited_nodes.contains(&i))
+                    .filter(|c| {
+                        c.from == i && !c.disabled && !c.recurrent && !visited_nodes.contains(&i)
+                    })
                     .for_each(|c| nodes_to_visit.push_back(c.to));
             }
         }
@@ -247,52 +383,140 @@ impl Genome {
         projecting
     }
 
-    fn is_projected(&self, target: usize, source: usize) -> bool {
-        self.is_projecting(source, target)
-    }
-
     pub fn can_connect(&self, from: usize, to: usize) -> bool {
         let from_node = self.node_genes.get(from).unwrap();
         let to_node = self.node_genes.get(to).unwrap();
 
         let is_from_output = matches!(from_node.kind, NodeKind::Output);
-        let is_to_input = matches!(to_node.kind, NodeKind::Input);
+        let is_to_input = matches!(to_node.kind, NodeKind::Input | NodeKind::Constant);
 
         let distances = self.calculate_node_distance_from_inputs();
-        let from_distance = distances.get(&from).unwrap();
+        // Nodes only reachable through recurrent connections behave like they are next to inputs
+        let from_distance = distances.get(&from).unwrap_or(&0);
         let to_distance = distances.get(&to).unwrap_or(&usize::MAX);
         let is_recurrent = from_distance > to_distance;
 
-        if is_from_output || is_to_input || is_recurrent {
+        // Distances are calculated through disabled connections too, a cycle would never settle
+        let forms_cycle = from == to || crossover::is_reachable(&self.connection_genes, to, from);
+
+        if is_from_output || is_to_input || is_recurrent || forms_cycle {
             false
         } else {
             !self.is_projecting(from, to)
         }
     }
 
-    pub fn add_connection(&mut self, from: usize, to: usize) -> Result<usize, ()> {
+    /// Checks if a recurrent connection can be added, that is only the case for connections which
+    /// would form a cycle if they were feed forward
+    pub fn can_connect_recurrent(&self, from: usize, to: usize) -> bool {
+        let to_node = self.node_genes.get(to).unwrap();
+
+        if matches!(to_node.kind, NodeKind::Input | NodeKind::Constant) {
+            return false;
+        }
+
+        let is_duplicate = self
+            .connection_genes
+            .iter()
+            .any(|c| c.from == from && c.to == to && c.recurrent && !c.disabled);
+
+        !is_duplicate
+            && self
+                .node_order_with(vec![ConnectionGene::new(from, to)])
+                .is_none()
+    }
+
+    pub fn add_connection(&mut self, from: usize, to: usize) -> Result<usize, GenomeError> {
+        self.add_connection_with_rng(from, to, &mut thread_rng())
+    }
+
+    pub fn add_connection_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        from: usize,
+        to: usize,
+        rng: &mut R,
+    ) -> Result<usize, GenomeError> {
         if !self.can_connect(from, to) {
-            return Err(());
+            return Err(GenomeError::CannotConnect { from, to });
+        }
+
+        let maybe_connection = self
+            .connection_genes
+            .iter_mut()
+            .find(|c| c.from == from && c.to == to && !c.recurrent);
+
+        if let Some(conn) = maybe_connection {
+            conn.disabled = false;
+        } else {
+            let innovation = self.next_innovation();
+            self.connection_genes
+                .push(ConnectionGene::with_innovation(from, to, innovation, rng));
+        }
+
+        Ok(self.connection_genes.len() - 1)
+    }
+
+    /// Returns an innovation number past every number of the genome for a connection added by a
+    /// structural mutation, an evolution numbers those again against its other genomes
+    fn next_innovation(&self) -> usize {
+        self.connection_genes
+            .iter()
+            .map(|c| c.innovation + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    pub fn add_recurrent_connection(
+        &mut self,
+        from: usize,
+        to: usize,
+    ) -> Result<usize, GenomeError> {
+        self.add_recurrent_connection_with_rng(from, to, &mut thread_rng())
+    }
+
+    pub fn add_recurrent_connection_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        from: usize,
+        to: usize,
+        rng: &mut R,
+    ) -> Result<usize, GenomeError> {
+        if !self.can_connect_recurrent(from, to) {
+            return Err(GenomeError::CannotConnect { from, to });
         }
 
         let maybe_connection = self
             .connection_genes
             .iter_mut()
-            .find(|c| c.from == from && c.to == to);
+            .find(|c| c.from == from && c.to == to && c.recurrent);
 
-        if let Some(mut conn) = maybe_connection {
+        if let Some(conn) = maybe_connection {
             conn.disabled = false;
         } else {
-            self.connection_genes.push(ConnectionGene::new(from, to));
+            let innovation = self.next_innovation();
+            let mut connection = ConnectionGene::with_innovation(from, to, innovation, rng);
+            connection.recurrent = true;
+
+            self.connection_genes.push(connection);
         }
 
         Ok(self.connection_genes.len() - 1)
     }
 
-    pub fn add_many_connections(&mut self, params: &[(usize, usize)]) -> Vec<Result<usize, ()>> {
+    pub fn add_many_connections(
+        &mut self,
+        params: &[(usize, usize)],
+    ) -> Vec<Result<usize, GenomeError>> {
+        self.add_many_connections_with_rng(params, &mut thread_rng())
+    }
+
+    pub fn add_many_connections_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        params: &[(usize, usize)],
+        rng: &mut R,
+    ) -> Vec<Result<usize, GenomeError>> {
         let results = params
             .iter()
-            .map(|(from, to)| self.add_connection(*from, *to))
+            .map(|(from, to)| self.add_connection_with_rng(*from, *to, rng))
             .collect();
 
         results
@@ -306,19 +530,390 @@ impl Genome {
         indexes.iter().for_each(|i| self.disable_connection(*i));
     }
 
+    /// Freezes every connection from `from` to `to` so mutations leave them as they are, fails
+    /// when there is no such connection
+    pub fn freeze_connection(&mut self, from: usize, to: usize) -> Result<(), GenomeError> {
+        let mut found = false;
+
+        self.connection_genes
+            .iter_mut()
+            .filter(|c| c.from == from && c.to == to)
+            .for_each(|c| {
+                c.frozen = true;
+                found = true;
+            });
+
+        if found {
+            Ok(())
+        } else {
+            Err(GenomeError::NoSuchConnection { from, to })
+        }
+    }
+
+    /// Freezes the node so mutations leave it as it is, fails when there is no such node
+    pub fn freeze_node(&mut self, index: usize) -> Result<(), GenomeError> {
+        let node = self
+            .node_genes
+            .get_mut(index)
+            .ok_or(GenomeError::NoSuchNode(index))?;
+        node.frozen = true;
+
+        Ok(())
+    }
+
     /// Add a new hidden node to the genome
     pub fn add_node(&mut self) -> usize {
+        self.add_node_with_rng(&mut thread_rng())
+    }
+
+    pub fn add_node_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize {
+        let index = self.node_genes.len();
+        self.node_genes
+            .push(NodeGene::new_with_rng(NodeKind::Hidden, rng));
+
+        index
+    }
+
+    /// Adds a hidden node whose activation and aggregation are among the configured allowed ones
+    pub fn add_node_with_configuration<R: Rng + ?Sized>(
+        &mut self,
+        configuration: &Configuration,
+        rng: &mut R,
+    ) -> usize {
+        let index = self.node_genes.len();
+        self.node_genes.push(NodeGene::new_with_configuration(
+            NodeKind::Hidden,
+            configuration,
+            rng,
+        ));
+
+        index
+    }
+
+    /// Writes the genome on one line like `I2 O1 H1 | 0-3:0.5 3-2:-0.2* | 2:Tanh:0.1 3:Relu:0:Max`
+    ///
+    /// The first section counts consecutive nodes of each kind, the second lists the connections
+    /// where `~` marks recurrent ones and `*` disabled ones, the third one gives every non input
+    /// node its activation, bias and aggregation when it isn't `Sum`
+    pub fn encode(&self) -> String {
+        text::encode(self)
+    }
+
+    /// Reads a genome written by `encode` or by hand, the node section is optional and nodes
+    /// missing from it get the identity activation, the sum aggregation and no bias
+    pub fn decode(text: &str) -> Result<Genome, ParseError> {
+        text::decode(text)
+    }
+
+    /// Lists every gene that changed on the way from this genome to the other one, connections
+    /// are matched by the nodes they connect and nodes by their index
+    pub fn diff(&self, other: &Genome) -> GenomeDiff {
+        diff::diff(self, other)
+    }
+
+    /// Checks the structural invariants of the genome and returns every violation found
+    pub fn validate(&self) -> Result<(), Vec<GenomeError>> {
+        let mut errors = vec![];
+        let node_count = self.node_genes.len();
+
+        let input_count = self
+            .node_genes
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Input))
+            .count();
+        if input_count != self.inputs {
+            errors.push(GenomeError::InputCountMismatch {
+                expected: self.inputs,
+                found: input_count,
+            });
+        }
+
+        let output_count = self
+            .node_genes
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Output))
+            .count();
+        if output_count != self.outputs {
+            errors.push(GenomeError::OutputCountMismatch {
+                expected: self.outputs,
+                found: output_count,
+            });
+        }
+
+        let mut in_range = true;
+        let mut seen_connections: HashSet<(usize, usize, bool)> = HashSet::new();
+
+        for (index, connection) in self.connection_genes.iter().enumerate() {
+            if connection.from >= node_count || connection.to >= node_count {
+                in_range = false;
+                errors.push(GenomeError::NodeOutOfRange {
+                    connection: index,
+                    node: usize::max(connection.from, connection.to),
+                });
+                continue;
+            }
+
+            if connection.disabled {
+                continue;
+            }
+
+            if matches!(self.node_genes[connection.to].kind, NodeKind::Input) {
+                errors.push(GenomeError::ConnectionToInput { connection: index });
+            }
+            if matches!(self.node_genes[connection.to].kind, NodeKind::Constant) {
+                errors.push(GenomeError::ConnectionToConstant { connection: index });
+            }
+            // Recurrent connections may feed outputs back into the network
+            if matches!(self.node_genes[connection.from].kind, NodeKind::Output)
+                && !connection.recurrent
+            {
+                errors.push(GenomeError::ConnectionFromOutput { connection: index });
+            }
+            if !seen_connections.insert((connection.from, connection.to, connection.recurrent)) {
+                errors.push(GenomeError::DuplicateConnection {
+                    from: connection.from,
+                    to: connection.to,
+                });
+            }
+        }
+
+        // Out of range connections make the order meaningless
+        let has_feed_forward = self
+            .connection_genes
+            .iter()
+            .any(|c| !c.disabled && !c.recurrent);
+        if in_range && has_feed_forward && self.node_order().is_none() {
+            errors.push(GenomeError::Cycle);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Sets the activation of every output node
+    pub fn set_output_activation(&mut self, activation: ActivationKind) {
+        self.node_genes
+            .iter_mut()
+            .filter(|n| matches!(n.kind, NodeKind::Output))
+            .for_each(|n| n.activation = activation.clone());
+    }
+
+    /// Removes hidden and constant nodes that have no path to an output and every connection that
+    /// can't affect the outputs, the network built from the genome computes the same outputs.
+    /// Evolution leaves such nodes behind and they only slow the network down, so it's worth
+    /// pruning the best genome before using or saving its network
+    pub fn prune(&mut self) {
+        // Walk back from the outputs, recurrent connections carry values to the outputs as well
+        let mut useful: HashSet<usize> = HashSet::new();
+        let mut to_visit: Vec<usize> = self
+            .node_genes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| matches!(n.kind, NodeKind::Output))
+            .map(|(i, _)| i)
+            .collect();
+
+        while let Some(i) = to_visit.pop() {
+            if useful.insert(i) {
+                self.connection_genes
+                    .iter()
+                    .filter(|c| c.to == i && !c.disabled)
+                    .for_each(|c| to_visit.push(c.from));
+            }
+        }
+
+        self.connection_genes
+            .retain(|c| !c.disabled && useful.contains(&c.from) && useful.contains(&c.to));
+        self.retain_nodes(|i, n| {
+            matches!(n.kind, NodeKind::Input | NodeKind::Output) || useful.contains(&i)
+        });
+    }
+
+    /// Removes hidden nodes without any enabled connection, those are left behind by the remove
+    /// node mutation which only disables connections, the remaining nodes are renumbered once and
+    /// connections keep their innovation numbers as those don't depend on node indexes
+    pub fn compact(&mut self) {
+        let dead_nodes: HashSet<usize> = self
+            .node_genes
+            .iter()
+            .enumerate()
+            .filter(|(i, n)| {
+                matches!(n.kind, NodeKind::Hidden)
+                    && !self
+                        .connection_genes
+                        .iter()
+                        .any(|c| !c.disabled && (c.from == *i || c.to == *i))
+            })
+            .map(|(i, _)| i)
+            .collect();
+
+        self.retain_nodes(|i, _| !dead_nodes.contains(&i));
+    }
+
+    /// Keeps the nodes the predicate accepts, connections of removed nodes are removed as well and
+    /// the rest are pointed at the new node indexes
+    fn retain_nodes<F: Fn(usize, &NodeGene) -> bool>(&mut self, keep: F) {
+        let new_indexes: HashMap<usize, usize> = self
+            .node_genes
+            .iter()
+            .enumerate()
+            .filter(|(i, n)| keep(*i, n))
+            .enumerate()
+            .map(|(new_index, (old_index, _))| (old_index, new_index))
+            .collect();
+
+        self.connection_genes
+            .retain(|c| new_indexes.contains_key(&c.from) && new_indexes.contains_key(&c.to));
+        self.connection_genes.iter_mut().for_each(|c| {
+            c.from = new_indexes[&c.from];
+            c.to = new_indexes[&c.to];
+        });
+
+        let mut old_index = 0;
+        self.node_genes.retain(|_| {
+            let keep = new_indexes.contains_key(&old_index);
+            old_index += 1;
+            keep
+        });
+    }
+
+    /// Add a new constant node to the genome, it outputs its bias regardless of the inputs
+    pub fn add_constant_node(&mut self) -> usize {
+        self.add_constant_node_with_rng(&mut thread_rng())
+    }
+
+    pub fn add_constant_node_with_rng<R: Rng + ?Sized>(&mut self, rng: &mut R) -> usize {
         let index = self.node_genes.len();
-        self.node_genes.push(NodeGene::new(NodeKind::Hidden));
+        self.node_genes
+            .push(NodeGene::new_with_rng(NodeKind::Constant, rng));
 
         index
     }
 
     pub fn mutate(&mut self, kind: &MutationKind) {
-        crate::mutations::mutate(kind, self);
+        self.mutate_with_rng(kind, &Default::default(), &mut thread_rng());
+    }
+
+    pub fn mutate_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        kind: &MutationKind,
+        configuration: &Configuration,
+        rng: &mut R,
+    ) {
+        crate::mutations::mutate(kind, self, configuration, rng);
+    }
+}
+
+/// Summarizes the topology like `2 inputs, 1 output, 3 hidden, 7 enabled connections, depth 3`
+impl fmt::Display for Genome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let enabled = self.connection_genes.iter().filter(|c| !c.disabled).count();
+
+        write_topology_summary(
+            f,
+            self.node_genes.iter().map(|n| &n.kind),
+            enabled,
+            self.depth(),
+        )
+    }
+}
+
+/// Writes the node counts by kind, constant nodes only when there are some, then the number of
+/// enabled connections and the depth
+pub(crate) fn write_topology_summary<'a, I>(
+    f: &mut fmt::Formatter,
+    kinds: I,
+    enabled_connections: usize,
+    depth: usize,
+) -> fmt::Result
+where
+    I: Iterator<Item = &'a NodeKind>,
+{
+    let (mut inputs, mut outputs, mut hidden, mut constants) = (0, 0, 0, 0);
+    kinds.for_each(|kind| match kind {
+        NodeKind::Input => inputs += 1,
+        NodeKind::Output => outputs += 1,
+        NodeKind::Hidden => hidden += 1,
+        NodeKind::Constant => constants += 1,
+    });
+    let counted = |count: usize, noun: &str| {
+        format!("{} {}{}", count, noun, if count == 1 { "" } else { "s" })
+    };
+
+    write!(
+        f,
+        "{}, {}, {} hidden",
+        counted(inputs, "input"),
+        counted(outputs, "output"),
+        hidden
+    )?;
+
+    if constants > 0 {
+        write!(f, ", {}", counted(constants, "constant"))?;
+    }
+
+    write!(
+        f,
+        ", {}, depth {}",
+        counted(enabled_connections, "enabled connection"),
+        depth
+    )
+}
+
+impl<T: Float> From<&Network<T>> for Genome {
+    fn from(network: &Network<T>) -> Self {
+        Genome::from_network(network)
     }
 }
 
+/// Length of the longest path of edges starting at an input or a constant, nodes that can't be
+/// reached from them don't count and edges in a cycle are never followed
+pub(crate) fn feed_forward_depth<'a, I>(kinds: I, edges: &[(usize, usize)]) -> usize
+where
+    I: Iterator<Item = &'a NodeKind>,
+{
+    let mut layers: Vec<Option<usize>> = kinds
+        .map(|kind| match kind {
+            NodeKind::Input | NodeKind::Constant => Some(0),
+            _ => None,
+        })
+        .collect();
+
+    // Nodes are visited once every edge into them was followed
+    let mut incoming_count = vec![0; layers.len()];
+    edges.iter().for_each(|(_, to)| incoming_count[*to] += 1);
+    let mut ready: Vec<usize> = (0..layers.len())
+        .filter(|i| incoming_count[*i] == 0)
+        .collect();
+
+    while let Some(i) = ready.pop() {
+        for (_, to) in edges.iter().filter(|(from, _)| *from == i) {
+            if let Some(layer) = layers[i] {
+                layers[*to] = Some(layers[*to].map_or(layer + 1, |l| l.max(layer + 1)));
+            }
+
+            incoming_count[*to] -= 1;
+            if incoming_count[*to] == 0 {
+                ready.push(*to);
+            }
+        }
+    }
+
+    layers.into_iter().flatten().max().unwrap_or(0)
+}
+
+/// Generates a version 4 uuid from the given random number generator so seeded runs get the same
+/// genome ids
+fn random_id<R: Rng + ?Sized>(rng: &mut R) -> GenomeId {
+    uuid::Builder::from_bytes(rng.gen())
+        .set_variant(uuid::Variant::RFC4122)
+        .set_version(uuid::Version::Random)
+        .build()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -486,7 +1081,86 @@ mod tests {
         g.add_connection(4, 3).unwrap();
         g.add_connection(5, 4).unwrap();
 
-        assert!(g.add_connection(3, 5).is_err());
+        assert_eq!(
+            g.add_connection(3, 5),
+            Err(GenomeError::CannotConnect { from: 3, to: 5 })
+        );
+    }
+
+    #[test]
+    fn diff_lists_changed_genes() {
+        let mut builder = Genome::builder(2, 1);
+        builder.connect(0, 2, 0.5).connect(1, 2, 0.5);
+        let before = builder.build().unwrap();
+        let mut after = before.clone();
+
+        assert!(before.diff(&after).is_empty());
+
+        after.connection_mut(0).unwrap().weight = -0.5;
+        after.node_mut(2).unwrap().bias = 0.25;
+        after.node_mut(2).unwrap().activation = ActivationKind::Relu;
+        let hidden = after.add_node();
+        after.disable_connection(1);
+        after.add_connection(1, hidden).unwrap();
+        after.add_connection(hidden, 2).unwrap();
+
+        let diff = before.diff(&after);
+
+        assert_eq!(diff.added_connections, vec![(1, hidden), (hidden, 2)]);
+        assert!(diff.removed_connections.is_empty());
+        assert_eq!(diff.disabled_connections, vec![(1, 2)]);
+        assert!(diff.enabled_connections.is_empty());
+        assert_eq!(diff.changed_weights, vec![((0, 2), 0.5, -0.5)]);
+        assert_eq!(diff.added_nodes, vec![hidden]);
+        assert!(diff.removed_nodes.is_empty());
+        assert_eq!(diff.changed_nodes.len(), 1);
+        assert_eq!(diff.changed_nodes[0].index, 2);
+        assert_eq!(
+            diff.changed_nodes[0].bias,
+            Some((before.nodes()[2].bias, 0.25))
+        );
+
+        let reverse = after.diff(&before);
+
+        assert_eq!(reverse.removed_connections, vec![(1, hidden), (hidden, 2)]);
+        assert_eq!(reverse.enabled_connections, vec![(1, 2)]);
+        assert_eq!(reverse.removed_nodes, vec![hidden]);
+    }
+
+    #[test]
+    fn depth_counts_layers() {
+        let mut g = Genome::empty(1, 1);
+
+        g.node_genes.push(NodeGene::new(NodeKind::Input));
+        g.node_genes.push(NodeGene::new(NodeKind::Output));
+        g.node_genes.push(NodeGene::new(NodeKind::Hidden));
+        g.node_genes.push(NodeGene::new(NodeKind::Hidden));
+
+        assert_eq!(g.depth(), 0);
+
+        g.add_connection(0, 2).unwrap();
+        assert_eq!(g.depth(), 1);
+
+        g.add_connection(2, 3).unwrap();
+        g.add_connection(3, 1).unwrap();
+        g.add_connection(0, 1).unwrap();
+        assert_eq!(g.depth(), 3);
+
+        g.add_recurrent_connection(1, 2).unwrap();
+        assert_eq!(g.depth(), 3);
+        assert_eq!(
+            g.add_recurrent_connection(0, 3),
+            Err(GenomeError::CannotConnect { from: 0, to: 3 })
+        );
+        assert_eq!(Network::from(&g).depth(), 3);
+    }
+
+    #[test]
+    fn depth_ignores_disabled_connections() {
+        let g = Genome::decode("I1 O1 H1 | 0-2:1* 2-1:1* 0-1:1").unwrap();
+
+        assert_eq!(g.depth(), 1);
+        assert_eq!(Network::from(&g).depth(), 1);
     }
 
     #[test]
@@ -516,4 +1190,196 @@ mod tests {
 
         assert!(g.add_connection(5, 3).is_err());
     }
+
+    #[test]
+    fn display_summarizes_the_topology() {
+        let g = Genome::decode("I2 O1 H2 C1 | 0-3:1 1-3:1 3-4:1 4-2:1 5-2:1 0-2:1*").unwrap();
+        assert_eq!(
+            g.to_string(),
+            "2 inputs, 1 output, 2 hidden, 1 constant, 5 enabled connections, depth 3"
+        );
+
+        let g = Genome::decode("I1 O2 | 0-1:1").unwrap();
+        assert_eq!(
+            g.to_string(),
+            "1 input, 2 outputs, 0 hidden, 1 enabled connection, depth 1"
+        );
+        assert_eq!(Network::from(&g).to_string(), g.to_string());
+    }
+
+    #[test]
+    fn display_ignores_disabled_connections() {
+        let g = Genome::decode("I1 O1 H1 | 0-2:1* 2-1:1* 0-1:1").unwrap();
+        let summary = "1 input, 1 output, 1 hidden, 1 enabled connection, depth 1";
+
+        assert_eq!(g.to_string(), summary);
+        assert_eq!(Network::from(&g).to_string(), summary);
+    }
+
+    #[test]
+    fn connections_never_close_a_cycle() {
+        // Node 4 isn't reachable from the inputs so distances can't tell 4 -> 3 closes a cycle
+        let mut g = Genome::decode("I1 O1 H3 | 0-2:1 2-1:1 3-4:1* 4-1:1 2~2:1").unwrap();
+
+        assert!(!g.can_connect(2, 2));
+        assert!(!g.can_connect(4, 3));
+        assert!(g.add_connection(4, 3).is_err());
+        assert!(g.can_connect(2, 3));
+    }
+
+    #[test]
+    fn prune_keeps_outputs_identical() {
+        let mut builder = Genome::builder(2, 1);
+        let useful = builder.add_hidden_node();
+        let dead_end = builder.add_hidden_node();
+        let unused_constant = builder.add_constant_node(1.);
+        let feeds_dead_end = builder.add_hidden_node();
+        let constant = builder.add_constant_node(0.5);
+        builder
+            .connect(0, useful, 0.5)
+            .connect(1, useful, -0.3)
+            .connect(useful, 2, 0.8)
+            .connect(constant, 2, 0.2)
+            .connect(0, feeds_dead_end, 1.)
+            .connect(feeds_dead_end, dead_end, 1.)
+            .connect(unused_constant, dead_end, 1.)
+            .connect(1, dead_end, 1.);
+        let mut g = builder.build().unwrap();
+        g.connection_genes[0].disabled = true;
+        g.connection_genes.push(ConnectionGene::new(0, 2));
+
+        let inputs = [[0., 0.], [1., 0.], [0.3, -0.7], [-1., 1.]];
+        let outputs_before: Vec<Vec<f64>> = inputs
+            .iter()
+            .map(|i| crate::Network::from(&g).forward(i))
+            .collect();
+
+        g.prune();
+
+        assert_eq!(g.nodes().len(), 5);
+        assert_eq!(g.connections().len(), 4);
+        assert!(g.connections().iter().all(|c| !c.disabled));
+        assert!(g.validate().is_ok());
+
+        let outputs_after: Vec<Vec<f64>> = inputs
+            .iter()
+            .map(|i| crate::Network::from(&g).forward(i))
+            .collect();
+        assert_eq!(outputs_before, outputs_after);
+    }
+
+    #[test]
+    fn initial_connectivity_controls_connections() {
+        let mut rng = thread_rng();
+
+        let full =
+            Genome::new_with_connectivity(3, 2, &InitialConnectivity::FullyConnected, &mut rng);
+        let none = Genome::new_with_connectivity(3, 2, &InitialConnectivity::Unconnected, &mut rng);
+        let partial =
+            Genome::new_with_connectivity(3, 2, &InitialConnectivity::PartialRandom(0.5), &mut rng);
+
+        assert_eq!(full.connections().len(), 6);
+        assert!(none.connections().is_empty());
+        assert!(partial.connections().len() <= 6);
+        assert!(none.node_order().is_some());
+
+        let outputs = crate::Network::from(&none).forward(&[1., 2., 3.]);
+        assert_eq!(outputs.len(), 2);
+    }
+
+    #[test]
+    fn compact_keeps_outputs_identical() {
+        let mut rng = thread_rng();
+        let mut g = Genome::new(2, 2);
+
+        (0..6).for_each(|_| crate::mutations::add_node(&mut g, &Default::default(), &mut rng));
+        (0..3).for_each(|_| g.mutate(&MutationKind::RemoveNode));
+        // A hidden node whose connections are all disabled is dead
+        let dead = g.add_node();
+        g.add_connection(0, dead).unwrap();
+        g.connection_genes.last_mut().unwrap().disabled = true;
+
+        let innovations: HashSet<usize> = g.connections().iter().map(|c| c.innovation).collect();
+        let dead_innovation = g.connections().last().unwrap().innovation;
+        let mut network = crate::Network::from(&g);
+
+        g.compact();
+        let mut compacted = crate::Network::from(&g);
+
+        assert!(g.validate().is_ok());
+        assert!(g.nodes().len() < network.nodes.len());
+        assert!(g.nodes().iter().enumerate().all(|(i, n)| {
+            !matches!(n.kind, NodeKind::Hidden)
+                || g.connections()
+                    .iter()
+                    .any(|c| !c.disabled && (c.from == i || c.to == i))
+        }));
+        assert!(g
+            .connections()
+            .iter()
+            .all(|c| innovations.contains(&c.innovation) && c.innovation != dead_innovation));
+
+        for inputs in [vec![0., 0.], vec![0.5, -0.25], vec![-1., 1.]] {
+            assert_eq!(
+                network.forward_pass(inputs.clone()),
+                compacted.forward_pass(inputs)
+            );
+        }
+    }
+
+    #[test]
+    fn complexity_counts_enabled_connections() {
+        let mut g = Genome::new(2, 1);
+
+        assert_eq!(g.enabled_connection_count(), 2);
+        assert_eq!(g.hidden_node_count(), 0);
+        assert_eq!(g.complexity(), 5);
+
+        crate::mutations::add_node(&mut g, &Default::default(), &mut thread_rng());
+
+        assert_eq!(g.connections().len(), 4);
+        assert_eq!(g.enabled_connection_count(), 3);
+        assert_eq!(g.hidden_node_count(), 1);
+        assert_eq!(g.complexity(), 7);
+    }
+
+    #[test]
+    fn validate_accepts_new_genome() {
+        assert_eq!(Genome::new(3, 2).validate(), Ok(()));
+    }
+
+    #[test]
+    fn validate_reports_every_violation() {
+        let mut g = Genome::new(2, 1);
+
+        g.node_genes.push(NodeGene::new(NodeKind::Hidden));
+        g.node_genes.push(NodeGene::new(NodeKind::Hidden));
+        g.connection_genes.push(ConnectionGene::new(3, 4));
+        g.connection_genes.push(ConnectionGene::new(4, 3));
+        g.connection_genes.push(ConnectionGene::new(2, 0));
+        g.connection_genes.push(ConnectionGene::new(0, 2));
+        g.outputs = 2;
+
+        let errors = g.validate().unwrap_err();
+
+        assert!(errors.contains(&GenomeError::OutputCountMismatch {
+            expected: 2,
+            found: 1
+        }));
+        assert!(errors.contains(&GenomeError::ConnectionToInput { connection: 4 }));
+        assert!(errors.contains(&GenomeError::ConnectionFromOutput { connection: 4 }));
+        assert!(errors.contains(&GenomeError::DuplicateConnection { from: 0, to: 2 }));
+        assert!(errors.contains(&GenomeError::Cycle));
+        assert_eq!(errors.len(), 5);
+
+        g.connection_genes.push(ConnectionGene::new(0, 9));
+
+        let errors = g.validate().unwrap_err();
+
+        assert!(errors.contains(&GenomeError::NodeOutOfRange {
+            connection: 6,
+            node: 9
+        }));
+        assert!(!errors.contains(&GenomeError::Cycle));
+    }
 }
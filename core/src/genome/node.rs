@@ -1,7 +1,8 @@
 use crate::activation::ActivationKind;
 use crate::aggregations::Aggregation;
 use crate::node::NodeKind;
-use rand::random;
+use crate::Configuration;
+use rand::{thread_rng, Rng};
 use std::hash::{Hash, Hasher};
 
 #[derive(Debug, Clone)]
@@ -10,18 +11,57 @@ pub struct NodeGene {
     pub aggregation: Aggregation,
     pub activation: ActivationKind,
     pub bias: f64,
+    /// Frozen nodes keep their bias, activation and aggregation and are never removed
+    pub frozen: bool,
 }
 
 impl NodeGene {
     pub fn new(kind: NodeKind) -> Self {
-        let aggregation = random();
+        NodeGene::new_with_rng(kind, &mut thread_rng())
+    }
+
+    pub fn new_with_rng<R: Rng + ?Sized>(kind: NodeKind, rng: &mut R) -> Self {
+        NodeGene::new_with_functions(
+            kind,
+            &ActivationKind::evolvable(),
+            &Aggregation::evolvable(),
+            rng,
+        )
+    }
+
+    /// Creates a node whose activation and aggregation are among the configured allowed ones
+    pub fn new_with_configuration<R: Rng + ?Sized>(
+        kind: NodeKind,
+        configuration: &Configuration,
+        rng: &mut R,
+    ) -> Self {
+        NodeGene::new_with_functions(
+            kind,
+            &configuration.allowed_activations,
+            &configuration.allowed_aggregations,
+            rng,
+        )
+    }
+
+    fn new_with_functions<R: Rng + ?Sized>(
+        kind: NodeKind,
+        activations: &[ActivationKind],
+        aggregations: &[Aggregation],
+        rng: &mut R,
+    ) -> Self {
+        // Constant nodes output their bias so their aggregation and activation are never used
+        let aggregation = match kind {
+            NodeKind::Input | NodeKind::Constant => Aggregation::Sum,
+            _ => aggregations[rng.gen_range(0, aggregations.len())].clone(),
+        };
         let activation = match kind {
             NodeKind::Input => ActivationKind::Input,
-            _ => random(),
+            NodeKind::Constant => ActivationKind::Identity,
+            _ => activations[rng.gen_range(0, activations.len())].clone(),
         };
         let bias: f64 = match kind {
             NodeKind::Input => 0.,
-            _ => random::<f64>() * 2. - 1.,
+            _ => rng.gen::<f64>() * 2. - 1.,
         };
 
         NodeGene {
@@ -29,6 +69,7 @@ impl NodeGene {
             kind,
             activation,
             bias,
+            frozen: false,
         }
     }
 }
@@ -52,3 +93,19 @@ impl Hash for NodeGene {
         self.bias.to_bits().hash(state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_node_is_not_randomized() {
+        for _ in 0..10 {
+            let node = NodeGene::new(NodeKind::Input);
+
+            assert_eq!(node.aggregation, Aggregation::Sum);
+            assert_eq!(node.activation, ActivationKind::Input);
+            assert!(node.bias.abs() < f64::EPSILON);
+        }
+    }
+}
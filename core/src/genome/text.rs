@@ -0,0 +1,299 @@
+use std::fmt::Write;
+
+use super::innovation::topology_innovation;
+use super::{ConnectionGene, Genome, NodeGene, ParseError};
+use crate::activation::ActivationKind;
+use crate::aggregations::Aggregation;
+use crate::node::NodeKind;
+
+/// Letter of the node kind in the node counts
+fn kind_letter(kind: &NodeKind) -> char {
+    match kind {
+        NodeKind::Input => 'I',
+        NodeKind::Output => 'O',
+        NodeKind::Hidden => 'H',
+        NodeKind::Constant => 'C',
+    }
+}
+
+pub fn encode(genome: &Genome) -> String {
+    // Consecutive nodes of the same kind are counted together so the node order is kept
+    let mut counts: Vec<(char, usize)> = vec![];
+    for node in genome.nodes() {
+        let letter = kind_letter(&node.kind);
+
+        match counts.last_mut() {
+            Some((last, count)) if *last == letter => *count += 1,
+            _ => counts.push((letter, 1)),
+        }
+    }
+
+    let counts: Vec<String> = counts
+        .iter()
+        .map(|(letter, count)| format!("{}{}", letter, count))
+        .collect();
+
+    let connections: Vec<String> = genome
+        .connections()
+        .iter()
+        .map(|c| {
+            let arrow = if c.recurrent { '~' } else { '-' };
+            let disabled = if c.disabled { "*" } else { "" };
+
+            format!("{}{}{}:{}{}", c.from, arrow, c.to, c.weight, disabled)
+        })
+        .collect();
+
+    let nodes: Vec<String> = genome
+        .nodes()
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| !matches!(n.kind, NodeKind::Input))
+        .map(|(index, n)| {
+            let mut node = format!("{}:{:?}:{}", index, n.activation, n.bias);
+            if n.aggregation != Aggregation::Sum {
+                write!(node, ":{:?}", n.aggregation).unwrap();
+            }
+
+            node
+        })
+        .collect();
+
+    // Every token starts with a space so empty sections are left as just the separator
+    let section = |tokens: Vec<String>| -> String {
+        tokens.iter().map(|token| format!(" {}", token)).collect()
+    };
+
+    format!(
+        "{} |{} |{}",
+        counts.join(" "),
+        section(connections),
+        section(nodes)
+    )
+}
+
+pub fn decode(text: &str) -> Result<Genome, ParseError> {
+    let mut sections = text.split('|').map(str::trim);
+
+    let node_kinds = parse_counts(sections.next().unwrap_or(""))?;
+    let inputs = node_kinds
+        .iter()
+        .take_while(|kind| matches!(kind, NodeKind::Input))
+        .count();
+    let outputs = node_kinds[inputs..]
+        .iter()
+        .take_while(|kind| matches!(kind, NodeKind::Output))
+        .count();
+
+    let mut genome = Genome::empty(inputs, outputs);
+    genome.node_genes = node_kinds
+        .into_iter()
+        .map(|kind| NodeGene {
+            activation: match kind {
+                NodeKind::Input => ActivationKind::Input,
+                _ => ActivationKind::Identity,
+            },
+            kind,
+            aggregation: Aggregation::Sum,
+            bias: 0.,
+            frozen: false,
+        })
+        .collect();
+
+    for token in sections.next().unwrap_or("").split_whitespace() {
+        genome.connection_genes.push(parse_connection(token)?);
+    }
+
+    for token in sections.next().unwrap_or("").split_whitespace() {
+        parse_node(token, &mut genome)?;
+    }
+
+    if let Some(section) = sections.next() {
+        return Err(ParseError::InvalidToken(section.to_owned()));
+    }
+
+    genome.validate().map_err(ParseError::Invalid)?;
+
+    Ok(genome)
+}
+
+/// Reads node counts like `I2 O1 H1`, inputs have to come first and outputs right after them
+fn parse_counts(section: &str) -> Result<Vec<NodeKind>, ParseError> {
+    let mut kinds = vec![];
+
+    for token in section.split_whitespace() {
+        let invalid = || ParseError::InvalidToken(token.to_owned());
+
+        let mut chars = token.chars();
+        let kind = match chars.next() {
+            Some('I') => NodeKind::Input,
+            Some('O') => NodeKind::Output,
+            Some('H') => NodeKind::Hidden,
+            Some('C') => NodeKind::Constant,
+            _ => return Err(invalid()),
+        };
+        let count: usize = chars.as_str().parse().map_err(|_| invalid())?;
+
+        let out_of_order = match kind {
+            NodeKind::Input => kinds.iter().any(|k| !matches!(k, NodeKind::Input)),
+            NodeKind::Output => kinds
+                .iter()
+                .any(|k| matches!(k, NodeKind::Hidden | NodeKind::Constant)),
+            _ => false,
+        };
+        if out_of_order {
+            return Err(invalid());
+        }
+
+        kinds.extend((0..count).map(|_| kind.clone()));
+    }
+
+    if !kinds.iter().any(|k| matches!(k, NodeKind::Input))
+        || !kinds.iter().any(|k| matches!(k, NodeKind::Output))
+    {
+        return Err(ParseError::MissingCounts);
+    }
+
+    Ok(kinds)
+}
+
+/// Reads a connection like `0-3:0.5`, `~` instead of `-` makes it recurrent and a trailing `*`
+/// disables it
+fn parse_connection(token: &str) -> Result<ConnectionGene, ParseError> {
+    let invalid = || ParseError::InvalidToken(token.to_owned());
+
+    let (rest, disabled) = match token.strip_suffix('*') {
+        Some(rest) => (rest, true),
+        None => (token, false),
+    };
+    let (nodes, weight) = rest.split_once(':').ok_or_else(invalid)?;
+    let (from, to, recurrent) = match (nodes.split_once('-'), nodes.split_once('~')) {
+        (Some((from, to)), None) => (from, to, false),
+        (None, Some((from, to))) => (from, to, true),
+        _ => return Err(invalid()),
+    };
+
+    let from: usize = from.parse().map_err(|_| invalid())?;
+    let to: usize = to.parse().map_err(|_| invalid())?;
+
+    Ok(ConnectionGene {
+        from,
+        to,
+        weight: weight.parse().map_err(|_| invalid())?,
+        disabled,
+        recurrent,
+        innovation: topology_innovation(from, to, recurrent),
+        frozen: false,
+    })
+}
+
+/// Reads a node like `3:Tanh:-0.3` with an optional aggregation after the bias
+fn parse_node(token: &str, genome: &mut Genome) -> Result<(), ParseError> {
+    let invalid = || ParseError::InvalidToken(token.to_owned());
+
+    let parts: Vec<&str> = token.split(':').collect();
+    if parts.len() != 3 && parts.len() != 4 {
+        return Err(invalid());
+    }
+
+    let index: usize = parts[0].parse().map_err(|_| invalid())?;
+    let node = match genome.node_genes.get_mut(index) {
+        Some(node) if !matches!(node.kind, NodeKind::Input) => node,
+        _ => return Err(ParseError::UnknownNode(index)),
+    };
+
+    node.activation = ActivationKind::evolvable()
+        .into_iter()
+        .find(|activation| format!("{:?}", activation) == parts[1])
+        .ok_or_else(invalid)?;
+    node.bias = parts[2].parse().map_err(|_| invalid())?;
+    if let Some(aggregation) = parts.get(3) {
+        node.aggregation = Aggregation::evolvable()
+            .into_iter()
+            .find(|a| format!("{:?}", a) == *aggregation)
+            .ok_or_else(invalid)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genome::GenomeError;
+
+    #[test]
+    fn hand_written_genome_is_decoded() {
+        let g = Genome::decode("I2 O1 H1 | 0-3:0.5 1-3:1 3-2:-0.2 0-2:1.5* 3~3:0.25").unwrap();
+
+        assert_eq!((g.input_count(), g.output_count()), (2, 1));
+        assert_eq!(g.nodes()[3].kind, NodeKind::Hidden);
+        assert_eq!(g.nodes()[3].activation, ActivationKind::Identity);
+        assert_eq!(g.connections().len(), 5);
+        assert!(g.connections()[3].disabled);
+        assert!(g.connections()[4].recurrent);
+        assert!((g.connections()[2].weight + 0.2).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn encoding_round_trips() {
+        let g = Genome::decode(
+            "I2 O1 H1 C1 H1 | 0-3:0.1 3-2:-0.7* 1-5:0.3 5-2:2 4-2:1 5~3:0.5 0-2:0.123456789 \
+             | 2:Tanh:0.5 3:Relu:-1:Max 4:Identity:0.75 5:Gaussian:0:Product",
+        )
+        .unwrap();
+        let text = g.encode();
+
+        assert_eq!(Genome::decode(&text).unwrap().encode(), text);
+        assert_eq!(Genome::decode(&text).unwrap().nodes(), g.nodes());
+        assert_eq!(
+            Genome::decode(&text).unwrap().connections(),
+            g.connections()
+        );
+        assert!(text.starts_with("I2 O1 H1 C1 H1 | 0-3:0.1 3-2:-0.7* "));
+
+        let g = Genome::new(3, 2);
+        assert_eq!(Genome::decode(&g.encode()).unwrap().nodes(), g.nodes());
+        assert_eq!(
+            Genome::decode(&g.encode()).unwrap().connections(),
+            g.connections()
+        );
+
+        let unconnected = Genome::decode("I1 O1").unwrap();
+        assert_eq!(unconnected.encode(), "I1 O1 | | 1:Identity:0");
+    }
+
+    #[test]
+    fn malformed_text_is_rejected() {
+        assert_eq!(Genome::decode(""), Err(ParseError::MissingCounts));
+        assert_eq!(Genome::decode("H1 | "), Err(ParseError::MissingCounts));
+        assert_eq!(
+            Genome::decode("O1 I1"),
+            Err(ParseError::InvalidToken("I1".to_owned()))
+        );
+        assert_eq!(
+            Genome::decode("I1 O1 | 0>1:1"),
+            Err(ParseError::InvalidToken("0>1:1".to_owned()))
+        );
+        assert_eq!(
+            Genome::decode("I1 O1 | 0-1:x"),
+            Err(ParseError::InvalidToken("0-1:x".to_owned()))
+        );
+        assert_eq!(
+            Genome::decode("I1 O1 | | 0:Tanh:1"),
+            Err(ParseError::UnknownNode(0))
+        );
+        assert_eq!(
+            Genome::decode("I1 O1 | | 1:Tanh"),
+            Err(ParseError::InvalidToken("1:Tanh".to_owned()))
+        );
+        assert!(Genome::decode("I1 O1 H1 | 0-1:1 1~2:1 2-1:1").is_ok());
+        assert_eq!(
+            Genome::decode("I1 O1 | 1-0:1"),
+            Err(ParseError::Invalid(vec![
+                GenomeError::ConnectionToInput { connection: 0 },
+                GenomeError::ConnectionFromOutput { connection: 0 },
+            ]))
+        );
+    }
+}
@@ -1,14 +1,23 @@
 mod activation;
 mod aggregations;
 mod connection;
+#[cfg(feature = "training")]
+pub mod fitness;
 mod genome;
 mod mutations;
 mod neat;
 mod network;
 mod node;
+#[cfg(feature = "training")]
 pub mod reporting;
+#[cfg(feature = "training")]
 mod speciation;
 
+pub use activation::ActivationKind;
+pub use aggregations::Aggregation;
 pub use genome::*;
 pub use neat::*;
 pub use network::*;
+pub use node::NodeKind;
+#[cfg(feature = "training")]
+pub use speciation::{DistanceMetric, GenomicDistance, SpeciesStat};
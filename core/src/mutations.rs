@@ -1,29 +1,68 @@
 use rand::distributions::{Distribution, Standard};
-use rand::random;
-use rand::thread_rng;
 use rand::Rng;
 use rand_distr::StandardNormal;
 
-use crate::activation::ActivationKind;
 use crate::genome::Genome;
 use crate::node::NodeKind;
-
-pub fn mutate(kind: &MutationKind, g: &mut Genome) {
+use crate::Configuration;
+
+pub fn mutate<R: Rng + ?Sized>(
+    kind: &MutationKind,
+    g: &mut Genome,
+    configuration: &Configuration,
+    rng: &mut R,
+) {
     use MutationKind::*;
 
+    let connection_count = g.connections().len();
+    let node_count = g.nodes().len();
+
     match kind {
-        AddConnection => add_connection(g),
-        RemoveConnection => disable_connection(g),
-        AddNode => add_node(g),
-        RemoveNode => remove_node(g),
-        ModifyWeight => change_weight(g),
-        ModifyBias => change_bias(g),
-        ModifyActivation => change_activation(g),
-        ModifyAggregation => change_aggregation(g),
+        AddConnection => add_connection(g, configuration, rng),
+        RemoveConnection => disable_connection(g, rng),
+        AddNode => add_node(g, configuration, rng),
+        RemoveNode => remove_node(g, configuration, rng),
+        ModifyWeight => change_weight(g, configuration, rng),
+        ModifyBias => change_bias(g, configuration, rng),
+        ModifyActivation => change_activation(g, configuration, rng),
+        ModifyAggregation => change_aggregation(g, configuration, rng),
+        AddConstant => add_constant(g, configuration, rng),
+        ReenableConnection => reenable_connection(g, configuration, rng),
     };
+
+    // Changed weights and biases are clamped by their mutations, only the added genes are left
+    (connection_count..g.connections().len()).for_each(|i| clamp_weight(g, i, configuration));
+    (node_count..g.nodes().len()).for_each(|i| clamp_bias(g, i, configuration));
+}
+
+/// Keeps weights and non input biases inside the configured bounds, new genes are sampled in
+/// [-1, 1] regardless of the bounds and frozen genes are left as they are
+#[cfg_attr(not(feature = "training"), allow(dead_code))]
+pub fn clamp_to_bounds(g: &mut Genome, configuration: &Configuration) {
+    (0..g.connections().len()).for_each(|i| clamp_weight(g, i, configuration));
+    (0..g.nodes().len()).for_each(|i| clamp_bias(g, i, configuration));
+}
+
+fn clamp_weight(g: &mut Genome, index: usize, configuration: &Configuration) {
+    let (low, high) = configuration.weight_bounds;
+    let connection = g.connection_mut(index).unwrap();
+
+    if !connection.frozen {
+        connection.weight = connection.weight.max(low).min(high);
+    }
+}
+
+fn clamp_bias(g: &mut Genome, index: usize, configuration: &Configuration) {
+    let (low, high) = configuration.bias_bounds;
+    let node = g.node_mut(index).unwrap();
+
+    if !matches!(node.kind, NodeKind::Input) && !node.frozen {
+        node.bias = node.bias.max(low).min(high);
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "config-toml", derive(serde::Deserialize))]
 pub enum MutationKind {
     AddConnection,
     RemoveConnection,
@@ -33,26 +72,70 @@ pub enum MutationKind {
     ModifyBias,
     ModifyActivation,
     ModifyAggregation,
+    AddConstant,
+    ReenableConnection,
 }
 
 impl Distribution<MutationKind> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> MutationKind {
         use MutationKind::*;
 
-        match rng.gen_range(0, 7) {
+        match rng.gen_range(0, 8) {
             0 => AddConnection,
             1 => RemoveConnection,
             2 => AddNode,
             3 => RemoveNode,
             4 => ModifyWeight,
             5 => ModifyBias,
-            _ => ModifyActivation,
+            6 => ModifyActivation,
+            _ => ModifyAggregation,
         }
     }
 }
 
-/// Adds a new random connection
-pub fn add_connection(g: &mut Genome) {
+/// Checks if the genome is allowed to get another node
+fn below_node_cap(g: &Genome, configuration: &Configuration) -> bool {
+    configuration
+        .max_nodes
+        .is_none_or(|max_nodes| g.nodes().len() < max_nodes)
+}
+
+/// Checks if the genome is allowed to get another enabled connection
+fn below_connection_cap(g: &Genome, configuration: &Configuration) -> bool {
+    configuration
+        .max_connections
+        .is_none_or(|max_connections| g.enabled_connection_count() < max_connections)
+}
+
+/// Random node pairs tried instead of all of them once the genome is close to its connection cap
+const SAMPLED_CONNECTIONS: usize = 32;
+
+/// Checks if the genome is within a tenth of its connection cap, only a few more connections fit
+/// so looking at every node pair isn't worth it
+fn near_connection_cap(g: &Genome, configuration: &Configuration) -> bool {
+    configuration
+        .max_connections
+        .is_some_and(|max_connections| {
+            max_connections.saturating_sub(g.enabled_connection_count()) * 10 <= max_connections
+        })
+}
+
+/// Adds a new random connection, it can be recurrent if the configuration allows it
+pub fn add_connection<R: Rng + ?Sized>(g: &mut Genome, configuration: &Configuration, rng: &mut R) {
+    if !below_connection_cap(g, configuration) {
+        return;
+    }
+
+    if near_connection_cap(g, configuration) {
+        match sample_connection(g, configuration, rng) {
+            Some((from, to, false)) => g.add_connection_with_rng(from, to, rng).unwrap(),
+            Some((from, to, true)) => g.add_recurrent_connection_with_rng(from, to, rng).unwrap(),
+            None => return,
+        };
+
+        return;
+    }
+
     let existing_connections: Vec<(usize, usize, bool)> = g
         .connections()
         .iter()
@@ -81,31 +164,111 @@ pub fn add_connection(g: &mut Genome) {
     possible_connections.sort_unstable();
     possible_connections.dedup();
 
+    let mut possible_recurrent_connections: Vec<(usize, usize)> = if configuration.recurrent {
+        // Recurrent connections can also loop back into the same node
+        possible_connections
+            .iter()
+            .cloned()
+            .chain((0..g.nodes().len()).map(|i| (i, i)))
+            .filter(|(i, j)| g.can_connect_recurrent(*i, *j))
+            .collect()
+    } else {
+        vec![]
+    };
+
     possible_connections = possible_connections
         .into_iter()
         .filter(|(i, j)| g.can_connect(*i, *j))
         .collect();
 
-    if possible_connections.is_empty() {
+    let possible_count = possible_connections.len() + possible_recurrent_connections.len();
+
+    if possible_count == 0 {
         return;
     }
 
-    let picked_connection = possible_connections
-        .get(random::<usize>() % possible_connections.len())
-        .unwrap();
+    let picked_index = if configuration.prefer_local_connections {
+        pick_local_connection(
+            g,
+            possible_connections
+                .iter()
+                .chain(possible_recurrent_connections.iter()),
+            rng,
+        )
+    } else {
+        rng.gen::<usize>() % possible_count
+    };
 
-    g.add_connection(picked_connection.0, picked_connection.1)
-        .unwrap();
+    if picked_index < possible_connections.len() {
+        let picked_connection = possible_connections.get(picked_index).unwrap();
+
+        g.add_connection_with_rng(picked_connection.0, picked_connection.1, rng)
+            .unwrap();
+    } else {
+        let picked_connection =
+            possible_recurrent_connections.swap_remove(picked_index - possible_connections.len());
+
+        g.add_recurrent_connection_with_rng(picked_connection.0, picked_connection.1, rng)
+            .unwrap();
+    }
+}
+
+/// Tries a bounded number of random node pairs and returns the first one that can be connected
+/// along with whether the connection is recurrent, the distance between the nodes isn't preferred
+fn sample_connection<R: Rng + ?Sized>(
+    g: &Genome,
+    configuration: &Configuration,
+    rng: &mut R,
+) -> Option<(usize, usize, bool)> {
+    let node_count = g.nodes().len();
+
+    (0..SAMPLED_CONNECTIONS).find_map(|_| {
+        let from = rng.gen_range(0, node_count);
+        let to = rng.gen_range(0, node_count);
+
+        if g.can_connect(from, to) {
+            Some((from, to, false))
+        } else if configuration.recurrent && g.can_connect_recurrent(from, to) {
+            Some((from, to, true))
+        } else {
+            None
+        }
+    })
+}
+
+/// Picks the index of a connection with a probability that halves for every layer between its
+/// nodes, nodes only reachable recurrently count as inputs
+fn pick_local_connection<'a, I, R>(g: &Genome, connections: I, rng: &mut R) -> usize
+where
+    I: Iterator<Item = &'a (usize, usize)>,
+    R: Rng + ?Sized,
+{
+    let distances = g.calculate_node_distance_from_inputs();
+    let distance = |node: &usize| *distances.get(node).unwrap_or(&0) as f64;
+
+    let weights: Vec<f64> = connections
+        .map(|(from, to)| 0.5f64.powf((distance(from) - distance(to)).abs()))
+        .collect();
+
+    let mut remaining = rng.gen::<f64>() * weights.iter().sum::<f64>();
+    for (index, weight) in weights.iter().enumerate() {
+        if remaining < *weight {
+            return index;
+        }
+        remaining -= weight;
+    }
+
+    weights.len() - 1
 }
 
 /// Removes a random connection if it's not the only one
-fn disable_connection(g: &mut Genome) {
+fn disable_connection<R: Rng + ?Sized>(g: &mut Genome, rng: &mut R) {
     let eligible_indexes: Vec<usize> = g
         .connections()
         .iter()
         .enumerate()
         .filter(|(_, c)| {
-            if c.disabled {
+            if c.disabled || c.frozen {
                 return false;
             }
 
@@ -135,27 +298,36 @@ fn disable_connection(g: &mut Genome) {
     }
 
     let index = eligible_indexes
-        .get(random::<usize>() % eligible_indexes.len())
+        .get(rng.gen::<usize>() % eligible_indexes.len())
         .unwrap();
 
     g.disable_connection(*index);
 }
 
 /// Adds a random hidden node to the genome and its connections
-pub fn add_node(g: &mut Genome) {
-    let new_node_index = g.add_node();
+pub fn add_node<R: Rng + ?Sized>(g: &mut Genome, configuration: &Configuration, rng: &mut R) {
+    // Splitting a connection leaves one more enabled connection behind
+    if !below_node_cap(g, configuration) || !below_connection_cap(g, configuration) {
+        return;
+    }
 
-    // Only enabled connections can be disabled
+    // Only enabled connections can be disabled, recurrent and frozen ones are never split
     let enabled_connections: Vec<usize> = g
         .connections()
         .iter()
         .enumerate()
-        .filter(|(_, c)| !c.disabled)
+        .filter(|(_, c)| !c.disabled && !c.recurrent && !c.frozen)
         .map(|(i, _)| i)
         .collect();
 
+    if enabled_connections.is_empty() {
+        return;
+    }
+
+    let new_node_index = g.add_node_with_configuration(configuration, rng);
+
     let (picked_index, picked_from, picked_to, picked_weight) = {
-        let random_enabled_connection_index = random::<usize>() % enabled_connections.len();
+        let random_enabled_connection_index = rng.gen::<usize>() % enabled_connections.len();
         let picked_index = enabled_connections
             .get(random_enabled_connection_index)
             .unwrap();
@@ -171,15 +343,19 @@ pub fn add_node(g: &mut Genome) {
 
     g.disable_connection(*picked_index);
 
-    let connection_index = g.add_connection(picked_from, new_node_index).unwrap();
-    g.add_connection(new_node_index, picked_to).unwrap();
+    let connection_index = g
+        .add_connection_with_rng(picked_from, new_node_index, rng)
+        .unwrap();
+    g.add_connection_with_rng(new_node_index, picked_to, rng)
+        .unwrap();
 
     // Reuse the weight from the removed connection
     g.connection_mut(connection_index).unwrap().weight = picked_weight;
 }
 
-/// Removes a random hidden node from the genome and rewires connected nodes
-fn remove_node(g: &mut Genome) {
+/// Removes a random hidden node from the genome and rewires connected nodes, frozen nodes and
+/// nodes with frozen connections stay
+fn remove_node<R: Rng + ?Sized>(g: &mut Genome, configuration: &Configuration, rng: &mut R) {
     let hidden_nodes: Vec<usize> = g
         .nodes()
         .iter()
@@ -196,7 +372,16 @@ fn remove_node(g: &mut Genome) {
                 .filter(|c| c.from == *i && !c.disabled)
                 .count();
 
-            matches!(n.kind, NodeKind::Hidden) && incoming_count > 0 && outgoing_count > 0
+            let has_frozen_connections = g
+                .connections()
+                .iter()
+                .any(|c| (c.from == *i || c.to == *i) && !c.disabled && c.frozen);
+
+            matches!(n.kind, NodeKind::Hidden)
+                && !n.frozen
+                && !has_frozen_connections
+                && incoming_count > 0
+                && outgoing_count > 0
         })
         .map(|(i, _)| i)
         .collect();
@@ -206,7 +391,7 @@ fn remove_node(g: &mut Genome) {
     }
 
     let picked_node_index = hidden_nodes
-        .get(random::<usize>() % hidden_nodes.len())
+        .get(rng.gen::<usize>() % hidden_nodes.len())
         .unwrap();
 
     let incoming_connections_and_from_indexes: Vec<(usize, usize)> = g
@@ -240,8 +425,6 @@ fn remove_node(g: &mut Genome) {
         })
         .collect();
 
-    g.add_many_connections(&new_from_to_pairs);
-
     let connection_indexes_to_delete: Vec<usize> = g
         .connections()
         .iter()
@@ -251,84 +434,220 @@ fn remove_node(g: &mut Genome) {
         .collect();
 
     g.disable_many_connections(&connection_indexes_to_delete);
+
+    // Bypassing the node can take more connections than it had, the rest is left out at the cap
+    for (from, to) in new_from_to_pairs {
+        if !below_connection_cap(g, configuration) {
+            break;
+        }
+
+        let _ = g.add_connection_with_rng(from, to, rng);
+    }
 }
 
-/// Changes the weight of a random connection
-fn change_weight(g: &mut Genome) {
-    let index = random::<usize>() % g.connections().len();
+/// Changes the weight of a random connection, either by perturbing it or by resetting it
+fn change_weight<R: Rng + ?Sized>(g: &mut Genome, configuration: &Configuration, rng: &mut R) {
+    let eligible_indexes: Vec<usize> = g
+        .connections()
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| !c.frozen)
+        .map(|(i, _)| i)
+        .collect();
+
+    if eligible_indexes.is_empty() {
+        return;
+    }
+
+    let index = eligible_indexes[rng.gen::<usize>() % eligible_indexes.len()];
     let picked_connection = g.connection_mut(index).unwrap();
 
-    let new_weight = if random::<f64>() < 0.1 {
-        picked_connection.weight + thread_rng().sample::<f64, StandardNormal>(StandardNormal)
+    let new_weight = if rng.gen::<f64>() < configuration.weight_perturbation_prob {
+        picked_connection.weight
+            + rng.sample::<f64, StandardNormal>(StandardNormal)
+                * configuration.weight_perturbation_stddev
     } else {
-        random::<f64>() * 2. - 1.
+        let (low, high) = configuration.weight_reset_range;
+
+        low + rng.gen::<f64>() * (high - low)
     };
 
-    picked_connection.weight = new_weight.max(-1.).min(1.);
+    let (low, high) = configuration.weight_bounds;
+    picked_connection.weight = new_weight.max(low).min(high);
 }
 
 /// Changes the bias of a random non input node
-fn change_bias(g: &mut Genome) {
+fn change_bias<R: Rng + ?Sized>(g: &mut Genome, configuration: &Configuration, rng: &mut R) {
     let eligible_indexes: Vec<usize> = g
         .nodes()
         .iter()
         .enumerate()
-        .filter(|(_, n)| !matches!(n.kind, NodeKind::Input))
+        .filter(|(_, n)| !matches!(n.kind, NodeKind::Input) && !n.frozen)
         .map(|(i, _)| i)
         .collect();
 
+    if eligible_indexes.is_empty() {
+        return;
+    }
+
     let index = eligible_indexes
-        .get(random::<usize>() % eligible_indexes.len())
+        .get(rng.gen::<usize>() % eligible_indexes.len())
         .unwrap();
     let picked_node = g.node_mut(*index).unwrap();
 
-    let new_bias = if random::<f64>() < 0.1 {
-        picked_node.bias + thread_rng().sample::<f64, StandardNormal>(StandardNormal)
+    let new_bias = if rng.gen::<f64>() < 0.1 {
+        picked_node.bias + rng.sample::<f64, StandardNormal>(StandardNormal)
     } else {
-        random::<f64>() * 2. - 1.
+        rng.gen::<f64>() * 2. - 1.
     };
 
-    picked_node.bias = new_bias.max(-1.).min(1.);
+    let (low, high) = configuration.bias_bounds;
+    picked_node.bias = new_bias.max(low).min(high);
 }
 
-/// Changes the activation function of a random non input node
-fn change_activation(g: &mut Genome) {
+/// Changes the activation function of a random node that is neither input nor constant, outputs
+/// are skipped when their activation is pinned by the configuration
+fn change_activation<R: Rng + ?Sized>(g: &mut Genome, configuration: &Configuration, rng: &mut R) {
+    let outputs_pinned = configuration.output_activation.is_some();
+
     let eligible_indexes: Vec<usize> = g
         .nodes()
         .iter()
         .enumerate()
-        .filter(|(_, n)| !matches!(n.kind, NodeKind::Input))
+        .filter(|(_, n)| match n.kind {
+            _ if n.frozen => false,
+            NodeKind::Input | NodeKind::Constant => false,
+            NodeKind::Output => !outputs_pinned,
+            NodeKind::Hidden => true,
+        })
         .map(|(i, _)| i)
         .collect();
 
+    if eligible_indexes.is_empty() {
+        return;
+    }
+
     let index = eligible_indexes
-        .get(random::<usize>() % eligible_indexes.len())
+        .get(rng.gen::<usize>() % eligible_indexes.len())
         .unwrap();
     let picked_node = g.node_mut(*index).unwrap();
 
-    picked_node.activation = random::<ActivationKind>();
+    let allowed_activations = &configuration.allowed_activations;
+    picked_node.activation =
+        allowed_activations[rng.gen_range(0, allowed_activations.len())].clone();
 }
 
-fn change_aggregation(g: &mut Genome) {
+/// Changes the aggregation function of a random node that is neither input nor constant
+fn change_aggregation<R: Rng + ?Sized>(g: &mut Genome, configuration: &Configuration, rng: &mut R) {
     let eligible_indexes: Vec<usize> = g
         .nodes()
         .iter()
         .enumerate()
-        .filter(|(_, n)| !matches!(n.kind, NodeKind::Input))
+        .filter(|(_, n)| !matches!(n.kind, NodeKind::Input | NodeKind::Constant) && !n.frozen)
         .map(|(i, _)| i)
         .collect();
 
+    if eligible_indexes.is_empty() {
+        return;
+    }
+
     let index = eligible_indexes
-        .get(random::<usize>() % eligible_indexes.len())
+        .get(rng.gen::<usize>() % eligible_indexes.len())
         .unwrap();
     let picked_node = g.node_mut(*index).unwrap();
 
-    picked_node.aggregation = random();
+    let allowed_aggregations = &configuration.allowed_aggregations;
+    picked_node.aggregation =
+        allowed_aggregations[rng.gen_range(0, allowed_aggregations.len())].clone();
+}
+
+/// Enables a random disabled connection if that doesn't form a cycle or duplicate an enabled one
+fn reenable_connection<R: Rng + ?Sized>(
+    g: &mut Genome,
+    configuration: &Configuration,
+    rng: &mut R,
+) {
+    if !below_connection_cap(g, configuration) {
+        return;
+    }
+
+    let eligible_indexes: Vec<usize> = g
+        .connections()
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| {
+            if !c.disabled || c.frozen {
+                return false;
+            }
+
+            let is_duplicate = g.connections().iter().any(|o| {
+                !o.disabled && o.from == c.from && o.to == c.to && o.recurrent == c.recurrent
+            });
+
+            if is_duplicate {
+                return false;
+            }
+
+            // Recurrent connections don't take part in the node order
+            if c.recurrent {
+                return true;
+            }
+
+            let mut enabled = (*c).clone();
+            enabled.disabled = false;
+
+            g.node_order_with(vec![enabled]).is_some()
+        })
+        .map(|(i, _)| i)
+        .collect();
+
+    if eligible_indexes.is_empty() {
+        return;
+    }
+
+    let index = eligible_indexes
+        .get(rng.gen::<usize>() % eligible_indexes.len())
+        .unwrap();
+
+    g.connection_mut(*index).unwrap().disabled = false;
+}
+
+/// Adds a constant node and connects it to a random node that can take it
+fn add_constant<R: Rng + ?Sized>(g: &mut Genome, configuration: &Configuration, rng: &mut R) {
+    if !below_node_cap(g, configuration) || !below_connection_cap(g, configuration) {
+        return;
+    }
+
+    // Nothing feeds the new constant so it can't form a cycle, any hidden or output node will do
+    let eligible_indexes: Vec<usize> = g
+        .nodes()
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| matches!(n.kind, NodeKind::Hidden | NodeKind::Output))
+        .map(|(i, _)| i)
+        .collect();
+
+    if eligible_indexes.is_empty() {
+        return;
+    }
+
+    let index = *eligible_indexes
+        .get(rng.gen::<usize>() % eligible_indexes.len())
+        .unwrap();
+
+    let constant_index = g.add_constant_node_with_rng(rng);
+    g.add_connection_with_rng(constant_index, index, rng)
+        .unwrap();
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::activation::ActivationKind;
+    use crate::aggregations::Aggregation;
+    use crate::genome::GenomeError;
+    use crate::InitialConnectivity;
+    use rand::{random, thread_rng};
 
     #[test]
     fn add_connection_adds_missing_connection() {
@@ -339,10 +658,50 @@ mod tests {
         g.add_connection(3, 2).unwrap();
 
         assert!(!g.connections().iter().any(|c| c.from == 3 && c.to == 1));
-        add_connection(&mut g);
+        add_connection(&mut g, &Default::default(), &mut thread_rng());
         assert!(g.connections().iter().any(|c| c.from == 3 && c.to == 1));
     }
 
+    #[test]
+    fn local_connections_are_preferred() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut builder = Genome::builder(1, 1);
+        let a = builder.add_hidden_node();
+        let b = builder.add_hidden_node();
+        let c = builder.add_hidden_node();
+        builder
+            .connect(0, a, 0.5)
+            .connect(a, b, 0.5)
+            .connect(b, c, 0.5)
+            .connect(c, 1, 0.5);
+        let chain = builder.build().unwrap();
+
+        // Counts how often the new connection skips three or more layers
+        let long_connections = |configuration: &Configuration| {
+            let mut rng = StdRng::seed_from_u64(3);
+            let distances = chain.calculate_node_distance_from_inputs();
+
+            (0..300)
+                .filter(|_| {
+                    let mut g = chain.clone();
+                    add_connection(&mut g, configuration, &mut rng);
+
+                    let added = g.connections().last().unwrap();
+                    distances[&added.to] - distances[&added.from] >= 3
+                })
+                .count()
+        };
+
+        let uniform = long_connections(&Default::default());
+        let local = long_connections(&Configuration {
+            prefer_local_connections: true,
+            ..Default::default()
+        });
+
+        assert!(local * 3 < uniform * 2);
+    }
+
     #[test]
     fn add_connection_doesnt_add_unecessary_connections() {
         let mut g = Genome::new(1, 2);
@@ -353,20 +712,45 @@ mod tests {
 
         // This will add the last missing connection
         assert_eq!(g.connections().len(), 4);
-        add_connection(&mut g);
+        add_connection(&mut g, &Default::default(), &mut thread_rng());
         assert_eq!(g.connections().len(), 5);
 
         // There should be no new connections
-        add_connection(&mut g);
+        add_connection(&mut g, &Default::default(), &mut thread_rng());
         assert_eq!(g.connections().len(), 5);
     }
 
+    #[test]
+    fn add_connection_adds_recurrent_connections_only_when_configured() {
+        let feed_forward = Configuration::default();
+        let recurrent = Configuration {
+            recurrent: true,
+            ..Default::default()
+        };
+
+        let mut g = Genome::new(2, 1);
+        g.add_node();
+        g.add_connection(0, 3).unwrap();
+        g.add_connection(3, 2).unwrap();
+
+        for _ in 0..20 {
+            add_connection(&mut g, &feed_forward, &mut thread_rng());
+        }
+        assert!(g.connections().iter().all(|c| !c.recurrent));
+
+        for _ in 0..20 {
+            add_connection(&mut g, &recurrent, &mut thread_rng());
+        }
+        assert!(g.connections().iter().any(|c| c.recurrent));
+        assert!(g.node_order().is_some());
+    }
+
     #[test]
     fn remove_connection_doesnt_remove_last_connection_of_a_node() {
         let mut g = Genome::new(1, 2);
         assert_eq!(g.connections().iter().filter(|c| !c.disabled).count(), 2);
 
-        disable_connection(&mut g);
+        disable_connection(&mut g, &mut thread_rng());
         assert_eq!(g.connections().iter().filter(|c| !c.disabled).count(), 2);
     }
 
@@ -375,7 +759,7 @@ mod tests {
         let mut g = Genome::new(1, 1);
         let original_connections = g.connections().to_vec();
 
-        add_node(&mut g);
+        add_node(&mut g, &Default::default(), &mut thread_rng());
 
         let original_connections_not_modified = original_connections
             .iter()
@@ -399,10 +783,10 @@ mod tests {
         let mut g = Genome::new(1, 1);
         let connection_enabled_initially = !g.connections().first().unwrap().disabled;
 
-        add_node(&mut g);
+        add_node(&mut g, &Default::default(), &mut thread_rng());
         let connection_disabled_after_add = g.connections().first().unwrap().disabled;
 
-        remove_node(&mut g);
+        remove_node(&mut g, &Default::default(), &mut thread_rng());
         let connection_enabled_after_remove = !g.connections().first().unwrap().disabled;
 
         assert!(connection_enabled_initially);
@@ -418,7 +802,7 @@ mod tests {
         let output_bias = g.nodes().get(1).unwrap().bias;
 
         for _ in 0..10 {
-            change_bias(&mut g);
+            change_bias(&mut g, &Default::default(), &mut thread_rng());
         }
 
         let new_input_bias = g.nodes().get(0).unwrap().bias;
@@ -439,7 +823,7 @@ mod tests {
         let mut new_o_activations = vec![];
 
         for _ in 0..10 {
-            change_activation(&mut g);
+            change_activation(&mut g, &Default::default(), &mut thread_rng());
 
             new_i_activations.push(g.nodes().get(0).unwrap().activation.clone());
             new_o_activations.push(g.nodes().get(1).unwrap().activation.clone());
@@ -449,6 +833,27 @@ mod tests {
         assert!(new_o_activations.iter().any(|a| *a != o_activation));
     }
 
+    #[test]
+    fn change_aggregation_doesnt_change_input_nodes() {
+        let mut g = Genome::new(1, 1);
+
+        let i_aggregation = g.nodes()[0].aggregation.clone();
+        let o_aggregation = g.nodes()[1].aggregation.clone();
+
+        let mut new_i_aggregations = vec![];
+        let mut new_o_aggregations = vec![];
+
+        for _ in 0..10 {
+            change_aggregation(&mut g, &Default::default(), &mut thread_rng());
+
+            new_i_aggregations.push(g.nodes()[0].aggregation.clone());
+            new_o_aggregations.push(g.nodes()[1].aggregation.clone());
+        }
+
+        assert!(new_i_aggregations.iter().all(|a| *a == i_aggregation));
+        assert!(new_o_aggregations.iter().any(|a| *a != o_aggregation));
+    }
+
     #[test]
     fn mutate_genome() {
         use std::collections::HashMap;
@@ -463,7 +868,7 @@ mod tests {
             let kind: MutationKind = random();
 
             let before = std::time::Instant::now();
-            mutate(&kind, &mut g);
+            mutate(&kind, &mut g, &Default::default(), &mut thread_rng());
             let after = std::time::Instant::now();
             let duration = after.duration_since(before);
 
@@ -505,4 +910,357 @@ mod tests {
             g.connections().iter().filter(|c| !c.disabled).count(),
         );
     }
+
+    #[test]
+    fn change_weight_uses_configured_reset_range() {
+        let mut g = Genome::new(1, 1);
+        let configuration = Configuration {
+            weight_perturbation_prob: 0.,
+            weight_reset_range: (0.25, 0.5),
+            ..Default::default()
+        };
+
+        for _ in 0..10 {
+            change_weight(&mut g, &configuration, &mut thread_rng());
+
+            let weight = g.connections()[0].weight;
+            assert!((0.25..=0.5).contains(&weight));
+        }
+    }
+
+    #[test]
+    fn change_weight_perturbs_by_configured_stddev() {
+        let mut g = Genome::new(1, 1);
+        g.connection_mut(0).unwrap().weight = 0.5;
+        let configuration = Configuration {
+            weight_perturbation_prob: 1.,
+            weight_perturbation_stddev: 0.,
+            ..Default::default()
+        };
+
+        change_weight(&mut g, &configuration, &mut thread_rng());
+
+        assert!((g.connections()[0].weight - 0.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn raising_weight_bounds_lets_weights_exceed_one() {
+        let mut g = Genome::new(1, 1);
+        let configuration = Configuration {
+            weight_perturbation_prob: 1.,
+            weight_bounds: (-10., 10.),
+            ..Default::default()
+        };
+
+        // Every perturbation starts from the old upper bound, so half of them should exceed it
+        let max_weight = (0..50)
+            .map(|_| {
+                g.connection_mut(0).unwrap().weight = 1.;
+                change_weight(&mut g, &configuration, &mut thread_rng());
+                g.connections()[0].weight
+            })
+            .fold(f64::MIN, f64::max);
+
+        assert!(max_weight > 1.);
+        assert!(max_weight <= 10.);
+    }
+
+    #[test]
+    fn clamp_to_bounds_skips_input_nodes() {
+        let mut g = Genome::new(1, 1);
+        let configuration = Configuration {
+            weight_bounds: (0.5, 0.5),
+            bias_bounds: (0.25, 0.25),
+            ..Default::default()
+        };
+
+        clamp_to_bounds(&mut g, &configuration);
+
+        assert!((g.connections()[0].weight - 0.5).abs() < f64::EPSILON);
+        assert!(g.nodes()[0].bias.abs() < f64::EPSILON);
+        assert!((g.nodes()[1].bias - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn mutations_clamp_only_the_genes_they_add() {
+        let mut g = Genome::new(1, 1);
+        g.connection_mut(0).unwrap().weight = 5.;
+        let configuration = Configuration {
+            weight_bounds: (0.5, 0.5),
+            bias_bounds: (0.25, 0.25),
+            ..Default::default()
+        };
+
+        mutate(
+            &MutationKind::AddNode,
+            &mut g,
+            &configuration,
+            &mut thread_rng(),
+        );
+
+        assert!((g.connections()[0].weight - 5.).abs() < f64::EPSILON);
+        assert!(g.connections()[1..]
+            .iter()
+            .all(|c| (c.weight - 0.5).abs() < f64::EPSILON));
+        assert!((g.nodes()[2].bias - 0.25).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn add_constant_connects_the_constant() {
+        let mut g = Genome::new(1, 1);
+
+        add_constant(&mut g, &Default::default(), &mut thread_rng());
+
+        assert!(matches!(g.nodes().get(2).unwrap().kind, NodeKind::Constant));
+        assert_eq!(g.connections().len(), 2);
+        assert_eq!(g.connections().get(1).unwrap().from, 2);
+        assert_eq!(g.connections().get(1).unwrap().to, 1);
+        assert_eq!(g.validate(), Ok(()));
+        assert!(!g.can_connect(0, 2));
+    }
+
+    #[test]
+    fn add_constant_without_a_target_leaves_the_genome_alone() {
+        let mut g = Genome::new(2, 0);
+
+        add_constant(&mut g, &Default::default(), &mut thread_rng());
+
+        assert_eq!(g.nodes().len(), 2);
+        assert!(g.connections().is_empty());
+    }
+
+    #[test]
+    fn reenable_connection_skips_cycles() {
+        let mut builder = Genome::builder(1, 1);
+        let a = builder.add_hidden_node();
+        let b = builder.add_hidden_node();
+        builder
+            .connect(0, a, 0.5)
+            .connect(a, b, 0.5)
+            .connect(b, 1, 0.5)
+            .connect(0, 1, 0.5);
+        let mut g = builder.build().unwrap();
+
+        g.connection_mut(2).unwrap().disabled = true;
+        // Turn the last connection into a disabled one that would close a loop
+        let cycle = g.connection_mut(3).unwrap();
+        cycle.from = b;
+        cycle.to = a;
+        cycle.disabled = true;
+
+        reenable_connection(&mut g, &Default::default(), &mut thread_rng());
+        reenable_connection(&mut g, &Default::default(), &mut thread_rng());
+
+        assert!(!g.connections().get(2).unwrap().disabled);
+        assert!(g.connections().get(3).unwrap().disabled);
+        assert!(g.node_order().is_some());
+    }
+
+    #[test]
+    fn mutations_only_use_allowed_activations() {
+        let allowed = vec![
+            ActivationKind::Tanh,
+            ActivationKind::Relu,
+            ActivationKind::Identity,
+        ];
+        let configuration = Configuration {
+            allowed_activations: allowed.clone(),
+            ..Default::default()
+        };
+        let mut rng = thread_rng();
+        let mut g = Genome::new_with_configuration(2, 2, &configuration, &mut rng);
+
+        for _ in 0..150 {
+            let (kind, _) = configuration
+                .mutation_kinds
+                .get(rng.gen_range(0, configuration.mutation_kinds.len()))
+                .unwrap()
+                .clone();
+            mutate(&kind, &mut g, &configuration, &mut rng);
+        }
+
+        assert!(g.nodes().iter().any(|n| matches!(n.kind, NodeKind::Hidden)));
+        assert!(g
+            .nodes()
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Hidden | NodeKind::Output))
+            .all(|n| allowed.contains(&n.activation)));
+    }
+
+    #[test]
+    fn mutations_only_use_allowed_aggregations() {
+        let configuration = Configuration {
+            allowed_aggregations: vec![Aggregation::Sum],
+            ..Default::default()
+        };
+        let mut rng = thread_rng();
+        let mut g = Genome::new_with_configuration(2, 2, &configuration, &mut rng);
+
+        for _ in 0..150 {
+            let (kind, _) = configuration
+                .mutation_kinds
+                .get(rng.gen_range(0, configuration.mutation_kinds.len()))
+                .unwrap()
+                .clone();
+            mutate(&kind, &mut g, &configuration, &mut rng);
+        }
+
+        assert!(g.nodes().iter().any(|n| matches!(n.kind, NodeKind::Hidden)));
+        assert!(g.nodes().iter().all(|n| n.aggregation == Aggregation::Sum));
+    }
+
+    #[test]
+    fn mutations_leave_frozen_genes_alone() {
+        let mut g = Genome::new(2, 2);
+        add_node(&mut g, &Default::default(), &mut thread_rng());
+
+        let connections: Vec<(usize, usize)> =
+            g.connections().iter().map(|c| (c.from, c.to)).collect();
+        for (from, to) in connections {
+            g.freeze_connection(from, to).unwrap();
+        }
+        for index in 0..g.nodes().len() {
+            g.freeze_node(index).unwrap();
+        }
+        assert_eq!(
+            g.freeze_connection(0, 1),
+            Err(GenomeError::NoSuchConnection { from: 0, to: 1 })
+        );
+        assert_eq!(
+            g.freeze_node(g.nodes().len()),
+            Err(GenomeError::NoSuchNode(g.nodes().len()))
+        );
+
+        let frozen = g.clone();
+        for kind in [
+            MutationKind::RemoveConnection,
+            MutationKind::AddNode,
+            MutationKind::RemoveNode,
+            MutationKind::ModifyWeight,
+            MutationKind::ModifyBias,
+            MutationKind::ModifyActivation,
+            MutationKind::ModifyAggregation,
+        ] {
+            for _ in 0..100 {
+                mutate(&kind, &mut g, &Default::default(), &mut thread_rng());
+            }
+        }
+
+        assert_eq!(g.nodes(), frozen.nodes());
+        assert_eq!(g.connections(), frozen.connections());
+    }
+
+    #[test]
+    fn change_activation_keeps_pinned_output_activation() {
+        let configuration = Configuration {
+            output_activation: Some(ActivationKind::Identity),
+            ..Default::default()
+        };
+        let mut g = Genome::new(2, 2);
+        g.set_output_activation(ActivationKind::Identity);
+        add_node(&mut g, &Default::default(), &mut thread_rng());
+
+        for _ in 0..100 {
+            mutate(
+                &MutationKind::ModifyActivation,
+                &mut g,
+                &configuration,
+                &mut thread_rng(),
+            );
+        }
+
+        assert!(g
+            .nodes()
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Output))
+            .all(|n| n.activation == ActivationKind::Identity));
+
+        // Without hidden nodes there is nothing left to mutate
+        let mut g = Genome::new(1, 1);
+        g.set_output_activation(ActivationKind::Logistic);
+        change_activation(&mut g, &configuration, &mut thread_rng());
+        assert_eq!(
+            g.nodes().get(1).unwrap().activation,
+            ActivationKind::Logistic
+        );
+    }
+
+    #[test]
+    fn growth_mutations_stop_at_the_node_cap() {
+        let configuration = Configuration {
+            max_nodes: Some(5),
+            ..Default::default()
+        };
+        let mut g = Genome::new(2, 2);
+
+        for _ in 0..50 {
+            add_node(&mut g, &configuration, &mut thread_rng());
+            add_constant(&mut g, &configuration, &mut thread_rng());
+        }
+
+        assert_eq!(g.nodes().len(), 5);
+    }
+
+    #[test]
+    fn growth_mutations_stop_at_the_connection_cap() {
+        let configuration = Configuration {
+            max_connections: Some(6),
+            ..Default::default()
+        };
+        let mut g = Genome::new(2, 2);
+
+        for _ in 0..50 {
+            add_node(&mut g, &configuration, &mut thread_rng());
+            add_connection(&mut g, &configuration, &mut thread_rng());
+            add_constant(&mut g, &configuration, &mut thread_rng());
+            reenable_connection(&mut g, &configuration, &mut thread_rng());
+        }
+
+        assert_eq!(g.enabled_connection_count(), 6);
+    }
+
+    #[test]
+    fn remove_node_stays_within_the_connection_cap() {
+        let configuration = Configuration {
+            max_connections: Some(6),
+            ..Default::default()
+        };
+        let mut builder = Genome::builder(3, 3);
+        let hidden = builder.add_hidden_node();
+        (0..3).for_each(|i| {
+            builder.connect(i, hidden, 1.).connect(hidden, 3 + i, 1.);
+        });
+        let mut g = builder.build().unwrap();
+
+        remove_node(&mut g, &configuration, &mut thread_rng());
+
+        assert_eq!(g.enabled_connection_count(), 6);
+        assert!(g
+            .connections()
+            .iter()
+            .all(|c| c.disabled || (c.from != hidden && c.to != hidden)));
+    }
+
+    #[test]
+    fn add_connection_samples_pairs_near_the_connection_cap() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let configuration = Configuration {
+            max_connections: Some(10),
+            ..Default::default()
+        };
+        // Enumerating the hundred million node pairs would take far longer than the test does
+        let mut g =
+            Genome::new_with_connectivity(5000, 5000, &InitialConnectivity::Unconnected, &mut rng);
+        (0..9).for_each(|i| {
+            g.add_connection(i, 5000 + i).unwrap();
+        });
+
+        add_connection(&mut g, &configuration, &mut rng);
+        assert_eq!(g.enabled_connection_count(), 10);
+
+        add_connection(&mut g, &configuration, &mut rng);
+        assert_eq!(g.enabled_connection_count(), 10);
+    }
 }
@@ -0,0 +1,148 @@
+use super::{
+    ConfigError, Configuration, EvaluationSummary, InitialConnectivity, Parallelism,
+    TotalStagnation,
+};
+use crate::activation::ActivationKind;
+use crate::aggregations::Aggregation;
+use crate::mutations::MutationKind;
+
+/// Generates a chainable setter for every listed configuration option, optional options take the
+/// inner value
+macro_rules! setters {
+    ($($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.configuration.$field = $field;
+                self
+            }
+        )*
+    };
+    (optional $($field:ident: $ty:ty),* $(,)?) => {
+        $(
+            pub fn $field(mut self, $field: $ty) -> Self {
+                self.configuration.$field = Some($field);
+                self
+            }
+        )*
+    };
+}
+
+/// Builds a configuration starting from the defaults and checks the options before handing it out
+#[derive(Debug, Default)]
+pub struct ConfigurationBuilder {
+    configuration: Configuration,
+}
+
+impl ConfigurationBuilder {
+    setters!(
+        max_generations: usize,
+        population_size: usize,
+        elitism: f64,
+        species_elitism_min_size: usize,
+        elitism_species: usize,
+        global_elites: usize,
+        stagnation_after: usize,
+        total_stagnation: TotalStagnation,
+        node_cost: f64,
+        connection_cost: f64,
+        inherit_disabled_prob: f64,
+        mutation_rate: f64,
+        interspecies_mating_rate: f64,
+        survival_ratio: f64,
+        weight_perturbation_prob: f64,
+        weight_perturbation_stddev: f64,
+        weight_reset_range: (f64, f64),
+        weight_bounds: (f64, f64),
+        bias_bounds: (f64, f64),
+        mutation_kinds: Vec<(MutationKind, usize)>,
+        initial_connectivity: InitialConnectivity,
+        add_bias_input: bool,
+        allowed_activations: Vec<ActivationKind>,
+        allowed_aggregations: Vec<Aggregation>,
+        recurrent: bool,
+        prefer_local_connections: bool,
+        parallelism: Parallelism,
+        deterministic_fitness: bool,
+        debug_invariants: bool,
+        evaluations_per_genome: usize,
+        evaluation_summary: EvaluationSummary,
+        distance_connection_disjoint_coefficient: f64,
+        distance_connection_weight_coeficcient: f64,
+        distance_connection_disabled_coefficient: f64,
+        distance_node_bias_coefficient: f64,
+        distance_node_activation_coefficient: f64,
+        distance_node_aggregation_coefficient: f64,
+        compatibility_threshold: f64,
+        compatibility_threshold_step: f64,
+        novelty_neighbors: usize,
+        novelty_archive_threshold: f64,
+    );
+
+    setters!(
+        optional complexity_penalty_floor: f64,
+        stagnation_hypermutation: f64,
+        output_activation: ActivationKind,
+        max_nodes: usize,
+        max_connections: usize,
+        fitness_goal: f64,
+        patience: usize,
+        compact_every: usize,
+        seed: u64,
+        target_species: usize,
+    );
+
+    /// Returns the configuration if every option is within its valid range
+    pub fn build(self) -> Result<Configuration, ConfigError> {
+        self.configuration.validate()?;
+
+        Ok(self.configuration)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_sets_options() {
+        let configuration = Configuration::builder()
+            .population_size(100)
+            .max_generations(500)
+            .fitness_goal(0.95)
+            .build()
+            .unwrap();
+
+        assert_eq!(configuration.population_size, 100);
+        assert_eq!(configuration.max_generations, 500);
+        assert_eq!(configuration.fitness_goal, Some(0.95));
+        assert_eq!(
+            configuration.elitism,
+            Configuration::default().elitism,
+            "options that are not set keep their defaults"
+        );
+    }
+
+    #[test]
+    fn builder_rejects_invalid_options() {
+        assert_eq!(
+            Configuration::builder().elitism(1.5).build().unwrap_err(),
+            ConfigError::OutOfUnitRange {
+                field: "elitism",
+                value: 1.5
+            }
+        );
+        assert!(Configuration::builder()
+            .mutation_rate(f64::NAN)
+            .build()
+            .is_err());
+        assert_eq!(
+            Configuration::builder()
+                .population_size(0)
+                .build()
+                .unwrap_err(),
+            ConfigError::NotPositive {
+                field: "population_size"
+            }
+        );
+    }
+}
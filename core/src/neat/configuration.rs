@@ -1,9 +1,17 @@
 use std::default::Default;
 
+use super::{ConfigError, ConfigurationBuilder};
+use crate::activation::ActivationKind;
+use crate::aggregations::Aggregation;
 use crate::mutations::MutationKind;
 
 /// Holds configuration options of the whole NEAT process
 #[derive(Debug)]
+#[cfg_attr(
+    feature = "config-toml",
+    derive(serde::Deserialize),
+    serde(default, deny_unknown_fields)
+)]
 pub struct Configuration {
     /// The generations limit of for the evolution process
     pub max_generations: usize,
@@ -14,30 +22,136 @@ pub struct Configuration {
     /// The ratio of champion individuals that are copied to the next generation
     pub elitism: f64,
 
+    /// Species with at least this many members always pass their champion on unchanged
+    pub species_elitism_min_size: usize,
+
     /// The minimum amount of species that need to exist after the removal of stagnated ones
     pub elitism_species: usize,
 
+    /// How many of the fittest genomes of the whole population are copied to the next generation
+    /// unchanged, whatever happens to their species, at most the population size
+    pub global_elites: usize,
+
     /// How many generations of not making progress is considered stagnation
     pub stagnation_after: usize,
 
+    /// What happens once every species has stagnated
+    pub total_stagnation: TotalStagnation,
+
+    /// Multiplies the mutation rate of species that went without improvement for more than half
+    /// of `stagnation_after` generations, that gives them a push before they are removed
+    pub stagnation_hypermutation: Option<f64>,
+
     /// The fitness cost of every node in the gene
     pub node_cost: f64,
 
-    /// The fitness cost of every connection in the gene
+    /// The fitness cost of every connection in the gene, when both costs are 0 the value of the
+    /// fitness function is used as is so it can penalize size on its own through
+    /// `Network::node_count` and `Network::connection_count`
     pub connection_cost: f64,
 
+    /// Lowest fitness the node and connection costs can push a genome to, without it large costs
+    /// outweigh the raw fitness and selection favours the smallest genomes however they perform
+    pub complexity_penalty_floor: Option<f64>,
+
+    /// Chance that a connection disabled in either parent is also disabled in the child
+    pub inherit_disabled_prob: f64,
+
     /// The mutation rate of offspring
     pub mutation_rate: f64,
 
+    /// Chance that the second parent of a child is picked from the whole population instead of the
+    /// species of the first one
+    pub interspecies_mating_rate: f64,
+
     /// The ratio of genomes that will survive to the next generation
     pub survival_ratio: f64,
 
-    /// The types of mutations available and their sampling weights
+    /// The probability that a weight mutation perturbs the weight instead of resetting it
+    pub weight_perturbation_prob: f64,
+
+    /// The standard deviation of the normal distribution used to perturb weights
+    pub weight_perturbation_stddev: f64,
+
+    /// The range a weight is uniformly sampled from when it's reset
+    pub weight_reset_range: (f64, f64),
+
+    /// The range connection weights are clamped to
+    pub weight_bounds: (f64, f64),
+
+    /// The range node biases are clamped to
+    pub bias_bounds: (f64, f64),
+
+    /// The types of mutations available and their sampling weights, `AddConstant` and
+    /// `ReenableConnection` aren't part of the defaults, push them onto `default_mutation_kinds()`
+    /// with a weight to use them
+    #[cfg_attr(
+        feature = "config-toml",
+        serde(deserialize_with = "deserialize_mutation_kinds")
+    )]
     pub mutation_kinds: Vec<(MutationKind, usize)>,
 
+    /// Pins the activation of every output node, `Identity` suits regression and `Logistic`
+    /// classification
+    pub output_activation: Option<ActivationKind>,
+
+    /// How the inputs and outputs of the initial genomes are connected
+    pub initial_connectivity: InitialConnectivity,
+
+    /// Gives the initial genomes an extra last input that is always 1, genomes and networks count
+    /// it in their inputs while `forward_pass` still takes only the real inputs
+    pub add_bias_input: bool,
+
+    /// The activations new nodes and activation mutations pick from
+    pub allowed_activations: Vec<ActivationKind>,
+
+    /// The aggregations new nodes and aggregation mutations pick from
+    pub allowed_aggregations: Vec<Aggregation>,
+
+    /// Allows evolving recurrent connections which read the values of the previous forward pass
+    pub recurrent: bool,
+
+    /// New connections favour nodes that are a similar number of layers away from the inputs,
+    /// which tends to grow more modular topologies
+    pub prefer_local_connections: bool,
+
+    /// Growth mutations stop adding nodes once a genome has this many
+    pub max_nodes: Option<usize>,
+
+    /// Growth mutations stop adding connections once a genome has this many enabled ones, within
+    /// a tenth of the cap new connections are picked from a few random node pairs
+    pub max_connections: Option<usize>,
+
     /// The process will stop if the fitness goal is reached
     pub fitness_goal: Option<f64>,
 
+    /// The process will stop if the best fitness doesn't improve for this many generations
+    pub patience: Option<usize>,
+
+    /// Compacts the offspring every this many generations, see `Genome::compact`
+    pub compact_every: Option<usize>,
+
+    /// Seeds the random number generator so that runs with the same configuration are reproducible
+    pub seed: Option<u64>,
+
+    /// How fitness evaluation, crossover and mutation are spread over threads
+    pub parallelism: Parallelism,
+
+    /// Reuses the fitness of genomes carried over unchanged from the last generation, it's only
+    /// correct when the fitness function always gives the same network the same fitness
+    pub deterministic_fitness: bool,
+
+    /// Checks after every generation that all genomes are valid, that the population has the
+    /// configured size and that every genome has a fitness, it panics on the first violation
+    pub debug_invariants: bool,
+
+    /// Evaluates every genome this many times, the evaluations are spread over threads on their
+    /// own so genomes with long episodes don't hold up a whole thread
+    pub evaluations_per_genome: usize,
+
+    /// How the fitnesses of the evaluations of a genome are combined
+    pub evaluation_summary: EvaluationSummary,
+
     /*
      * Genomic distance during speciation
      */
@@ -53,6 +167,133 @@ pub struct Configuration {
 
     /// A limit on how distant two genomes can be to belong to the same species
     pub compatibility_threshold: f64,
+
+    /// Adjusts the compatibility threshold every generation to approach this many species
+    pub target_species: Option<usize>,
+
+    /// How much the compatibility threshold changes per generation when targeting a species count
+    pub compatibility_threshold_step: f64,
+
+    /*
+     * Novelty search
+     */
+    /// How many nearest behaviors are averaged into the novelty score
+    pub novelty_neighbors: usize,
+
+    /// Behaviors with a novelty score above this are added to the archive
+    pub novelty_archive_threshold: f64,
+}
+
+impl Configuration {
+    /// Starts building a configuration from the defaults, the options are checked on build
+    pub fn builder() -> ConfigurationBuilder {
+        ConfigurationBuilder::default()
+    }
+
+    /// Checks that the options are within their valid ranges and that the evolution can produce
+    /// offspring with them
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let ratios = [
+            ("elitism", self.elitism),
+            ("mutation_rate", self.mutation_rate),
+            ("interspecies_mating_rate", self.interspecies_mating_rate),
+            ("survival_ratio", self.survival_ratio),
+            ("inherit_disabled_prob", self.inherit_disabled_prob),
+            ("weight_perturbation_prob", self.weight_perturbation_prob),
+        ];
+        if let Some((field, value)) = ratios.iter().find(|(_, value)| !(0. ..=1.).contains(value)) {
+            return Err(ConfigError::OutOfUnitRange {
+                field,
+                value: *value,
+            });
+        }
+
+        if let InitialConnectivity::PartialRandom(probability) = self.initial_connectivity {
+            if !(0. ..=1.).contains(&probability) {
+                return Err(ConfigError::OutOfUnitRange {
+                    field: "initial_connectivity",
+                    value: probability,
+                });
+            }
+        }
+
+        let lists = [
+            ("allowed_activations", self.allowed_activations.len()),
+            ("allowed_aggregations", self.allowed_aggregations.len()),
+        ];
+        if let Some((field, _)) = lists.iter().find(|(_, length)| *length == 0) {
+            return Err(ConfigError::Empty { field });
+        }
+
+        let sizes = [
+            ("population_size", self.population_size),
+            ("max_generations", self.max_generations),
+            ("compact_every", self.compact_every.unwrap_or(1)),
+            ("evaluations_per_genome", self.evaluations_per_genome),
+        ];
+        if let Some((field, _)) = sizes.iter().find(|(_, size)| *size == 0) {
+            return Err(ConfigError::NotPositive { field });
+        }
+
+        if self
+            .stagnation_hypermutation
+            .is_some_and(|factor| factor.is_nan() || factor <= 0.)
+        {
+            return Err(ConfigError::NotPositive {
+                field: "stagnation_hypermutation",
+            });
+        }
+
+        // Elites and parents are both picked among the survivors
+        if self.survival_ratio == 0. {
+            return Err(ConfigError::NoSurvivors);
+        }
+
+        if self.mutation_kinds.iter().all(|(_, weight)| *weight == 0) {
+            return Err(ConfigError::NoMutationKinds);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "config-toml")]
+impl Configuration {
+    /// Reads a configuration from TOML, omitted options keep their defaults while unknown ones are
+    /// an error
+    pub fn from_toml_str(source: &str) -> Result<Self, ConfigError> {
+        let configuration: Configuration =
+            toml::from_str(source).map_err(|error| ConfigError::Parse(error.to_string()))?;
+        configuration.validate()?;
+
+        Ok(configuration)
+    }
+
+    /// Reads a configuration from a TOML file, see `from_toml_str`
+    pub fn from_toml_file<P: AsRef<std::path::Path>>(path: P) -> Result<Self, ConfigError> {
+        let source =
+            std::fs::read_to_string(path).map_err(|error| ConfigError::Parse(error.to_string()))?;
+
+        Configuration::from_toml_str(&source)
+    }
+}
+
+/// Reads mutation kinds from a table of kind names and their sampling weights
+#[cfg(feature = "config-toml")]
+fn deserialize_mutation_kinds<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Vec<(MutationKind, usize)>, D::Error> {
+    use serde::de::value::StrDeserializer;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    BTreeMap::<String, usize>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, weight)| {
+            MutationKind::deserialize(StrDeserializer::<D::Error>::new(&name))
+                .map(|kind| (kind, weight))
+        })
+        .collect()
 }
 
 impl Default for Configuration {
@@ -61,14 +302,43 @@ impl Default for Configuration {
             max_generations: 1000,
             population_size: 150,
             elitism: 0.1,
+            species_elitism_min_size: 5,
             elitism_species: 3,
+            global_elites: 0,
             stagnation_after: 50,
+            total_stagnation: TotalStagnation::Continue,
+            stagnation_hypermutation: None,
             node_cost: 0.,
             connection_cost: 0.,
+            complexity_penalty_floor: None,
+            inherit_disabled_prob: 0.75,
             mutation_rate: 0.5,
+            interspecies_mating_rate: 0.001,
             survival_ratio: 0.5,
+            weight_perturbation_prob: 0.1,
+            weight_perturbation_stddev: 1.,
+            weight_reset_range: (-1., 1.),
+            weight_bounds: (-1., 1.),
+            bias_bounds: (-1., 1.),
             mutation_kinds: default_mutation_kinds(),
+            output_activation: None,
+            initial_connectivity: InitialConnectivity::FullyConnected,
+            add_bias_input: false,
+            allowed_activations: ActivationKind::evolvable(),
+            allowed_aggregations: Aggregation::evolvable(),
+            recurrent: false,
+            prefer_local_connections: false,
+            max_nodes: None,
+            max_connections: None,
             fitness_goal: None,
+            patience: None,
+            compact_every: None,
+            seed: None,
+            parallelism: Parallelism::Auto,
+            deterministic_fitness: false,
+            debug_invariants: false,
+            evaluations_per_genome: 1,
+            evaluation_summary: EvaluationSummary::Mean,
             distance_connection_disjoint_coefficient: 1.,
             distance_connection_weight_coeficcient: 0.5,
             distance_connection_disabled_coefficient: 0.5,
@@ -76,6 +346,64 @@ impl Default for Configuration {
             distance_node_activation_coefficient: 0.33,
             distance_node_aggregation_coefficient: 0.33,
             compatibility_threshold: 3.,
+            target_species: None,
+            compatibility_threshold_step: 0.1,
+            novelty_neighbors: 15,
+            novelty_archive_threshold: 1.,
+        }
+    }
+}
+
+/// How the inputs and outputs of a new genome are connected
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "config-toml", derive(serde::Deserialize))]
+pub enum InitialConnectivity {
+    /// Every input is connected to every output
+    FullyConnected,
+    /// There are no connections, the outputs only emit their bias until connections evolve
+    Unconnected,
+    /// Every input is connected to every output with the given probability
+    PartialRandom(f64),
+}
+
+/// The response to every species stagnating at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config-toml", derive(serde::Deserialize))]
+pub enum TotalStagnation {
+    /// Keeps evolving, stagnated species are still removed as usual
+    Continue,
+    /// Stops the evolution early
+    Stop,
+    /// Keeps the two fittest genomes and replaces the rest of the population with new genomes
+    Repopulate,
+}
+
+/// How the work of a generation is spread over threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config-toml", derive(serde::Deserialize))]
+pub enum Parallelism {
+    /// Uses the global rayon thread pool
+    Auto,
+    /// Uses a dedicated thread pool with this many threads
+    Threads(usize),
+    /// Runs everything on the calling thread, for fitness functions that must not leave it
+    Sequential,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "config-toml", derive(serde::Deserialize))]
+pub enum EvaluationSummary {
+    /// Averages the fitnesses of the evaluations
+    Mean,
+    /// Keeps the worst fitness, that rewards genomes which do well every time
+    Min,
+}
+
+impl EvaluationSummary {
+    pub fn summarize(&self, fitnesses: &[f64]) -> f64 {
+        match self {
+            EvaluationSummary::Mean => fitnesses.iter().sum::<f64>() / fitnesses.len() as f64,
+            EvaluationSummary::Min => fitnesses.iter().cloned().fold(f64::MAX, f64::min),
         }
     }
 }
@@ -94,3 +422,124 @@ pub fn default_mutation_kinds() -> Vec<(MutationKind, usize)> {
         (ModifyAggregation, 10),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_configuration_is_valid() {
+        assert_eq!(Configuration::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn opt_in_mutations_are_left_out_of_the_defaults() {
+        let kinds: Vec<MutationKind> = default_mutation_kinds()
+            .into_iter()
+            .map(|(kind, _)| kind)
+            .collect();
+
+        assert!(!kinds.contains(&MutationKind::AddConstant));
+        assert!(!kinds.contains(&MutationKind::ReenableConnection));
+    }
+
+    #[test]
+    fn evaluation_summaries() {
+        let fitnesses = [3., 1., 2.];
+
+        assert!((EvaluationSummary::Mean.summarize(&fitnesses) - 2.).abs() < f64::EPSILON);
+        assert!((EvaluationSummary::Min.summarize(&fitnesses) - 1.).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn validate_rejects_unusable_configurations() {
+        let no_survivors = Configuration {
+            survival_ratio: 0.,
+            ..Default::default()
+        };
+        assert_eq!(no_survivors.validate(), Err(ConfigError::NoSurvivors));
+
+        let no_mutations = Configuration {
+            mutation_kinds: vec![],
+            ..Default::default()
+        };
+        assert_eq!(no_mutations.validate(), Err(ConfigError::NoMutationKinds));
+
+        let no_activations = Configuration {
+            allowed_activations: vec![],
+            ..Default::default()
+        };
+        assert_eq!(
+            no_activations.validate(),
+            Err(ConfigError::Empty {
+                field: "allowed_activations"
+            })
+        );
+
+        let no_aggregations = Configuration {
+            allowed_aggregations: vec![],
+            ..Default::default()
+        };
+        assert_eq!(
+            no_aggregations.validate(),
+            Err(ConfigError::Empty {
+                field: "allowed_aggregations"
+            })
+        );
+
+        let ratio_out_of_range = Configuration {
+            survival_ratio: -0.5,
+            ..Default::default()
+        };
+        assert!(matches!(
+            ratio_out_of_range.validate(),
+            Err(ConfigError::OutOfUnitRange {
+                field: "survival_ratio",
+                ..
+            })
+        ));
+    }
+
+    #[cfg(feature = "config-toml")]
+    #[test]
+    fn configuration_from_toml() {
+        let configuration = Configuration::from_toml_str(
+            r#"
+            population_size = 50
+            fitness_goal = 0.95
+            weight_bounds = [-2.0, 2.0]
+            parallelism = "Sequential"
+
+            [mutation_kinds]
+            AddNode = 5
+            ModifyWeight = 20
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(configuration.population_size, 50);
+        assert_eq!(configuration.fitness_goal, Some(0.95));
+        assert_eq!(configuration.weight_bounds, (-2., 2.));
+        assert_eq!(configuration.parallelism, Parallelism::Sequential);
+        assert_eq!(
+            configuration.mutation_kinds,
+            vec![(MutationKind::AddNode, 5), (MutationKind::ModifyWeight, 20)]
+        );
+        assert_eq!(
+            configuration.max_generations,
+            Configuration::default().max_generations
+        );
+    }
+
+    #[cfg(feature = "config-toml")]
+    #[test]
+    fn unknown_toml_keys_are_rejected() {
+        let error = Configuration::from_toml_str("population = 50").unwrap_err();
+
+        assert!(matches!(error, ConfigError::Parse(message) if message.contains("population")));
+        assert!(matches!(
+            Configuration::from_toml_str("[mutation_kinds]\nGrowWings = 1"),
+            Err(ConfigError::Parse(_))
+        ));
+    }
+}
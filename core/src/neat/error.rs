@@ -0,0 +1,42 @@
+use std::fmt;
+
+/// A configuration option that is set to a value the evolution can't work with
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConfigError {
+    /// The ratio or probability option must be within [0, 1]
+    OutOfUnitRange { field: &'static str, value: f64 },
+    /// The option must be greater than zero
+    NotPositive { field: &'static str },
+    /// No genome survives selection so there are no parents for the offspring
+    NoSurvivors,
+    /// The list option must have at least one element
+    Empty { field: &'static str },
+    /// There are no mutation kinds with a positive sampling weight
+    NoMutationKinds,
+    /// The configuration couldn't be read or parsed
+    Parse(String),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConfigError::OutOfUnitRange { field, value } => {
+                write!(f, "{} must be within [0, 1] but is {}", field, value)
+            }
+            ConfigError::NotPositive { field } => {
+                write!(f, "{} must be greater than zero", field)
+            }
+            ConfigError::Empty { field } => write!(f, "{} must not be empty", field),
+            ConfigError::NoSurvivors => write!(
+                f,
+                "survival_ratio of zero leaves no parents for the offspring"
+            ),
+            ConfigError::NoMutationKinds => {
+                write!(f, "mutation_kinds has no kind with a positive weight")
+            }
+            ConfigError::Parse(message) => write!(f, "invalid configuration file, {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
@@ -0,0 +1,71 @@
+use rayon::prelude::*;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+
+use crate::Parallelism;
+
+/// Runs the parallelizable steps of the evolution process the way the configuration asks for
+pub struct Executor {
+    parallelism: Parallelism,
+    pool: Option<ThreadPool>,
+}
+
+impl Executor {
+    pub fn new(parallelism: Parallelism) -> Self {
+        let pool = match parallelism {
+            Parallelism::Threads(count) => Some(
+                ThreadPoolBuilder::new()
+                    .num_threads(count)
+                    .build()
+                    .expect("Could not build the thread pool"),
+            ),
+            _ => None,
+        };
+
+        Executor { parallelism, pool }
+    }
+
+    /// Maps every item and keeps their order, sequential execution runs on the calling thread
+    pub fn map<T, U, F>(&self, items: Vec<T>, f: F) -> Vec<U>
+    where
+        T: Send,
+        U: Send,
+        F: Fn(T) -> U + Sync + Send,
+    {
+        match (&self.parallelism, &self.pool) {
+            (Parallelism::Sequential, _) => items.into_iter().map(f).collect(),
+            (_, Some(pool)) => pool.install(|| items.into_par_iter().map(f).collect()),
+            _ => items.into_par_iter().map(f).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_parallelism_keeps_the_order() {
+        let items: Vec<usize> = (0..100).collect();
+        let expected: Vec<usize> = items.iter().map(|i| i * 2).collect();
+
+        for parallelism in [
+            Parallelism::Auto,
+            Parallelism::Threads(2),
+            Parallelism::Sequential,
+        ] {
+            let executor = Executor::new(parallelism);
+
+            assert_eq!(executor.map(items.clone(), |i| i * 2), expected);
+        }
+    }
+
+    #[test]
+    fn sequential_runs_on_the_calling_thread() {
+        let executor = Executor::new(Parallelism::Sequential);
+        let caller = std::thread::current().id();
+
+        let threads = executor.map(vec![(); 10], |_| std::thread::current().id());
+
+        assert!(threads.iter().all(|thread| *thread == caller));
+    }
+}
@@ -0,0 +1,2188 @@
+mod executor;
+mod novelty;
+mod pareto;
+mod reporter;
+mod speciation;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::{ConfigError, Configuration, Parallelism, TotalStagnation};
+use crate::genome::innovation::Innovations;
+use crate::genome::{crossover_with_rng, Genome, GenomeError, GenomeId};
+use crate::mutations::{clamp_to_bounds, MutationKind};
+use crate::network::Network;
+use crate::reporting::{self, Generation, Population};
+use crate::speciation::{apportion, DistanceMetric, SpeciesSet, SpeciesStat};
+use executor::Executor;
+use novelty::NoveltyArchive;
+use pareto::pareto_scores;
+use reporter::Reporter;
+use speciation::GenomeBank;
+
+type BehaviorFn = Box<dyn Fn(&mut Network) -> Vec<f64> + Sync + Send>;
+type ObjectivesFn = Box<dyn Fn(&mut Network) -> Vec<f64> + Sync + Send>;
+type Schedule = Box<dyn FnMut(usize, &mut Configuration)>;
+
+pub struct NEAT {
+    inputs: usize,
+    outputs: usize,
+    fitness_fn: Box<dyn Fn(&mut Network) -> f64 + Sync + Send>,
+    behavior_fn: Option<BehaviorFn>,
+    novelty_archive: NoveltyArchive,
+    objectives_fn: Option<ObjectivesFn>,
+    objectives: BTreeMap<GenomeId, Vec<f64>>,
+    pub genomes: GenomeBank,
+    pub species_set: SpeciesSet,
+    configuration: Rc<RefCell<Configuration>>,
+    reporter: RefCell<Reporter>,
+    schedules: Vec<Schedule>,
+    lifecycle_reporter: RefCell<Option<Box<dyn reporting::Reporter>>>,
+    rng: RefCell<StdRng>,
+    innovations: RefCell<Innovations>,
+    executor: Executor,
+    generation: usize,
+    stop_reason: Option<StopReason>,
+    seed_genomes: Vec<Genome>,
+    cancel: Option<Arc<AtomicBool>>,
+    started: Option<Instant>,
+}
+
+/// Why the evolution process stopped
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// The best genome reached the configured fitness goal
+    FitnessGoal,
+    /// The best fitness didn't improve for the configured number of generations
+    Patience,
+    /// The generations limit was reached
+    MaxGenerations,
+    /// Every species stagnated and the configuration asked to stop
+    Stagnation,
+    /// The cancellation flag was set
+    Cancelled,
+}
+
+/// The smallest improvement of the best fitness that resets the patience counter
+const PATIENCE_EPSILON: f64 = 1e-9;
+
+impl NEAT {
+    pub fn new<F>(inputs: usize, outputs: usize, fitness_fn: F) -> Self
+    where
+        F: Fn(&mut Network) -> f64 + Sync + Send + 'static,
+    {
+        let configuration: Rc<RefCell<Configuration>> = Default::default();
+
+        NEAT {
+            inputs,
+            outputs,
+            fitness_fn: Box::new(fitness_fn),
+            behavior_fn: None,
+            novelty_archive: NoveltyArchive::new(),
+            objectives_fn: None,
+            objectives: BTreeMap::new(),
+            genomes: GenomeBank::new(configuration.clone()),
+            species_set: SpeciesSet::new(configuration.clone()),
+            configuration,
+            reporter: RefCell::new(Reporter::new()),
+            schedules: vec![],
+            lifecycle_reporter: RefCell::new(None),
+            rng: RefCell::new(StdRng::from_entropy()),
+            innovations: RefCell::new(Innovations::new()),
+            executor: Executor::new(Parallelism::Auto),
+            generation: 0,
+            stop_reason: None,
+            seed_genomes: vec![],
+            cancel: None,
+            started: None,
+        }
+    }
+
+    /// Rewards behavioral novelty instead of fitness, the behavior function describes what a
+    /// network does and genomes are scored by how far that is from their nearest neighbors in the
+    /// population and the archive
+    pub fn with_novelty<F>(inputs: usize, outputs: usize, behavior_fn: F) -> Self
+    where
+        F: Fn(&mut Network) -> Vec<f64> + Sync + Send + 'static,
+    {
+        let mut system = NEAT::new(inputs, outputs, |_| 0.);
+        system.behavior_fn = Some(Box::new(behavior_fn));
+
+        system
+    }
+
+    /// Optimizes several objectives at once, every one of them is maximized and genomes are scored
+    /// by their nondominated front and crowding distance, node and connection costs don't apply so
+    /// complexity should be added as an objective if it matters
+    pub fn with_objectives<F>(inputs: usize, outputs: usize, objectives_fn: F) -> Self
+    where
+        F: Fn(&mut Network) -> Vec<f64> + Sync + Send + 'static,
+    {
+        let mut system = NEAT::new(inputs, outputs, |_| 0.);
+        system.objectives_fn = Some(Box::new(objectives_fn));
+
+        system
+    }
+
+    pub fn set_configuration(&mut self, config: Configuration) {
+        *self.configuration.borrow_mut() = config;
+    }
+
+    /// Adds genomes to the initial population, `start` fills the rest of it with new genomes and
+    /// drops the seeds that don't fit into the population size, the genomes must have the same
+    /// input and output counts as the system and a valid structure
+    pub fn seed_population(&mut self, genomes: Vec<Genome>) -> Result<(), GenomeError> {
+        let expected_inputs = self.inputs + self.configuration.borrow().add_bias_input as usize;
+
+        for genome in &genomes {
+            if genome.input_count() != expected_inputs {
+                return Err(GenomeError::InputCountMismatch {
+                    expected: expected_inputs,
+                    found: genome.input_count(),
+                });
+            }
+            if genome.output_count() != self.outputs {
+                return Err(GenomeError::OutputCountMismatch {
+                    expected: self.outputs,
+                    found: genome.output_count(),
+                });
+            }
+            if let Err(mut errors) = genome.validate() {
+                return Err(errors.remove(0));
+            }
+        }
+
+        self.seed_genomes.extend(genomes);
+
+        Ok(())
+    }
+
+    /// Runs the evolution and returns the best network and its fitness, panics if the
+    /// configuration is invalid
+    pub fn start(&mut self) -> (Network, f64) {
+        self.try_start()
+            .unwrap_or_else(|error| panic!("Invalid configuration: {}", error))
+    }
+
+    /// Runs the evolution like `start` until the flag is set, it's checked at the start of every
+    /// generation and the best network found so far is returned
+    pub fn start_with_cancel(&mut self, cancel: Arc<AtomicBool>) -> (Network, f64) {
+        self.cancel = Some(cancel);
+        let result = self.try_start();
+        self.cancel = None;
+
+        result.unwrap_or_else(|error| panic!("Invalid configuration: {}", error))
+    }
+
+    /// Runs the evolution like `start` but returns an error if the configuration is invalid
+    pub fn try_start(&mut self) -> Result<(Network, f64), ConfigError> {
+        self.configuration.borrow().validate()?;
+        self.started = Some(Instant::now());
+
+        let (population_size, max_generations, seed, parallelism) = {
+            let config = self.configuration.borrow();
+
+            (
+                config.population_size,
+                config.max_generations,
+                config.seed,
+                config.parallelism,
+            )
+        };
+
+        self.executor = Executor::new(parallelism);
+
+        if let Some(seed) = seed {
+            *self.rng.borrow_mut() = StdRng::seed_from_u64(seed);
+        }
+        *self.innovations.borrow_mut() = Innovations::new();
+
+        // Seeds come first, new genomes make up the rest of the initial population
+        let mut seed_genomes = std::mem::take(&mut self.seed_genomes);
+        seed_genomes.truncate(population_size);
+        seed_genomes.iter_mut().for_each(|genome| {
+            genome.renew_id(&mut *self.rng.borrow_mut());
+            self.prepare_genome(genome);
+        });
+
+        let new_genomes: Vec<Genome> = (seed_genomes.len()..population_size)
+            .map(|_| self.new_genome())
+            .collect();
+
+        seed_genomes
+            .into_iter()
+            .chain(new_genomes)
+            .for_each(|genome| self.genomes.add_genome(genome));
+
+        self.test_fitness();
+
+        let mut best_fitness_so_far = f64::MIN;
+        let mut generations_without_improvement = 0;
+        self.stop_reason = None;
+
+        for i in 1..=max_generations {
+            if self
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::SeqCst))
+            {
+                self.stop_reason = Some(StopReason::Cancelled);
+                break;
+            }
+
+            self.generation = i;
+            let generation = Generation {
+                number: i,
+                started: Instant::now(),
+            };
+            self.report_with(|reporter, _| reporter.on_generation_start(&generation));
+
+            if !self.schedules.is_empty() {
+                let mut configuration = self.configuration.borrow_mut();
+                self.schedules
+                    .iter_mut()
+                    .for_each(|schedule| schedule(i, &mut configuration));
+
+                configuration.validate()?;
+            }
+
+            let current_genome_ids: Vec<GenomeId> =
+                self.genomes.genomes().keys().cloned().collect();
+            let previous_and_current_genomes = self
+                .genomes
+                .genomes()
+                .iter()
+                .chain(self.genomes.previous_genomes())
+                .map(|(genome_id, genome)| (genome_id.clone(), genome.clone()))
+                .collect();
+
+            let mut stagnant_species_ids = self.species_set.speciate(
+                i,
+                &current_genome_ids,
+                &previous_and_current_genomes,
+                self.genomes.fitnesses(),
+            );
+
+            let total_stagnation = self.configuration.borrow().total_stagnation;
+            let totally_stagnant =
+                total_stagnation != TotalStagnation::Continue && self.species_set.is_stagnant(i);
+
+            // Species protected from removal are reported as well when all of them stagnated
+            if totally_stagnant {
+                stagnant_species_ids.extend(self.species_set.species().keys());
+            }
+
+            self.report_with(|reporter, system| {
+                let species_stats = system.species_stats();
+
+                stagnant_species_ids.iter().for_each(|species_id| {
+                    reporter.on_species_stagnant(*species_id, &species_stats)
+                });
+
+                if species_stats.is_empty() || totally_stagnant {
+                    reporter.on_extinction();
+                }
+            });
+
+            if totally_stagnant && total_stagnation == TotalStagnation::Stop {
+                self.stop_reason = Some(StopReason::Stagnation);
+                self.report_with(|reporter, system| {
+                    reporter.on_generation_end(
+                        &generation,
+                        &system.population_view(),
+                        &system.species_stats(),
+                    );
+                });
+
+                break;
+            }
+
+            if totally_stagnant && total_stagnation == TotalStagnation::Repopulate {
+                self.repopulate();
+
+                let current_genome_ids: Vec<GenomeId> =
+                    self.genomes.genomes().keys().cloned().collect();
+                let current_genomes = self
+                    .genomes
+                    .genomes()
+                    .iter()
+                    .map(|(genome_id, genome)| (*genome_id, genome.clone()))
+                    .collect();
+
+                self.species_set.speciate(
+                    i,
+                    &current_genome_ids,
+                    &current_genomes,
+                    self.genomes.fitnesses(),
+                );
+            }
+
+            // Species halfway to stagnating mutate more to get out of the rut
+            let (stagnation_after, stagnation_hypermutation) = {
+                let config = self.configuration.borrow();
+
+                (config.stagnation_after, config.stagnation_hypermutation)
+            };
+            let hypermutating_species_ids: Vec<usize> = if stagnation_hypermutation.is_some() {
+                self.species_stats()
+                    .iter()
+                    .filter(|stat| {
+                        stat.generations_since_improvement as f64 > stagnation_after as f64 / 2.
+                    })
+                    .map(|stat| stat.id)
+                    .collect()
+            } else {
+                vec![]
+            };
+
+            self.report_with(|reporter, system| {
+                let species_stats = system.species_stats();
+
+                hypermutating_species_ids.iter().for_each(|species_id| {
+                    reporter.on_species_hypermutation(*species_id, &species_stats)
+                });
+            });
+
+            let (
+                elitism,
+                species_elitism_min_size,
+                population_size,
+                global_elites,
+                mutation_rate,
+                survival_ratio,
+                interspecies_mating_rate,
+            ) = {
+                let config = self.configuration.borrow();
+
+                (
+                    config.elitism,
+                    config.species_elitism_min_size,
+                    config.population_size,
+                    usize::min(config.global_elites, config.population_size),
+                    config.mutation_rate,
+                    config.survival_ratio,
+                    config.interspecies_mating_rate,
+                )
+            };
+
+            let mut rng = self.rng.borrow_mut();
+            let config = self.configuration.borrow();
+            let configuration: &Configuration = &config;
+
+            // Large enough species always keep their champion so they need at least one child
+            let (shares, minimums): (Vec<f64>, Vec<usize>) = self
+                .species_set
+                .species()
+                .values()
+                .map(|species| {
+                    let minimum = if species.members.len() >= species_elitism_min_size {
+                        1
+                    } else {
+                        0
+                    };
+
+                    (species.adjusted_fitness.unwrap(), minimum)
+                })
+                .unzip();
+            let offspring_counts = apportion(&shares, &minimums, population_size - global_elites);
+
+            let global_ids_and_fitnesses: Vec<(GenomeId, f64)> = self
+                .genomes
+                .fitnesses()
+                .iter()
+                .map(|(genome_id, fitness)| (*genome_id, *fitness))
+                .collect();
+
+            // The fittest genomes of the whole population are copied before species reproduce,
+            // species don't copy them a second time
+            let mut global_elite_ids = global_ids_and_fitnesses.clone();
+            global_elite_ids.sort_by(|a, b| b.1.total_cmp(&a.1));
+            let global_elite_ids: Vec<GenomeId> = global_elite_ids
+                .into_iter()
+                .take(global_elites)
+                .map(|(genome_id, _)| genome_id)
+                .collect();
+            let global_elite_children: Vec<Genome> = global_elite_ids
+                .iter()
+                .map(|genome_id| self.genomes.genomes().get(genome_id).unwrap().clone())
+                .collect();
+
+            // Children are paired with how many of their connections they got from their parents,
+            // the ones after those were added by mutations and still need innovation numbers
+            let (mut offspring, mut known_connections): (Vec<Genome>, Vec<usize>) = self
+                .species_set
+                .species()
+                .iter()
+                .zip(offspring_counts)
+                .flat_map(|((species_id, species), offspring_count)| {
+                    let mutation_rate = match stagnation_hypermutation {
+                        Some(factor) if hypermutating_species_ids.contains(species_id) => {
+                            f64::min(mutation_rate * factor, 1.)
+                        }
+                        _ => mutation_rate,
+                    };
+
+                    let mut elites_count: usize =
+                        (offspring_count as f64 * elitism).ceil() as usize;
+
+                    if species.members.len() >= species_elitism_min_size {
+                        elites_count = usize::max(elites_count, 1);
+                    }
+                    elites_count = usize::min(elites_count, offspring_count);
+
+                    let mut member_ids_and_fitnesses: Vec<(GenomeId, f64)> = species
+                        .members
+                        .iter()
+                        .map(|member_id| {
+                            (
+                                *member_id,
+                                *self.genomes.fitnesses().get(member_id).unwrap(),
+                            )
+                        })
+                        .collect();
+
+                    // Fittest members first
+                    member_ids_and_fitnesses.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+                    // Pick survivors, the fittest member always survives so there is a parent
+                    let surviving_count: usize = usize::max(
+                        (member_ids_and_fitnesses.len() as f64 * survival_ratio).ceil() as usize,
+                        1,
+                    );
+                    member_ids_and_fitnesses.truncate(surviving_count);
+
+                    if member_ids_and_fitnesses.is_empty() {
+                        return vec![];
+                    }
+
+                    let elite_children: Vec<Genome> =
+                        (0..usize::min(elites_count, member_ids_and_fitnesses.len()))
+                            .map(|elite_index| {
+                                let (elite_genome_id, _) =
+                                    member_ids_and_fitnesses.get(elite_index).unwrap();
+
+                                elite_genome_id
+                            })
+                            .filter(|elite_genome_id| !global_elite_ids.contains(elite_genome_id))
+                            .map(|elite_genome_id| {
+                                self.genomes.genomes().get(elite_genome_id).unwrap().clone()
+                            })
+                            .collect();
+
+                    // Species with fewer survivors than elites make up for them with children
+                    let nonelites_count: usize = offspring_count - elite_children.len();
+
+                    let crossover_data: Vec<(&Genome, f64, &Genome, f64, u64)> = (0
+                        ..nonelites_count)
+                        .map(|_| {
+                            let parent_a_index =
+                                rng.gen::<usize>() % member_ids_and_fitnesses.len();
+
+                            // Occasionally the second parent comes from the whole population
+                            let parent_b_pool = if rng.gen::<f64>() < interspecies_mating_rate {
+                                &global_ids_and_fitnesses
+                            } else {
+                                &member_ids_and_fitnesses
+                            };
+                            let parent_b_index = rng.gen::<usize>() % parent_b_pool.len();
+
+                            let (parent_a_id, parent_a_fitness) =
+                                member_ids_and_fitnesses.get(parent_a_index).unwrap();
+                            let (parent_b_id, parent_b_fitness) =
+                                parent_b_pool.get(parent_b_index).unwrap();
+
+                            let parent_a_genome = self.genomes.genomes().get(parent_a_id).unwrap();
+                            let parent_b_genome = self.genomes.genomes().get(parent_b_id).unwrap();
+
+                            (
+                                parent_a_genome,
+                                *parent_a_fitness,
+                                parent_b_genome,
+                                *parent_b_fitness,
+                                rng.gen::<u64>(),
+                            )
+                        })
+                        .collect();
+
+                    // Every child gets its own generator seeded from the main one, that way the
+                    // results don't depend on how rayon schedules the work
+                    let crossover_children: Vec<Genome> = self
+                        .executor
+                        .map(
+                            crossover_data,
+                            |(parent_a, fitness_a, parent_b, fitness_b, seed)| {
+                                crossover_with_rng(
+                                    (parent_a, fitness_a),
+                                    (parent_b, fitness_b),
+                                    configuration,
+                                    &mut StdRng::seed_from_u64(seed),
+                                )
+                            },
+                        )
+                        .into_iter()
+                        .flatten()
+                        .collect();
+
+                    let mutations_for_children: Vec<Option<(MutationKind, u64)>> =
+                        crossover_children
+                            .iter()
+                            .map(|_| {
+                                if rng.gen::<f64>() < mutation_rate {
+                                    Some((self.pick_mutation(&mut *rng), rng.gen::<u64>()))
+                                } else {
+                                    None
+                                }
+                            })
+                            .collect();
+
+                    let crossover_children: Vec<(Genome, usize)> = self.executor.map(
+                        crossover_children
+                            .into_iter()
+                            .zip(mutations_for_children)
+                            .collect(),
+                        |(mut child, maybe_mutation)| {
+                            let known = child.connections().len();
+                            if let Some((mutation, seed)) = maybe_mutation {
+                                child.mutate_with_rng(
+                                    &mutation,
+                                    configuration,
+                                    &mut StdRng::seed_from_u64(seed),
+                                );
+                            }
+
+                            (child, known)
+                        },
+                    );
+
+                    elite_children
+                        .into_iter()
+                        .map(|elite| {
+                            let known = elite.connections().len();
+                            (elite, known)
+                        })
+                        .chain(crossover_children)
+                        .collect::<Vec<(Genome, usize)>>()
+                })
+                .unzip();
+            known_connections.splice(
+                0..0,
+                global_elite_children
+                    .iter()
+                    .map(|elite| elite.connections().len()),
+            );
+            offspring.splice(0..0, global_elite_children);
+
+            drop(config);
+            drop(rng);
+
+            self.innovations
+                .borrow_mut()
+                .number_offspring(&mut offspring, &known_connections);
+
+            let compact_every = self.configuration.borrow().compact_every;
+            if compact_every.is_some_and(|every| i % every == 0) {
+                offspring.iter_mut().for_each(Genome::compact);
+            }
+
+            self.genomes.clear();
+            offspring
+                .into_iter()
+                .for_each(|genome| self.genomes.add_genome(genome));
+
+            self.report_with(|reporter, system| {
+                reporter.on_reproduction_end(&system.population_view(), &system.species_stats());
+            });
+
+            self.test_fitness();
+
+            if self.configuration.borrow().debug_invariants {
+                self.check_invariants();
+            }
+
+            self.report_with(|reporter, system| {
+                let (_, best_genome, _) = system.get_best();
+                reporter.on_evaluation_end(
+                    &system.population_view(),
+                    &system.species_stats(),
+                    best_genome,
+                );
+            });
+
+            let (_, _, best_fitness) = self.get_best();
+            if best_fitness > best_fitness_so_far + PATIENCE_EPSILON {
+                best_fitness_so_far = best_fitness;
+                generations_without_improvement = 0;
+            } else {
+                generations_without_improvement += 1;
+            }
+
+            self.stop_reason = {
+                let config = self.configuration.borrow();
+
+                if config.fitness_goal.is_some_and(|goal| best_fitness >= goal) {
+                    Some(StopReason::FitnessGoal)
+                } else if config
+                    .patience
+                    .is_some_and(|patience| generations_without_improvement >= patience)
+                {
+                    Some(StopReason::Patience)
+                } else if i == max_generations {
+                    Some(StopReason::MaxGenerations)
+                } else {
+                    None
+                }
+            };
+
+            if self.stop_reason == Some(StopReason::FitnessGoal) {
+                self.report_with(|reporter, system| {
+                    let (_, best_genome, _) = system.get_best();
+                    reporter.on_solution_found(&generation, &system.population_view(), best_genome);
+                });
+            }
+            self.report_with(|reporter, system| {
+                reporter.on_generation_end(
+                    &generation,
+                    &system.population_view(),
+                    &system.species_stats(),
+                );
+            });
+
+            self.reporter.borrow_mut().report(i, self);
+
+            if self.stop_reason.is_some() {
+                break;
+            }
+        }
+
+        let (_, best_genome, best_fitness) = self.get_best();
+        Ok((Network::from(best_genome), best_fitness))
+    }
+
+    /// Creates a genome of the initial topology
+    fn new_genome(&self) -> Genome {
+        let configuration = self.configuration.borrow();
+
+        let mut genome = Genome::new_with_configuration(
+            self.inputs,
+            self.outputs,
+            &configuration,
+            &mut *self.rng.borrow_mut(),
+        );
+        drop(configuration);
+        self.prepare_genome(&mut genome);
+
+        genome
+    }
+
+    /// Fits a genome of the initial population to the configured bounds and output activation and
+    /// numbers its connections for the run, seeds go through it as well as new genomes
+    fn prepare_genome(&self, genome: &mut Genome) {
+        let configuration = self.configuration.borrow();
+
+        clamp_to_bounds(genome, &configuration);
+        if let Some(activation) = &configuration.output_activation {
+            genome.set_output_activation(activation.clone());
+        }
+        self.innovations.borrow_mut().register(genome);
+    }
+
+    /// Keeps the two fittest genomes, fills the rest of the population with new genomes and
+    /// evaluates them, the species are forgotten so they form again from the new population
+    fn repopulate(&mut self) {
+        let population_size = self.configuration.borrow().population_size;
+
+        let mut ids_and_fitnesses: Vec<(GenomeId, f64)> = self
+            .genomes
+            .fitnesses()
+            .iter()
+            .map(|(genome_id, fitness)| (*genome_id, *fitness))
+            .collect();
+        ids_and_fitnesses.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        let survivors: Vec<Genome> = ids_and_fitnesses
+            .iter()
+            .take(usize::min(2, population_size))
+            .map(|(genome_id, _)| self.genomes.genomes().get(genome_id).unwrap().clone())
+            .collect();
+        let new_genomes: Vec<Genome> = (survivors.len()..population_size)
+            .map(|_| self.new_genome())
+            .collect();
+
+        self.genomes.clear();
+        survivors
+            .into_iter()
+            .chain(new_genomes)
+            .for_each(|genome| self.genomes.add_genome(genome));
+        self.species_set.clear();
+
+        self.test_fitness();
+    }
+
+    /// Panics when the current generation isn't what reproduction and evaluation should leave
+    /// behind
+    fn check_invariants(&self) {
+        let population_size = self.configuration.borrow().population_size;
+        let genomes = self.genomes.genomes();
+
+        assert_eq!(
+            genomes.len(),
+            population_size,
+            "Generation {} has {} genomes instead of {}",
+            self.generation,
+            genomes.len(),
+            population_size
+        );
+
+        genomes.iter().for_each(|(genome_id, genome)| {
+            if let Err(errors) = genome.validate() {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+
+                panic!(
+                    "Genome {} of generation {} is invalid, {}",
+                    genome_id,
+                    self.generation,
+                    errors.join(", ")
+                );
+            }
+
+            assert!(
+                self.genomes.fitnesses().contains_key(genome_id),
+                "Genome {} of generation {} has no fitness",
+                genome_id,
+                self.generation
+            );
+        });
+    }
+
+    fn test_fitness(&mut self) {
+        let ids_and_networks: Vec<(GenomeId, Network)> = self
+            .genomes
+            .genomes()
+            .iter()
+            .map(|(genome_id, genome)| (*genome_id, Network::from(genome)))
+            .collect();
+
+        let node_cost = self.configuration.borrow().node_cost;
+        let connection_cost = self.configuration.borrow().connection_cost;
+        let complexity_penalty_floor = self.configuration.borrow().complexity_penalty_floor;
+        let complexity_cost = |network: &Network| {
+            node_cost * network.node_count() as f64
+                + connection_cost * network.connection_count() as f64
+        };
+        // The penalty can't push a fitness below the floor, fitnesses already below it stay as is
+        let penalize = |fitness: f64, cost: f64| match complexity_penalty_floor {
+            Some(floor) => f64::max(fitness - cost, f64::min(floor, fitness)),
+            None => fitness - cost,
+        };
+
+        let ids_and_fitnesses: Vec<(GenomeId, f64)> = if let Some(behavior_fn) = &self.behavior_fn {
+            let (ids_and_costs, behaviors): (Vec<(GenomeId, f64)>, Vec<Vec<f64>>) = self
+                .executor
+                .map(ids_and_networks, |(genome_id, mut network)| {
+                    let behavior = (behavior_fn)(&mut network);
+
+                    ((genome_id, complexity_cost(&network)), behavior)
+                })
+                .into_iter()
+                .unzip();
+
+            let (neighbors, threshold) = {
+                let config = self.configuration.borrow();
+
+                (config.novelty_neighbors, config.novelty_archive_threshold)
+            };
+            let novelties = self.novelty_archive.score(&behaviors, neighbors, threshold);
+
+            ids_and_costs
+                .into_iter()
+                .zip(novelties)
+                .map(|((genome_id, cost), novelty)| (genome_id, penalize(novelty, cost)))
+                .collect()
+        } else if let Some(objectives_fn) = &self.objectives_fn {
+            let (genome_ids, objectives): (Vec<GenomeId>, Vec<Vec<f64>>) = self
+                .executor
+                .map(ids_and_networks, |(genome_id, mut network)| {
+                    (genome_id, (objectives_fn)(&mut network))
+                })
+                .into_iter()
+                .unzip();
+
+            let scores = pareto_scores(&objectives);
+            self.objectives = genome_ids.iter().cloned().zip(objectives).collect();
+
+            genome_ids.into_iter().zip(scores).collect()
+        } else {
+            let fitness_fn = &self.fitness_fn;
+            let (deterministic_fitness, evaluations_per_genome, evaluation_summary) = {
+                let config = self.configuration.borrow();
+
+                (
+                    config.deterministic_fitness,
+                    config.evaluations_per_genome,
+                    config.evaluation_summary,
+                )
+            };
+
+            // Genomes carried over unchanged keep their fitness instead of being evaluated again
+            let (remembered, unknown) = ids_and_networks
+                .into_iter()
+                .partition::<Vec<(GenomeId, Network)>, _>(|(genome_id, _)| {
+                    deterministic_fitness && self.genomes.previous_fitness(genome_id).is_some()
+                });
+
+            // Every evaluation is a task of its own and runs on a fresh copy of the network
+            let evaluations: Vec<(usize, Network)> = unknown
+                .iter()
+                .enumerate()
+                .flat_map(|(index, (_, network))| {
+                    (0..evaluations_per_genome).map(move |_| (index, network.clone()))
+                })
+                .collect();
+
+            let mut fitnesses = vec![vec![]; unknown.len()];
+            self.executor
+                .map(evaluations, |(index, mut network)| {
+                    (index, (fitness_fn)(&mut network))
+                })
+                .into_iter()
+                .for_each(|(index, fitness)| fitnesses[index].push(fitness));
+
+            unknown
+                .iter()
+                .zip(fitnesses)
+                .map(|((genome_id, network), fitnesses)| {
+                    let fitness = evaluation_summary.summarize(&fitnesses);
+
+                    (*genome_id, penalize(fitness, complexity_cost(network)))
+                })
+                .chain(remembered.into_iter().map(|(genome_id, _)| {
+                    (
+                        genome_id,
+                        self.genomes.previous_fitness(&genome_id).unwrap(),
+                    )
+                }))
+                .collect()
+        };
+
+        // A NaN fitness compares false with everything, it counts as the lowest finite fitness of
+        // the generation instead, a sentinel like f64::MIN would swamp the species fitness shares
+        let lowest_fitness = ids_and_fitnesses
+            .iter()
+            .map(|(_, fitness)| *fitness)
+            .filter(|fitness| fitness.is_finite())
+            .fold(None, |lowest: Option<f64>, fitness| {
+                Some(lowest.map_or(fitness, |lowest| lowest.min(fitness)))
+            })
+            .unwrap_or(0.);
+
+        ids_and_fitnesses
+            .into_iter()
+            .for_each(|(genome_id, genome_fitness)| {
+                let genome_fitness = if genome_fitness.is_nan() {
+                    lowest_fitness
+                } else {
+                    genome_fitness
+                };
+
+                self.genomes.mark_fitness(genome_id, genome_fitness)
+            });
+    }
+
+    pub fn get_best(&self) -> (GenomeId, &Genome, f64) {
+        // The first genome is the starting point so the lowest fitnesses still have a best genome
+        let (best_genome_id, best_fitness) = self
+            .genomes
+            .fitnesses()
+            .iter()
+            .fold(None, |best, (genome_id, genome_fitness)| match best {
+                Some((_, best_fitness)) if *genome_fitness <= best_fitness => best,
+                _ => Some((*genome_id, *genome_fitness)),
+            })
+            .unwrap();
+
+        let best_genome = self.genomes.genomes().get(&best_genome_id).unwrap();
+
+        (best_genome_id, best_genome, best_fitness)
+    }
+
+    /// Returns the id of the species the best genome was assigned to, genomes born after the last
+    /// speciation don't belong to any species yet
+    pub fn best_species_id(&self) -> Option<usize> {
+        let (best_genome_id, _, _) = self.get_best();
+
+        self.species_set
+            .species()
+            .iter()
+            .find(|(_, species)| species.members.contains(&best_genome_id))
+            .map(|(species_id, _)| *species_id)
+    }
+
+    /// Speciates with a custom distance metric instead of the configured `GenomicDistance`
+    pub fn set_distance_metric(&mut self, metric: Box<dyn DistanceMetric>) {
+        self.species_set.set_distance_metric(metric);
+    }
+
+    /// Sets the reporter that receives structured lifecycle callbacks
+    pub fn set_reporter<R: reporting::Reporter + 'static>(&mut self, reporter: R) {
+        *self.lifecycle_reporter.get_mut() = Some(Box::new(reporter));
+    }
+
+    fn report_with<F>(&self, f: F)
+    where
+        F: FnOnce(&mut dyn reporting::Reporter, &NEAT),
+    {
+        if let Some(reporter) = self.lifecycle_reporter.borrow_mut().as_mut() {
+            f(reporter.as_mut(), self);
+        }
+    }
+
+    fn population_view(&self) -> Population<'_> {
+        Population {
+            genomes: self.genomes.genomes(),
+            fitnesses: self.genomes.fitnesses(),
+        }
+    }
+
+    /// Every alive species as its id, the id of its representative and its size
+    pub fn species_representatives(&self) -> Vec<(usize, GenomeId, usize)> {
+        self.species_set
+            .species()
+            .iter()
+            .map(|(species_id, species)| (*species_id, species.representative_id(), species.size()))
+            .collect()
+    }
+
+    /// Per species statistics of the latest speciation
+    pub fn species_stats(&self) -> Vec<SpeciesStat> {
+        self.species_set.stats(self.generation)
+    }
+
+    /// Why the evolution stopped, it's set before the hooks of the last generation are called
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.stop_reason
+    }
+
+    /// Every genome of the current generation as a network paired with its fitness, fittest first
+    pub fn population(&self) -> Vec<(Network, f64)> {
+        let mut population: Vec<(Network, f64)> = self
+            .genomes
+            .genomes()
+            .iter()
+            .map(|(genome_id, genome)| {
+                (
+                    Network::from(genome),
+                    *self.genomes.fitnesses().get(genome_id).unwrap(),
+                )
+            })
+            .collect();
+
+        population.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        population
+    }
+
+    fn pick_mutation<R: Rng + ?Sized>(&self, rng: &mut R) -> MutationKind {
+        use rand::distributions::Distribution;
+        use rand_distr::weighted_alias::WeightedAliasIndex;
+
+        let dist = WeightedAliasIndex::new(
+            self.configuration
+                .borrow()
+                .mutation_kinds
+                .iter()
+                .map(|k| k.1)
+                .collect(),
+        )
+        .unwrap();
+
+        self.configuration
+            .borrow()
+            .mutation_kinds
+            .get(dist.sample(rng))
+            .cloned()
+            .unwrap()
+            .0
+    }
+
+    /// Objective values of the current genomes when optimizing several objectives
+    pub fn objectives(&self) -> &BTreeMap<GenomeId, Vec<f64>> {
+        &self.objectives
+    }
+
+    /// Behaviors archived so far by novelty search
+    pub fn novelty_archive(&self) -> &[Vec<f64>] {
+        self.novelty_archive.behaviors()
+    }
+
+    /// The compatibility threshold used for the latest speciation
+    pub fn compatibility_threshold(&self) -> f64 {
+        self.species_set.compatibility_threshold()
+    }
+
+    /// Wall clock time since the evolution started, zero before it does
+    pub fn elapsed(&self) -> Duration {
+        self.started
+            .map(|started| started.elapsed())
+            .unwrap_or_default()
+    }
+
+    /// How far along the current generation is towards `max_generations`, from 0 to 1
+    pub fn generation_fraction(&self) -> f64 {
+        self.generation as f64 / self.configuration.borrow().max_generations as f64
+    }
+
+    /// Estimates the time left until `max_generations` from the pace of the generations so far,
+    /// there is no estimate before the first generation
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.generation_fraction();
+
+        if fraction > 0. {
+            Some(self.elapsed().mul_f64((1. - fraction).max(0.) / fraction))
+        } else {
+            None
+        }
+    }
+
+    pub fn add_hook<F>(&mut self, every: usize, hook: F)
+    where
+        F: FnMut(usize, &NEAT) + 'static,
+    {
+        self.reporter.get_mut().register(every, hook);
+    }
+
+    /// Registers a function that can change the configuration at the start of every generation,
+    /// the changes take effect in that same generation except for `max_generations`, `seed` and
+    /// `parallelism` which are only read when the evolution starts
+    pub fn add_schedule<F>(&mut self, schedule: F)
+    where
+        F: FnMut(usize, &mut Configuration) + 'static,
+    {
+        self.schedules.push(Box::new(schedule));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EvaluationSummary, InitialConnectivity};
+
+    const SEED: u64 = 3;
+
+    #[test]
+    fn xor() {
+        let inputs: Vec<Vec<f64>> = vec![vec![0., 0.], vec![0., 1.], vec![1., 0.], vec![1., 1.]];
+        let outputs: Vec<f64> = vec![0., 1., 1., 0.];
+
+        let mut system = NEAT::new(2, 1, move |n| {
+            let error: f64 = n
+                .forward_pass_batch(&inputs)
+                .iter()
+                .zip(outputs.iter())
+                .map(|(results, o)| (o - results.first().unwrap()).powi(2))
+                .sum();
+
+            1. / (1. + error)
+        });
+
+        system.set_configuration(Configuration {
+            population_size: 150,
+            max_generations: 100,
+            mutation_rate: 0.75,
+            fitness_goal: Some(0.9099),
+            node_cost: 0.01,
+            connection_cost: 0.01,
+            compatibility_threshold: 3.,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.add_hook(1, |i, system| {
+            let (_, _, fitness) = system.get_best();
+            println!("Generation {}, best fitness is {}", i, fitness);
+        });
+
+        let (mut network, fitness) = system.start();
+
+        let inputs: Vec<Vec<f64>> = vec![vec![0., 0.], vec![0., 1.], vec![1., 0.], vec![1., 1.]];
+        for i in inputs {
+            let o = network.forward_pass(i.clone());
+            dbg!(i, o);
+        }
+
+        dbg!(&network, &fitness);
+
+        println!(
+            "Found network with {} nodes and {} connections, of fitness {}",
+            network.nodes.len(),
+            network.connection_count(),
+            fitness
+        );
+    }
+    #[test]
+    fn fitness_fn_can_capture_state() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let evaluations = Arc::new(AtomicUsize::new(0));
+        let evaluations_in_fn = evaluations.clone();
+
+        let mut system = NEAT::new(1, 1, move |_| {
+            evaluations_in_fn.fetch_add(1, Ordering::SeqCst);
+            0.
+        });
+
+        system.set_configuration(Configuration {
+            population_size: 10,
+            max_generations: 1,
+            ..Default::default()
+        });
+
+        system.start();
+
+        assert!(evaluations.load(Ordering::SeqCst) >= 10);
+    }
+    #[test]
+    fn invalid_configuration_is_rejected_before_running() {
+        let mut system = NEAT::new(2, 1, |_| 0.);
+        system.set_configuration(Configuration {
+            population_size: 0,
+            ..Default::default()
+        });
+
+        assert_eq!(
+            system.try_start().unwrap_err(),
+            ConfigError::NotPositive {
+                field: "population_size"
+            }
+        );
+    }
+
+    #[test]
+    fn single_member_species_with_low_survival_ratio() {
+        let mut system = NEAT::new(2, 1, |_| 1.);
+        system.set_configuration(Configuration {
+            population_size: 10,
+            max_generations: 5,
+            survival_ratio: 0.1,
+            compatibility_threshold: 0.,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+    }
+
+    #[test]
+    fn population_size_is_kept_every_generation() {
+        let mut system = NEAT::new(2, 1, |network| {
+            let output = network.forward_pass(vec![1., 0.]);
+            output[0]
+        });
+        system.set_configuration(Configuration {
+            population_size: 37,
+            max_generations: 15,
+            compatibility_threshold: 1.,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.add_hook(1, |_, system| {
+            assert_eq!(system.genomes.genomes().len(), 37);
+        });
+
+        system.start();
+    }
+
+    #[test]
+    fn interspecies_mating_keeps_genomes_valid() {
+        let mut system = NEAT::new(2, 1, |network| {
+            let output = network.forward_pass(vec![1., 0.]);
+            output[0]
+        });
+        system.set_configuration(Configuration {
+            population_size: 30,
+            max_generations: 10,
+            interspecies_mating_rate: 1.,
+            compatibility_threshold: 1.,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.add_hook(1, |_, system| {
+            assert!(system
+                .genomes
+                .genomes()
+                .values()
+                .all(|genome| genome.validate().is_ok()));
+        });
+
+        system.start();
+    }
+
+    #[test]
+    fn best_species_id_finds_the_champions_species() {
+        let mut system = NEAT::new(2, 1, |_| 1.);
+        system.set_configuration(Configuration {
+            population_size: 10,
+            max_generations: 1,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.start();
+
+        // Speciating the current genomes puts the champion into one of the species
+        let genome_ids: Vec<GenomeId> = system.genomes.genomes().keys().cloned().collect();
+        let all_genomes = system
+            .genomes
+            .genomes()
+            .iter()
+            .chain(system.genomes.previous_genomes())
+            .map(|(genome_id, genome)| (*genome_id, genome.clone()))
+            .collect();
+        system
+            .species_set
+            .speciate(2, &genome_ids, &all_genomes, system.genomes.fitnesses());
+
+        let (best_genome_id, _, _) = system.get_best();
+        let species_id = system.best_species_id().unwrap();
+        assert!(system.species_set.species()[&species_id]
+            .members
+            .contains(&best_genome_id));
+    }
+
+    #[test]
+    fn unconnected_start_grows_connections() {
+        let mut system = NEAT::new(2, 1, |network| {
+            let output = network.forward_pass(vec![1., 0.]);
+            output[0]
+        });
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 10,
+            initial_connectivity: InitialConnectivity::Unconnected,
+            mutation_rate: 1.,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+
+        assert!(system
+            .genomes
+            .genomes()
+            .values()
+            .any(|genome| !genome.connections().is_empty()));
+    }
+
+    #[test]
+    fn seeded_runs_are_reproducible() {
+        let run = || {
+            let mut system = NEAT::new(2, 1, |n| {
+                let results = n.forward_pass(vec![0.5, -0.5]);
+
+                -(1. - results.first().unwrap()).abs()
+            });
+
+            system.set_configuration(Configuration {
+                population_size: 30,
+                max_generations: 10,
+                seed: Some(SEED),
+                ..Default::default()
+            });
+
+            system.start();
+
+            let (_, best_genome, best_fitness) = system.get_best();
+            (best_genome.clone(), best_fitness)
+        };
+
+        let (first_genome, first_fitness) = run();
+        let (second_genome, second_fitness) = run();
+
+        assert_eq!(first_genome, second_genome);
+        assert_eq!(first_fitness.to_bits(), second_fitness.to_bits());
+    }
+
+    #[test]
+    fn best_fitness_never_decreases() {
+        let mut system = NEAT::new(2, 1, |n| {
+            let result = *n.forward_pass(vec![0.5, -0.5]).first().unwrap();
+
+            if result.is_finite() {
+                -(1. - result).abs()
+            } else {
+                -10.
+            }
+        });
+
+        system.set_configuration(Configuration {
+            population_size: 50,
+            max_generations: 30,
+            species_elitism_min_size: 1,
+            stagnation_after: 100,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        let best_fitnesses = Rc::new(RefCell::new(vec![]));
+        let best_fitnesses_clone = best_fitnesses.clone();
+        system.add_hook(1, move |_, system| {
+            let (_, _, fitness) = system.get_best();
+            best_fitnesses_clone.borrow_mut().push(fitness);
+        });
+
+        system.start();
+
+        let best_fitnesses = best_fitnesses.borrow();
+
+        assert_eq!(best_fitnesses.len(), 30);
+        assert!(best_fitnesses.windows(2).all(|w| w[1] >= w[0]));
+    }
+
+    #[test]
+    fn population_is_sorted_by_fitness() {
+        let mut system = NEAT::new(2, 1, |n| {
+            let result = *n.forward_pass(vec![0.5, -0.5]).first().unwrap();
+
+            if result.is_finite() {
+                -(1. - result).abs()
+            } else {
+                -10.
+            }
+        });
+
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 3,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        let (_, best_fitness) = system.start();
+        let population = system.population();
+
+        assert_eq!(population.len(), system.genomes.genomes().len());
+        assert_eq!(
+            population.first().unwrap().1.to_bits(),
+            best_fitness.to_bits()
+        );
+        assert!(population.windows(2).all(|w| w[0].1 >= w[1].1));
+    }
+
+    #[test]
+    fn patience_stops_on_plateau() {
+        let mut system = NEAT::new(2, 1, |_| 1.);
+
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 100,
+            patience: Some(5),
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.add_hook(1, |i, system| {
+            if let Some(reason) = system.stop_reason() {
+                println!("Stopped at generation {} because of {:?}", i, reason);
+                assert_eq!(i, 6);
+            }
+        });
+
+        system.start();
+
+        assert_eq!(system.stop_reason(), Some(StopReason::Patience));
+    }
+
+    #[test]
+    fn species_stats_cover_population() {
+        let mut system = NEAT::new(2, 1, |_| 1.);
+
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 3,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        assert!(system.species_stats().is_empty());
+
+        system.add_hook(1, |_, system| {
+            let stats = system.species_stats();
+
+            assert!(!stats.is_empty());
+            assert!(stats.iter().all(|stat| stat.member_count > 0));
+            assert!(stats.iter().all(|stat| stat.mean_fitness.is_some()));
+
+            let adjusted_sum: f64 = stats
+                .iter()
+                .map(|stat| stat.adjusted_fitness.unwrap())
+                .sum();
+            assert!((adjusted_sum - 1.).abs() < 1e-9);
+        });
+
+        system.start();
+    }
+
+    #[test]
+    fn lifecycle_reporter_is_called() {
+        #[derive(Default)]
+        struct Counts {
+            generation_starts: usize,
+            generation_ends: usize,
+            evaluations: usize,
+            reproductions: usize,
+            solutions: usize,
+        }
+
+        struct CountingReporter(Rc<RefCell<Counts>>);
+
+        impl reporting::Reporter for CountingReporter {
+            fn on_generation_start(&mut self, _: &Generation) {
+                self.0.borrow_mut().generation_starts += 1;
+            }
+
+            fn on_generation_end(&mut self, _: &Generation, _: &Population, _: &[SpeciesStat]) {
+                self.0.borrow_mut().generation_ends += 1;
+            }
+
+            fn on_evaluation_end(&mut self, _: &Population, _: &[SpeciesStat], _: &Genome) {
+                self.0.borrow_mut().evaluations += 1;
+            }
+
+            fn on_reproduction_end(&mut self, _: &Population, _: &[SpeciesStat]) {
+                self.0.borrow_mut().reproductions += 1;
+            }
+
+            fn on_solution_found(&mut self, _: &Generation, _: &Population, _: &Genome) {
+                self.0.borrow_mut().solutions += 1;
+            }
+        }
+
+        let counts = Rc::new(RefCell::new(Counts::default()));
+
+        let mut system = NEAT::new(2, 1, |_| 1.);
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 5,
+            fitness_goal: Some(1.),
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.set_reporter(CountingReporter(counts.clone()));
+
+        system.start();
+
+        let counts = counts.borrow();
+        assert_eq!(counts.generation_starts, 1);
+        assert_eq!(counts.generation_ends, 1);
+        assert_eq!(counts.evaluations, 1);
+        assert_eq!(counts.reproductions, 1);
+        assert_eq!(counts.solutions, 1);
+    }
+
+    #[test]
+    fn custom_distance_metric_drives_speciation() {
+        struct Constant(f64);
+
+        impl DistanceMetric for Constant {
+            fn distance(&self, _: &Genome, _: &Genome) -> f64 {
+                self.0
+            }
+        }
+
+        let species_count = |distance: f64| {
+            let mut system = NEAT::new(2, 1, |network| network.forward_pass(vec![1., 0.])[0]);
+            system.set_configuration(Configuration {
+                population_size: 10,
+                max_generations: 1,
+                seed: Some(SEED),
+                ..Default::default()
+            });
+            system.set_distance_metric(Box::new(Constant(distance)));
+            system.start();
+
+            system.species_stats().len()
+        };
+
+        assert_eq!(species_count(0.), 1);
+        assert_eq!(species_count(f64::MAX), 10);
+    }
+
+    #[test]
+    fn zero_costs_keep_the_fitness_verbatim() {
+        let size_fitness = |network: &Network| {
+            10. - network.node_count() as f64 - network.connection_count() as f64 * 0.5
+        };
+        let mut system = NEAT::new(2, 1, move |network| size_fitness(network));
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 3,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+
+        let (_, best_genome, best_fitness) = system.get_best();
+        let network = Network::from(best_genome);
+
+        assert!((best_fitness - size_fitness(&network)).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn species_representatives_match_the_species() {
+        let mut system = NEAT::new(2, 1, |network| network.forward_pass(vec![1., 0.])[0]);
+        system.set_configuration(Configuration {
+            population_size: 30,
+            max_generations: 3,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.add_hook(1, |_, system| {
+            let representatives = system.species_representatives();
+            let stats = system.species_stats();
+
+            assert!(!representatives.is_empty());
+            assert_eq!(representatives.len(), stats.len());
+            for ((species_id, representative_id, size), stat) in representatives.iter().zip(&stats)
+            {
+                let species = system.species_set.species().get(species_id).unwrap();
+
+                assert_eq!(*species_id, stat.id);
+                assert_eq!(*size, stat.member_count);
+                assert_eq!(*representative_id, species.representative_id());
+            }
+        });
+
+        system.start();
+    }
+
+    #[test]
+    fn bias_input_survives_evolution() {
+        let mut system = NEAT::new(2, 1, |network| network.forward_pass(vec![0., 0.])[0]);
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 5,
+            add_bias_input: true,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        let (network, _) = system.start();
+
+        assert!(network.bias_input);
+        assert_eq!(network.input_count, 3);
+        assert!(system
+            .genomes
+            .previous_genomes()
+            .values()
+            .all(|genome| genome.has_bias_input() && genome.input_count() == 3));
+    }
+
+    #[test]
+    fn seeded_genomes_join_the_initial_population() {
+        let mut system = NEAT::new(2, 1, |_| 1.);
+        system.set_configuration(Configuration {
+            population_size: 5,
+            max_generations: 1,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        let mut builder = Genome::builder(2, 1);
+        let hidden = builder.add_hidden_node();
+        builder.connect(0, hidden, 1.).connect(hidden, 2, 1.);
+        let seed = builder.build().unwrap();
+
+        system.seed_population(vec![seed.clone(); 7]).unwrap();
+        system.add_hook(1, move |_, system| {
+            assert_eq!(system.genomes.previous_genomes().len(), 5);
+            assert!(system
+                .genomes
+                .previous_genomes()
+                .values()
+                .all(|genome| genome.nodes() == seed.nodes()));
+        });
+
+        system.start();
+
+        assert_eq!(
+            system.seed_population(vec![Genome::new(3, 1)]),
+            Err(GenomeError::InputCountMismatch {
+                expected: 2,
+                found: 3
+            })
+        );
+        assert_eq!(
+            system.seed_population(vec![Genome::new(2, 2)]),
+            Err(GenomeError::OutputCountMismatch {
+                expected: 1,
+                found: 2
+            })
+        );
+    }
+
+    #[test]
+    fn seeded_genomes_are_prepared_like_new_ones() {
+        use crate::{ActivationKind, NodeKind};
+        use std::collections::HashMap;
+
+        let mut system = NEAT::new(2, 1, |_| 1.);
+        system.set_configuration(Configuration {
+            population_size: 6,
+            max_generations: 1,
+            output_activation: Some(ActivationKind::Identity),
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        let mut builder = Genome::builder(2, 1);
+        let hidden = builder.add_hidden_node();
+        builder
+            .connect(0, hidden, 5.)
+            .connect(hidden, 2, -5.)
+            .connect(1, 2, 0.5);
+        system
+            .seed_population(vec![builder.build().unwrap()])
+            .unwrap();
+
+        system.add_hook(1, |_, system| {
+            let genomes = system.genomes.previous_genomes();
+            assert!(genomes
+                .values()
+                .all(|genome| genome.connections().iter().all(|c| c.weight.abs() <= 1.)));
+            assert!(genomes.values().all(|genome| genome
+                .nodes()
+                .iter()
+                .filter(|n| matches!(n.kind, NodeKind::Output))
+                .all(|n| n.activation == ActivationKind::Identity)));
+
+            // The connection the seed shares with new genomes has the same innovation number
+            let mut innovations: HashMap<(usize, usize), usize> = HashMap::new();
+            assert!(genomes
+                .values()
+                .all(|genome| genome.connections().iter().all(|c| {
+                    *innovations.entry((c.from, c.to)).or_insert(c.innovation) == c.innovation
+                })));
+            assert_eq!(innovations.len(), 4);
+        });
+
+        system.start();
+    }
+
+    #[test]
+    fn nan_fitness_never_wins() {
+        let mut system = NEAT::new(2, 1, |network| {
+            let output = network.forward_pass(vec![1., 1.])[0];
+
+            if output > 0. {
+                f64::NAN
+            } else {
+                output
+            }
+        });
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 5,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        let (_, best_fitness) = system.start();
+
+        assert!(!best_fitness.is_nan());
+        assert!(system.genomes.fitnesses().values().all(|f| !f.is_nan()));
+    }
+
+    #[test]
+    fn nan_fitness_keeps_offspring_proportional() {
+        let mut system = NEAT::new(2, 1, |network| {
+            let output = network.forward_pass(vec![1., 1.])[0];
+
+            if output > 0.5 {
+                f64::NAN
+            } else {
+                output
+            }
+        });
+        system.set_configuration(Configuration {
+            population_size: 30,
+            max_generations: 1,
+            compatibility_threshold: 0.5,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+
+        let shares: Vec<f64> = system
+            .species_set
+            .species()
+            .values()
+            .map(|species| species.adjusted_fitness.unwrap())
+            .collect();
+        assert!(shares.len() > 1);
+        assert!((shares.iter().sum::<f64>() - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn total_stagnation_can_stop_the_run() {
+        let mut system = NEAT::new(2, 1, |_| 1.);
+
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 100,
+            stagnation_after: 3,
+            total_stagnation: TotalStagnation::Stop,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+
+        assert_eq!(system.stop_reason(), Some(StopReason::Stagnation));
+        assert!(system.generation < 100);
+    }
+
+    #[test]
+    fn total_stagnation_can_repopulate() {
+        #[derive(Default)]
+        struct Counts {
+            stagnant_species: usize,
+            extinctions: usize,
+        }
+
+        struct CountingReporter(Rc<RefCell<Counts>>);
+
+        impl reporting::Reporter for CountingReporter {
+            fn on_species_stagnant(&mut self, _: usize, _: &[SpeciesStat]) {
+                self.0.borrow_mut().stagnant_species += 1;
+            }
+
+            fn on_extinction(&mut self) {
+                self.0.borrow_mut().extinctions += 1;
+            }
+        }
+
+        let counts = Rc::new(RefCell::new(Counts::default()));
+
+        let mut system = NEAT::new(2, 1, |_| 1.);
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 10,
+            stagnation_after: 3,
+            total_stagnation: TotalStagnation::Repopulate,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.set_reporter(CountingReporter(counts.clone()));
+        system.add_hook(1, |_, system| {
+            assert_eq!(system.genomes.genomes().len(), 20);
+        });
+
+        system.start();
+
+        let counts = counts.borrow();
+        assert!(counts.extinctions > 0);
+        assert!(counts.stagnant_species >= counts.extinctions);
+        assert_eq!(system.stop_reason(), Some(StopReason::MaxGenerations));
+    }
+
+    #[test]
+    fn start_can_be_cancelled() {
+        use std::sync::atomic::AtomicUsize;
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        let evaluations = Arc::new(AtomicUsize::new(0));
+        let (flag, counter) = (cancel.clone(), evaluations.clone());
+
+        // The flag is set from the evaluation threads during the third generation
+        let mut system = NEAT::new(2, 1, move |network| {
+            if counter.fetch_add(1, Ordering::SeqCst) == 20 * 3 {
+                flag.store(true, Ordering::SeqCst);
+            }
+
+            network.forward_pass(vec![1., 0.])[0]
+        });
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 100,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        let (_, fitness) = system.start_with_cancel(cancel);
+
+        assert_eq!(system.stop_reason(), Some(StopReason::Cancelled));
+        assert_eq!(evaluations.load(Ordering::SeqCst), 20 * 4);
+        assert!((fitness - system.get_best().2).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn hooks_can_see_progress() {
+        let progress = Rc::new(RefCell::new(vec![]));
+        let recorded = progress.clone();
+
+        let mut system = NEAT::new(2, 1, |network| network.forward_pass(vec![1., 0.])[0]);
+        system.set_configuration(Configuration {
+            population_size: 10,
+            max_generations: 4,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        assert_eq!(system.elapsed(), Duration::default());
+        assert_eq!(system.eta(), None);
+
+        system.add_hook(1, move |_, system| {
+            recorded.borrow_mut().push((
+                system.generation_fraction(),
+                system.elapsed(),
+                system.eta().unwrap(),
+            ));
+        });
+        system.start();
+
+        let progress = progress.borrow();
+        let fractions: Vec<f64> = progress.iter().map(|(fraction, _, _)| *fraction).collect();
+        assert_eq!(fractions, vec![0.25, 0.5, 0.75, 1.]);
+        assert!(progress.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(progress.last().unwrap().2, Duration::default());
+    }
+
+    #[test]
+    fn global_elites_survive_the_generation() {
+        let best_ids = Rc::new(RefCell::new(vec![]));
+        let survived = Rc::new(RefCell::new(vec![]));
+        let (recorded_best, recorded_survived) = (best_ids.clone(), survived.clone());
+
+        let mut system = NEAT::new(2, 1, |network| network.forward_pass(vec![1., 0.])[0]);
+        // Species don't keep their champions on their own
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 10,
+            elitism: 0.,
+            species_elitism_min_size: 100,
+            global_elites: 1,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.add_hook(1, move |_, system| {
+            if let Some(previous_best) = recorded_best.borrow().last() {
+                recorded_survived
+                    .borrow_mut()
+                    .push(system.genomes.genomes().contains_key(previous_best));
+            }
+
+            let (best_id, _, _) = system.get_best();
+            recorded_best.borrow_mut().push(best_id);
+            assert_eq!(system.genomes.genomes().len(), 20);
+        });
+
+        system.start();
+
+        assert_eq!(best_ids.borrow().len(), 10);
+        assert!(survived.borrow().iter().all(|survived| *survived));
+    }
+
+    #[test]
+    fn invariants_hold_through_evolution() {
+        let mut system = NEAT::new(2, 1, |network| network.forward_pass(vec![1., 0.])[0]);
+        system.set_configuration(Configuration {
+            population_size: 30,
+            max_generations: 20,
+            global_elites: 2,
+            add_bias_input: true,
+            recurrent: true,
+            compact_every: Some(5),
+            debug_invariants: true,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+    }
+
+    #[test]
+    #[should_panic(expected = "Generation 3 has 21 genomes instead of 20")]
+    fn invariant_violations_panic() {
+        let mut system = NEAT::new(2, 1, |network| network.forward_pass(vec![1., 0.])[0]);
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 3,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.start();
+
+        system.genomes.add_genome(Genome::new(2, 1));
+        system.check_invariants();
+    }
+
+    #[test]
+    fn deterministic_fitness_skips_unchanged_elites() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let run = |deterministic_fitness: bool| {
+            let evaluations = Arc::new(AtomicUsize::new(0));
+            let counter = evaluations.clone();
+
+            let mut system = NEAT::new(2, 1, move |network| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                network.forward_pass(vec![1., 0.])[0]
+            });
+            system.set_configuration(Configuration {
+                population_size: 20,
+                max_generations: 5,
+                elitism: 0.2,
+                deterministic_fitness,
+                seed: Some(SEED),
+                ..Default::default()
+            });
+
+            let (_, fitness) = system.start();
+
+            (evaluations.load(Ordering::SeqCst), fitness)
+        };
+
+        let (all_evaluations, fitness) = run(false);
+        let (memoized_evaluations, memoized_fitness) = run(true);
+
+        assert_eq!(all_evaluations, 20 * 6);
+        assert!(memoized_evaluations < all_evaluations);
+        assert!((fitness - memoized_fitness).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn every_genome_is_evaluated_several_times() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let evaluations = Arc::new(AtomicUsize::new(0));
+        let counter = evaluations.clone();
+
+        let mut system = NEAT::new(2, 1, move |network| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            network.forward_pass(vec![1., 0.])[0]
+        });
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 3,
+            evaluations_per_genome: 4,
+            evaluation_summary: EvaluationSummary::Min,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+
+        assert_eq!(evaluations.load(Ordering::SeqCst), 20 * 4 * 4);
+    }
+
+    #[test]
+    fn schedule_can_stop_structural_change() {
+        use std::collections::BTreeSet;
+
+        let innovations = Rc::new(RefCell::new(vec![]));
+        let recorded = innovations.clone();
+
+        let mut system = NEAT::new(2, 1, |network| network.forward_pass(vec![1., 0.])[0]);
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 6,
+            mutation_rate: 1.,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.add_schedule(|generation, configuration| {
+            if generation > 3 {
+                configuration.mutation_rate = 0.;
+            }
+        });
+        system.add_hook(1, move |_, system| {
+            let generation_innovations: BTreeSet<usize> = system
+                .genomes
+                .genomes()
+                .values()
+                .flat_map(|genome| genome.connections().iter().map(|c| c.innovation))
+                .collect();
+
+            recorded.borrow_mut().push(generation_innovations);
+        });
+
+        system.start();
+
+        let innovations = innovations.borrow();
+        assert_eq!(innovations.len(), 6);
+        assert!(!innovations[2].is_subset(&innovations[0]));
+        assert!(innovations[3..]
+            .windows(2)
+            .all(|pair| pair[1].is_subset(&pair[0])));
+        assert!(innovations[3].is_subset(&innovations[2]));
+
+        let mut invalid = NEAT::new(2, 1, |_| 1.);
+        invalid.add_schedule(|_, configuration| configuration.population_size = 0);
+        assert!(matches!(
+            invalid.try_start(),
+            Err(ConfigError::NotPositive {
+                field: "population_size"
+            })
+        ));
+    }
+
+    #[test]
+    fn stagnating_species_hypermutate() {
+        struct HypermutationReporter(Rc<RefCell<Vec<(usize, usize)>>>);
+
+        impl reporting::Reporter for HypermutationReporter {
+            fn on_species_hypermutation(&mut self, species_id: usize, species: &[SpeciesStat]) {
+                let stat = species.iter().find(|stat| stat.id == species_id).unwrap();
+
+                self.0
+                    .borrow_mut()
+                    .push((species_id, stat.generations_since_improvement));
+            }
+        }
+
+        let events = Rc::new(RefCell::new(vec![]));
+
+        let mut system = NEAT::new(2, 1, |_| 1.);
+        system.set_configuration(Configuration {
+            population_size: 20,
+            max_generations: 10,
+            stagnation_after: 10,
+            stagnation_hypermutation: Some(5.),
+            seed: Some(SEED),
+            ..Default::default()
+        });
+        system.set_reporter(HypermutationReporter(events.clone()));
+
+        system.start();
+
+        let events = events.borrow();
+        assert!(!events.is_empty());
+        assert!(events
+            .iter()
+            .all(|(_, since_improvement)| *since_improvement > 5));
+
+        let invalid = Configuration {
+            stagnation_hypermutation: Some(0.),
+            ..Default::default()
+        };
+        assert_eq!(
+            invalid.validate(),
+            Err(ConfigError::NotPositive {
+                field: "stagnation_hypermutation"
+            })
+        );
+    }
+
+    #[test]
+    fn novelty_search_fills_the_archive() {
+        let mut system = NEAT::with_novelty(2, 1, |n| n.forward_pass(vec![0.5, -0.5]));
+
+        system.set_configuration(Configuration {
+            population_size: 30,
+            max_generations: 5,
+            novelty_neighbors: 3,
+            novelty_archive_threshold: 0.01,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+
+        assert!(!system.novelty_archive().is_empty());
+        assert!(system
+            .novelty_archive()
+            .iter()
+            .all(|behavior| behavior.len() == 1));
+        assert!(system.genomes.fitnesses().values().all(|n| *n >= 0.));
+    }
+
+    #[test]
+    fn objectives_prefer_the_pareto_front() {
+        let mut system = NEAT::with_objectives(2, 1, |n| {
+            let result = *n.forward_pass(vec![0.5, -0.5]).first().unwrap();
+
+            vec![result, -(n.connection_count() as f64)]
+        });
+
+        system.set_configuration(Configuration {
+            population_size: 30,
+            max_generations: 5,
+            seed: Some(SEED),
+            ..Default::default()
+        });
+
+        system.start();
+
+        let (best_genome_id, _, _) = system.get_best();
+        let best_objectives = system.objectives().get(&best_genome_id).unwrap();
+
+        assert_eq!(system.objectives().len(), system.genomes.genomes().len());
+        assert!(system.objectives().values().all(|objectives| {
+            !(objectives[0] > best_objectives[0] && objectives[1] > best_objectives[1])
+        }));
+    }
+
+    #[test]
+    fn complexity_penalty_floor_limits_large_costs() {
+        let run = |complexity_penalty_floor: Option<f64>| {
+            let mut system = NEAT::new(2, 1, |_| 1.);
+
+            system.set_configuration(Configuration {
+                population_size: 10,
+                max_generations: 2,
+                node_cost: 100.,
+                complexity_penalty_floor,
+                seed: Some(SEED),
+                ..Default::default()
+            });
+
+            system.start();
+
+            system
+                .genomes
+                .fitnesses()
+                .values()
+                .cloned()
+                .collect::<Vec<f64>>()
+        };
+
+        assert!(run(None).iter().all(|fitness| *fitness < -200.));
+        assert!(run(Some(0.)).iter().all(|fitness| *fitness == 0.));
+    }
+
+    #[test]
+    fn sequential_runs_stay_on_the_calling_thread() {
+        let caller = std::thread::current().id();
+        let run = move || {
+            let mut system = NEAT::new(2, 1, move |n| {
+                assert_eq!(std::thread::current().id(), caller);
+
+                -(1. - n.forward_pass(vec![0.5, -0.5]).first().unwrap()).abs()
+            });
+
+            system.set_configuration(Configuration {
+                population_size: 20,
+                max_generations: 5,
+                parallelism: Parallelism::Sequential,
+                seed: Some(SEED),
+                ..Default::default()
+            });
+
+            system.start();
+
+            let (_, best_genome, best_fitness) = system.get_best();
+            (best_genome.clone(), best_fitness)
+        };
+
+        let (first_genome, first_fitness) = run();
+        let (second_genome, second_fitness) = run();
+
+        assert_eq!(first_genome, second_genome);
+        assert_eq!(first_fitness.to_bits(), second_fitness.to_bits());
+    }
+}
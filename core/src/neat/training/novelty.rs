@@ -0,0 +1,97 @@
+/// Behaviors that were novel enough to be remembered, new behaviors are compared against them
+/// and against the rest of their generation
+#[derive(Debug, Default)]
+pub struct NoveltyArchive {
+    behaviors: Vec<Vec<f64>>,
+}
+
+impl NoveltyArchive {
+    pub fn new() -> Self {
+        NoveltyArchive { behaviors: vec![] }
+    }
+
+    pub fn behaviors(&self) -> &[Vec<f64>] {
+        &self.behaviors
+    }
+
+    /// Scores every behavior by the mean distance to its nearest neighbors, behaviors scoring
+    /// above the threshold are archived afterwards
+    pub fn score(&mut self, behaviors: &[Vec<f64>], neighbors: usize, threshold: f64) -> Vec<f64> {
+        let scores: Vec<f64> = behaviors
+            .iter()
+            .enumerate()
+            .map(|(i, behavior)| {
+                let others = behaviors
+                    .iter()
+                    .enumerate()
+                    .filter(|(j, _)| *j != i)
+                    .map(|(_, other)| other)
+                    .chain(self.behaviors.iter());
+
+                sparseness(behavior, others, neighbors)
+            })
+            .collect();
+
+        behaviors
+            .iter()
+            .zip(scores.iter())
+            .filter(|(_, score)| **score > threshold)
+            .for_each(|(behavior, _)| self.behaviors.push(behavior.clone()));
+
+        scores
+    }
+}
+
+/// Mean euclidean distance to the nearest neighbors, zero when there is nothing to compare to
+fn sparseness<'a, I>(behavior: &[f64], others: I, neighbors: usize) -> f64
+where
+    I: Iterator<Item = &'a Vec<f64>>,
+{
+    let mut distances: Vec<f64> = others
+        .map(|other| {
+            behavior
+                .iter()
+                .zip(other.iter())
+                .map(|(a, b)| (a - b).powi(2))
+                .sum::<f64>()
+                .sqrt()
+        })
+        .collect();
+
+    distances.sort_by(|a, b| a.total_cmp(b));
+    distances.truncate(neighbors);
+
+    if distances.is_empty() {
+        0.
+    } else {
+        distances.iter().sum::<f64>() / distances.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparseness_uses_nearest_neighbors() {
+        let others = [vec![1., 0.], vec![0., 2.], vec![10., 10.]];
+
+        assert!((sparseness(&[0., 0.], others.iter(), 2) - 1.5).abs() < f64::EPSILON);
+        assert!(sparseness(&[0., 0.], std::iter::empty(), 2).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn novel_behaviors_are_archived() {
+        let mut archive = NoveltyArchive::new();
+
+        let scores = archive.score(&[vec![0.], vec![0.], vec![5.]], 1, 1.);
+
+        assert_eq!(scores, vec![0., 0., 5.]);
+        assert_eq!(archive.behaviors(), &[vec![5.]]);
+
+        // Archived behaviors count as neighbors for the next generation
+        let scores = archive.score(&[vec![5.]], 1, 1.);
+        assert_eq!(scores, vec![0.]);
+        assert_eq!(archive.behaviors().len(), 1);
+    }
+}
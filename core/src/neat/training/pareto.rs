@@ -0,0 +1,138 @@
+/// Turns objective vectors into scalar scores that order genomes like NSGA-II does, a genome in a
+/// better nondominated front always scores higher and ties inside a front go to the less crowded
+/// genome
+pub fn pareto_scores(objectives: &[Vec<f64>]) -> Vec<f64> {
+    let ranks = nondominated_ranks(objectives);
+    let max_rank = ranks.iter().cloned().max().unwrap_or(0);
+
+    let mut scores = vec![0.; objectives.len()];
+
+    (0..=max_rank).for_each(|rank| {
+        let front: Vec<usize> = (0..objectives.len())
+            .filter(|i| ranks[*i] == rank)
+            .collect();
+        let distances = crowding_distances(objectives, &front);
+
+        front.iter().zip(distances).for_each(|(i, distance)| {
+            // Squashes the crowding distance below the gap between two fronts
+            let crowding = if distance.is_infinite() {
+                0.5
+            } else {
+                0.5 * distance / (1. + distance)
+            };
+
+            scores[*i] = -(rank as f64) + crowding;
+        });
+    });
+
+    scores
+}
+
+/// Every objective is maximized, `a` dominates `b` if it's no worse in all of them and better in
+/// at least one
+fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(a, b)| a >= b) && a.iter().zip(b).any(|(a, b)| a > b)
+}
+
+/// Index of the nondominated front every objective vector belongs to, the first front is 0
+fn nondominated_ranks(objectives: &[Vec<f64>]) -> Vec<usize> {
+    let count = objectives.len();
+    let mut ranks = vec![0; count];
+    let mut dominated_by: Vec<Vec<usize>> = vec![vec![]; count];
+    let mut domination_counts = vec![0; count];
+
+    (0..count).for_each(|i| {
+        (0..count).for_each(|j| {
+            if dominates(&objectives[i], &objectives[j]) {
+                dominated_by[i].push(j);
+            } else if dominates(&objectives[j], &objectives[i]) {
+                domination_counts[i] += 1;
+            }
+        })
+    });
+
+    let mut front: Vec<usize> = (0..count).filter(|i| domination_counts[*i] == 0).collect();
+    let mut rank = 0;
+
+    while !front.is_empty() {
+        let mut next_front = vec![];
+
+        front.iter().for_each(|i| {
+            ranks[*i] = rank;
+
+            dominated_by[*i].iter().for_each(|j| {
+                domination_counts[*j] -= 1;
+
+                if domination_counts[*j] == 0 {
+                    next_front.push(*j);
+                }
+            });
+        });
+
+        front = next_front;
+        rank += 1;
+    }
+
+    ranks
+}
+
+/// Crowding distance of every member of the front, members on the edges are infinitely far
+fn crowding_distances(objectives: &[Vec<f64>], front: &[usize]) -> Vec<f64> {
+    let mut distances = vec![0.; front.len()];
+    let objectives_count = front.first().map(|i| objectives[*i].len()).unwrap_or(0);
+
+    (0..objectives_count).for_each(|objective| {
+        let mut order: Vec<usize> = (0..front.len()).collect();
+        order.sort_by(|a, b| {
+            objectives[front[*a]][objective].total_cmp(&objectives[front[*b]][objective])
+        });
+
+        let first = *order.first().unwrap();
+        let last = *order.last().unwrap();
+        let range = objectives[front[last]][objective] - objectives[front[first]][objective];
+
+        distances[first] = f64::INFINITY;
+        distances[last] = f64::INFINITY;
+
+        if range > 0. {
+            order.windows(3).for_each(|window| {
+                let gap = objectives[front[window[2]]][objective]
+                    - objectives[front[window[0]]][objective];
+
+                distances[window[1]] += gap / range;
+            });
+        }
+    });
+
+    distances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_follow_domination() {
+        let objectives = vec![vec![1., 1.], vec![2., 2.], vec![3., 0.], vec![0., 0.]];
+
+        assert_eq!(nondominated_ranks(&objectives), vec![1, 0, 0, 2]);
+    }
+
+    #[test]
+    fn better_fronts_always_score_higher() {
+        let objectives = vec![
+            vec![0., 4.],
+            vec![1., 3.],
+            vec![2., 2.],
+            vec![4., 0.],
+            vec![1., 1.],
+        ];
+
+        let scores = pareto_scores(&objectives);
+
+        assert!(scores[..4].iter().all(|score| *score > scores[4]));
+        // The crowded middle of the front is worth less than its edges
+        assert!(scores[1] < scores[0]);
+        assert!(scores[1] < scores[3]);
+    }
+}
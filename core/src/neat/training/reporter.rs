@@ -1,6 +1,6 @@
 use super::NEAT;
 
-pub type Hook = fn(i: usize, &NEAT) -> ();
+pub type Hook = Box<dyn FnMut(usize, &NEAT)>;
 
 pub struct Reporter {
     hooks: Vec<(usize, Hook)>,
@@ -11,14 +11,17 @@ impl Reporter {
         Reporter { hooks: vec![] }
     }
 
-    pub fn register(&mut self, every: usize, hook: Hook) {
-        self.hooks.push((every, hook));
+    pub fn register<F>(&mut self, every: usize, hook: F)
+    where
+        F: FnMut(usize, &NEAT) + 'static,
+    {
+        self.hooks.push((every, Box::new(hook)));
     }
 
-    pub fn report(&self, i: usize, system: &NEAT) {
+    pub fn report(&mut self, i: usize, system: &NEAT) {
         self.hooks
-            .iter()
-            .filter(|(every, _)| i % every == 0)
+            .iter_mut()
+            .filter(|(every, _)| i % *every == 0)
             .for_each(|(_, hook)| hook(i, system));
     }
 }
@@ -77,4 +80,27 @@ mod tests {
             reporter.report(i, &system);
         }
     }
+
+    #[test]
+    fn hooks_can_capture_state() {
+        use crate::neat::NEAT;
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let mut reporter = Reporter::new();
+        let reported = Rc::new(RefCell::new(vec![]));
+
+        let reported_clone = reported.clone();
+        reporter.register(3, move |i, _| {
+            reported_clone.borrow_mut().push(i);
+        });
+
+        let system = NEAT::new(1, 1, |_| 0.);
+
+        for i in 1..=10 {
+            reporter.report(i, &system);
+        }
+
+        assert_eq!(*reported.borrow(), vec![3, 6, 9]);
+    }
 }
@@ -0,0 +1,121 @@
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use crate::genome::{Genome, GenomeId};
+use crate::Configuration;
+
+/// Holds all genomes and species, does the process of speciation
+#[derive(Debug)]
+pub struct GenomeBank {
+    configuration: Rc<RefCell<Configuration>>,
+    genomes: BTreeMap<GenomeId, Genome>,
+    previous_genomes: BTreeMap<GenomeId, Genome>,
+    fitnesses: BTreeMap<GenomeId, f64>,
+    previous_fitnesses: BTreeMap<GenomeId, f64>,
+}
+
+impl GenomeBank {
+    pub fn new(configuration: Rc<RefCell<Configuration>>) -> Self {
+        GenomeBank {
+            configuration,
+            genomes: BTreeMap::new(),
+            previous_genomes: BTreeMap::new(),
+            fitnesses: BTreeMap::new(),
+            previous_fitnesses: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a new genome
+    pub fn add_genome(&mut self, genome: Genome) {
+        self.genomes.insert(genome.id(), genome);
+    }
+
+    /// Clear genomes
+    pub fn clear(&mut self) {
+        let mut new_bank = GenomeBank::new(self.configuration.clone());
+        new_bank.previous_genomes = self.genomes.clone();
+        new_bank.previous_fitnesses = std::mem::take(&mut self.fitnesses);
+
+        *self = new_bank;
+    }
+
+    /// Returns a reference to the genomes
+    pub fn genomes(&self) -> &BTreeMap<GenomeId, Genome> {
+        &self.genomes
+    }
+
+    pub fn previous_genomes(&self) -> &BTreeMap<GenomeId, Genome> {
+        &self.previous_genomes
+    }
+
+    /// Tracks the fitness of a particular genome
+    pub fn mark_fitness(&mut self, genome_id: GenomeId, fitness: f64) {
+        self.fitnesses.insert(genome_id, fitness);
+    }
+
+    /// Returns a reference to the fitnesses
+    pub fn fitnesses(&self) -> &BTreeMap<GenomeId, f64> {
+        &self.fitnesses
+    }
+
+    /// The fitness the genome had in the previous generation if it was carried over unchanged
+    pub fn previous_fitness(&self, genome_id: &GenomeId) -> Option<f64> {
+        match (
+            self.genomes.get(genome_id),
+            self.previous_genomes.get(genome_id),
+        ) {
+            (Some(genome), Some(previous_genome)) if genome == previous_genome => {
+                self.previous_fitnesses.get(genome_id).copied()
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_add_genome() {
+        let configuration: Rc<RefCell<Configuration>> = Default::default();
+        let mut bank = GenomeBank::new(configuration);
+
+        let genome = Genome::new(1, 1);
+        bank.add_genome(genome);
+    }
+
+    #[test]
+    fn can_mark_fitness() {
+        let configuration: Rc<RefCell<Configuration>> = Default::default();
+        let mut bank = GenomeBank::new(configuration);
+
+        let genome = Genome::new(1, 1);
+        bank.add_genome(genome.clone());
+
+        bank.mark_fitness(genome.id(), 1337.);
+    }
+
+    #[test]
+    fn previous_fitness_needs_an_unchanged_genome() {
+        let configuration: Rc<RefCell<Configuration>> = Default::default();
+        let mut bank = GenomeBank::new(configuration);
+
+        let kept = Genome::new(1, 1);
+        let mut changed = Genome::new(1, 1);
+        bank.add_genome(kept.clone());
+        bank.add_genome(changed.clone());
+        bank.mark_fitness(kept.id(), 1.);
+        bank.mark_fitness(changed.id(), 2.);
+
+        bank.clear();
+        changed.node_mut(1).unwrap().bias += 1.;
+        bank.add_genome(kept.clone());
+        bank.add_genome(changed.clone());
+
+        assert_eq!(bank.previous_fitness(&kept.id()), Some(1.));
+        assert_eq!(bank.previous_fitness(&changed.id()), None);
+        assert!(bank.fitnesses().is_empty());
+    }
+}
@@ -1,61 +1,76 @@
+use num_traits::Float;
+use rand::{thread_rng, Rng};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::OnceLock;
+
 use crate::activation::*;
 use crate::aggregations::aggregate;
 use crate::connection::*;
-use crate::genome::Genome;
+use crate::genome::{feed_forward_depth, write_topology_summary, Genome};
 use crate::node::*;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(
     feature = "network-serde",
     derive(serde::Serialize, serde::Deserialize)
 )]
-pub struct Network {
+pub struct Network<T = f64> {
     pub input_count: usize,
     pub output_count: usize,
-    pub nodes: Vec<Node>,
-    pub connections: Vec<Connection>,
+    pub nodes: Vec<Node<T>>,
+    connections: Vec<Connection<T>>,
     node_calculation_order: Vec<usize>,
+    /// Indexes into `connections` of the connections ending in each node, built again on first use
+    /// after loading a network
+    #[cfg_attr(feature = "network-serde", serde(skip))]
+    incoming_connections: OnceLock<Vec<Vec<usize>>>,
+    /// Replaces NaN and infinite outputs with zero
+    #[cfg_attr(feature = "network-serde", serde(default))]
+    pub sanitize_outputs: bool,
+    /// The last input is set to 1 by the network itself and counts in `input_count`, exported
+    /// models keep it as a regular input
+    #[cfg_attr(feature = "network-serde", serde(default))]
+    pub bias_input: bool,
+    /// Names of the outputs used by `forward_pass_labeled`, outputs are named by their index when
+    /// they aren't set
+    #[cfg_attr(feature = "network-serde", serde(default))]
+    output_labels: Option<Vec<String>>,
 }
 
-impl Network {
-    fn is_node_ready(&self, index: usize) -> bool {
-        let node = self.nodes.get(index).unwrap();
-
-        let requirements_fullfilled = self.connections.iter().filter(|c| c.to == index).all(|c| {
-            let from_index = c.from;
-            let from_node = &self.nodes[from_index];
-
-            from_node.value.is_some()
-        });
-        let has_no_value = node.value.is_none();
+impl<T: Float> Network<T> {
+    /// Builds a network of any float precision, `Network::from` builds the default `f64` one
+    pub fn from_genome(g: &Genome) -> Self {
+        let nodes: Vec<Node<T>> = g.nodes().iter().map(From::from).collect();
+        let connections: Vec<Connection<T>> = g
+            .connections()
+            .iter()
+            .filter(|c| !c.disabled)
+            .map(From::from)
+            .collect();
 
-        requirements_fullfilled && has_no_value
+        Network {
+            input_count: g.input_count(),
+            output_count: g.output_count(),
+            nodes,
+            connections,
+            node_calculation_order: g.node_order().unwrap(),
+            incoming_connections: OnceLock::new(),
+            sanitize_outputs: false,
+            bias_input: g.has_bias_input(),
+            output_labels: None,
+        }
     }
 
-    pub fn forward_pass(&mut self, inputs: Vec<f64>) -> Vec<f64> {
-        for i in &self.node_calculation_order {
-            let node = self.nodes.get(*i).unwrap();
-
-            if matches!(node.kind, NodeKind::Input) {
-                self.nodes.get_mut(*i).unwrap().value = Some(*inputs.get(*i).unwrap());
-            } else {
-                let components: Vec<f64> = self
-                    .connections
-                    .iter()
-                    .filter(|c| c.to == *i)
-                    .map(|c| {
-                        let incoming_value = self.nodes.get(c.from).unwrap().value.unwrap();
-                        incoming_value * c.weight
-                    })
-                    .collect();
-
-                let aggregated = aggregate(&node.aggregation, &components);
-                let aggregated_with_bias = aggregated + node.bias;
+    /// Calculates the outputs and stores the value of every node, each pass starts from cleared
+    /// values so only recurrent connections can see what the previous pass calculated
+    pub fn forward_pass(&mut self, inputs: Vec<T>) -> Vec<T> {
+        let values = self.calculate_values(&inputs);
 
-                self.nodes.get_mut(*i).unwrap().value =
-                    Some(activate(aggregated_with_bias, &node.activation));
-            }
-        }
+        self.nodes
+            .iter_mut()
+            .zip(values)
+            .for_each(|(node, value)| node.value = value);
 
         self.nodes
             .iter()
@@ -139,34 +154,351 @@ impl Network {
         // outputs
     }
 
+    /// Returns the kind and value of every node after the most recent `forward_pass`, indexed the
+    /// same way as `nodes` so they can be matched with `connections`
+    pub fn node_values(&self) -> Vec<(&NodeKind, Option<T>)> {
+        self.nodes.iter().map(|n| (&n.kind, n.value)).collect()
+    }
+
+    /// Returns the indexes of the nodes in the order their values are calculated
+    pub fn node_order(&self) -> &[usize] {
+        &self.node_calculation_order
+    }
+
+    /// Does a forward pass for every row of inputs in order, the same as calling `forward_pass` for
+    /// each of them but the scratch buffers are reused between rows
+    pub fn forward_pass_batch(&mut self, inputs: &[Vec<T>]) -> Vec<Vec<T>> {
+        let mut previous: Vec<Option<T>> = self.nodes.iter().map(|n| n.value).collect();
+        let mut values: Vec<Option<T>> = Vec::with_capacity(self.nodes.len());
+        let mut components: Vec<T> = vec![];
+
+        let outputs: Vec<Vec<T>> = inputs
+            .iter()
+            .map(|row| {
+                self.calculate_values_into(
+                    row,
+                    |i| previous[i],
+                    &mut values,
+                    &mut components,
+                    true,
+                );
+                std::mem::swap(&mut previous, &mut values);
+
+                self.nodes
+                    .iter()
+                    .zip(&previous)
+                    .filter(|(n, _)| matches!(n.kind, NodeKind::Output))
+                    .map(|(_, value)| value.unwrap())
+                    .collect()
+            })
+            .collect();
+
+        self.nodes
+            .iter_mut()
+            .zip(previous)
+            .for_each(|(node, value)| node.value = value);
+
+        outputs
+    }
+
+    /// The connections of the network, disabled connection genes aren't part of it
+    pub fn connections(&self) -> &[Connection<T>] {
+        &self.connections
+    }
+
+    fn incoming_connections(&self) -> &[Vec<usize>] {
+        self.incoming_connections.get_or_init(|| {
+            let mut incoming_connections: Vec<Vec<usize>> = vec![vec![]; self.nodes.len()];
+            self.connections
+                .iter()
+                .enumerate()
+                .for_each(|(i, c)| incoming_connections[c.to].push(i));
+
+            incoming_connections
+        })
+    }
+
+    /// Number of nodes, inputs and outputs included
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Number of connections, disabled connection genes aren't part of the network
+    pub fn connection_count(&self) -> usize {
+        self.connections.len()
+    }
+
+    /// Names the outputs in order, there has to be a label for every output
+    pub fn set_output_labels(&mut self, labels: Vec<String>) {
+        if labels.len() != self.output_count {
+            panic!(
+                "Network has {} outputs but {} labels were given",
+                self.output_count,
+                labels.len()
+            );
+        }
+
+        self.output_labels = Some(labels);
+    }
+
+    pub fn output_labels(&self) -> Vec<String> {
+        self.output_labels
+            .clone()
+            .unwrap_or_else(|| (0..self.output_count).map(|i| i.to_string()).collect())
+    }
+
+    /// Does a `forward_pass` and pairs every output with its label
+    pub fn forward_pass_labeled(&mut self, inputs: Vec<T>) -> HashMap<String, T> {
+        let outputs = self.forward_pass(inputs);
+
+        self.output_labels().into_iter().zip(outputs).collect()
+    }
+
+    /// Does a forward pass that applies a softmax across the outputs instead of their own
+    /// activations, so the outputs sum up to 1, the probabilities are also what recurrent
+    /// connections read from the outputs in the next pass
+    pub fn forward_pass_softmax(&mut self, inputs: Vec<T>) -> Vec<T> {
+        let previous: Vec<Option<T>> = self.nodes.iter().map(|n| n.value).collect();
+        let mut values = vec![];
+
+        self.calculate_values_into(&inputs, |i| previous[i], &mut values, &mut vec![], false);
+
+        let output_indexes: Vec<usize> = self
+            .nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| matches!(n.kind, NodeKind::Output))
+            .map(|(i, _)| i)
+            .collect();
+        let probabilities = softmax(
+            &output_indexes
+                .iter()
+                .map(|i| values[*i].unwrap())
+                .collect::<Vec<T>>(),
+        );
+
+        output_indexes
+            .iter()
+            .zip(&probabilities)
+            .for_each(|(i, probability)| values[*i] = Some(*probability));
+        self.nodes
+            .iter_mut()
+            .zip(values)
+            .for_each(|(node, value)| node.value = value);
+
+        probabilities
+    }
+
+    /// Does a `forward_pass_softmax` and picks the index of an output with the probabilities as
+    /// weights, so the same inputs don't always lead to the same action
+    pub fn sample_action(&mut self, inputs: Vec<T>) -> usize {
+        self.sample_action_with_rng(inputs, &mut thread_rng())
+    }
+
+    pub fn sample_action_with_rng<R: Rng + ?Sized>(
+        &mut self,
+        inputs: Vec<T>,
+        rng: &mut R,
+    ) -> usize {
+        let probabilities = self.forward_pass_softmax(inputs);
+        let mut remaining = T::from(rng.gen::<f64>()).unwrap();
+
+        // Rounding can leave the probabilities a bit short of 1, the last output takes the rest
+        probabilities
+            .iter()
+            .position(|probability| {
+                if remaining < *probability {
+                    true
+                } else {
+                    remaining = remaining - *probability;
+                    false
+                }
+            })
+            .unwrap_or(probabilities.len() - 1)
+    }
+
+    /// Does a `forward_pass` and squashes the outputs into `[min, max]` so they can be used as
+    /// actions directly
+    pub fn forward_pass_clamped(&mut self, inputs: Vec<T>, min: T, max: T) -> Vec<T> {
+        clamp_outputs(&self.forward_pass(inputs), min, max)
+    }
+
+    /// Checks if the last `forward_pass` produced a NaN or infinite output
+    pub fn has_nonfinite_output(&self) -> bool {
+        self.nodes
+            .iter()
+            .filter(|n| matches!(n.kind, NodeKind::Output))
+            .any(|n| n.value.is_some_and(|value| !value.is_finite()))
+    }
+
+    /// Number of layers between the inputs and the furthest node, recurrent connections excluded
+    pub fn depth(&self) -> usize {
+        let edges: Vec<(usize, usize)> = self
+            .connections
+            .iter()
+            .filter(|c| !c.recurrent)
+            .map(|c| (c.from, c.to))
+            .collect();
+
+        feed_forward_depth(self.nodes.iter().map(|n| &n.kind), &edges)
+    }
+
+    /// Does a forward pass without changing the network so it can be shared between threads,
+    /// recurrent connections read the values stored by the last `forward_pass`
+    pub fn forward(&self, inputs: &[T]) -> Vec<T> {
+        let values = self.calculate_values(inputs);
+
+        self.nodes
+            .iter()
+            .zip(values)
+            .filter(|(n, _)| matches!(n.kind, NodeKind::Output))
+            .map(|(_, value)| value.unwrap())
+            .collect()
+    }
+
+    /// Calculates the values of all nodes into a scratch buffer indexed by node
+    fn calculate_values(&self, inputs: &[T]) -> Vec<Option<T>> {
+        let mut values = vec![];
+
+        self.calculate_values_into(
+            inputs,
+            |i| self.nodes.get(i).unwrap().value,
+            &mut values,
+            &mut vec![],
+            true,
+        );
+
+        values
+    }
+
+    /// Calculates the values of all nodes into the given buffers, recurrent connections read the
+    /// previous pass through `previous_value`, outputs are left before their activation unless
+    /// `activate_outputs` is set
+    fn calculate_values_into<P>(
+        &self,
+        inputs: &[T],
+        previous_value: P,
+        values: &mut Vec<Option<T>>,
+        components: &mut Vec<T>,
+        activate_outputs: bool,
+    ) where
+        P: Fn(usize) -> Option<T>,
+    {
+        let expected_inputs = self.input_count - self.bias_input as usize;
+
+        if inputs.len() != expected_inputs {
+            panic!(
+                "Network has {} inputs but {} input values were given",
+                expected_inputs,
+                inputs.len()
+            );
+        }
+
+        values.clear();
+        values.resize(self.nodes.len(), None);
+
+        for i in &self.node_calculation_order {
+            let node = self.nodes.get(*i).unwrap();
+
+            if matches!(node.kind, NodeKind::Input) {
+                // Only the bias input is missing from the given values
+                *values.get_mut(*i).unwrap() = Some(inputs.get(*i).copied().unwrap_or_else(T::one));
+            } else if matches!(node.kind, NodeKind::Constant) {
+                *values.get_mut(*i).unwrap() = Some(node.bias);
+            } else {
+                components.clear();
+                components.extend(
+                    self.incoming_connections()[*i]
+                        .iter()
+                        .map(|connection_index| &self.connections[*connection_index])
+                        .map(|c| {
+                            // Recurrent connections need the values from the previous pass, unset
+                            // ones count as zero
+                            let incoming_value = if c.recurrent {
+                                previous_value(c.from).unwrap_or_else(T::zero)
+                            } else {
+                                values.get(c.from).unwrap().unwrap()
+                            };
+
+                            incoming_value * c.weight
+                        }),
+                );
+
+                let aggregated = aggregate(&node.aggregation, components);
+                let aggregated_with_bias = aggregated + node.bias;
+                let mut value = if activate_outputs || !matches!(node.kind, NodeKind::Output) {
+                    activate(aggregated_with_bias, &node.activation)
+                } else {
+                    aggregated_with_bias
+                };
+
+                if self.sanitize_outputs
+                    && matches!(node.kind, NodeKind::Output)
+                    && !value.is_finite()
+                {
+                    value = T::zero();
+                }
+
+                *values.get_mut(*i).unwrap() = Some(value);
+            }
+        }
+    }
+
+    /// Forgets the values of the previous pass, recurrent connections will read zeros again
+    pub fn reset(&mut self) {
+        self.clear_values();
+    }
+
     fn clear_values(&mut self) {
         self.nodes.iter_mut().for_each(|n| n.value = None);
     }
 }
 
+/// Turns the values into a probability distribution, the largest value is subtracted first so
+/// large values don't overflow
+fn softmax<T: Float>(values: &[T]) -> Vec<T> {
+    let max = values
+        .iter()
+        .fold(T::neg_infinity(), |max, value| max.max(*value));
+    let exponentials: Vec<T> = values.iter().map(|value| (*value - max).exp()).collect();
+    let sum = exponentials
+        .iter()
+        .fold(T::zero(), |sum, value| sum + *value);
+
+    exponentials.into_iter().map(|value| value / sum).collect()
+}
+
+/// Squashes every output into `[min, max]`, NaN outputs become `min`
+pub fn clamp_outputs<T: Float>(outputs: &[T], min: T, max: T) -> Vec<T> {
+    outputs
+        .iter()
+        .map(|output| output.max(min).min(max))
+        .collect()
+}
+
+/// Summarizes the topology the same way as the genome it was built from, every connection of a
+/// network is enabled
+impl<T: Float> fmt::Display for Network<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_topology_summary(
+            f,
+            self.nodes.iter().map(|n| &n.kind),
+            self.connections.len(),
+            self.depth(),
+        )
+    }
+}
+
 impl From<&Genome> for Network {
     fn from(g: &Genome) -> Self {
-        let nodes: Vec<Node> = g.nodes().iter().map(From::from).collect();
-        let connections: Vec<Connection> = g
-            .connections()
-            .iter()
-            .filter(|c| !c.disabled)
-            .map(From::from)
-            .collect();
-
-        Network {
-            input_count: g.input_count(),
-            output_count: g.output_count(),
-            nodes,
-            connections,
-            node_calculation_order: g.node_order().unwrap(),
-        }
+        Network::from_genome(g)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aggregations::Aggregation;
 
     #[test]
     fn init_network() {
@@ -187,4 +519,398 @@ mod tests {
             dbg!(i, o);
         }
     }
+    #[test]
+    fn forward_pass_is_repeatable() {
+        let mut g = Genome::new(2, 2);
+        crate::mutations::add_node(&mut g, &Default::default(), &mut rand::thread_rng());
+        let mut n = Network::from(&g);
+
+        let first_output = n.forward_pass(vec![0.3, 0.7]);
+        n.forward_pass(vec![-1., 1.]);
+        let second_output = n.forward_pass(vec![0.3, 0.7]);
+
+        assert_eq!(first_output, second_output);
+    }
+
+    #[test]
+    #[should_panic(expected = "Network has 2 inputs but 3 input values were given")]
+    fn forward_pass_wrong_input_count() {
+        let g = Genome::new(2, 1);
+        let mut n = Network::from(&g);
+
+        n.forward_pass(vec![0., 1., 2.]);
+    }
+
+    #[test]
+    fn softmax_replaces_output_activations() {
+        let mut g = Genome::new(1, 3);
+        for (index, weight) in [1., 2., -1.].iter().enumerate() {
+            g.connection_mut(index).unwrap().weight = *weight;
+            g.node_mut(index + 1).unwrap().bias = 0.;
+            g.node_mut(index + 1).unwrap().aggregation = Aggregation::Sum;
+            g.node_mut(index + 1).unwrap().activation = ActivationKind::Step;
+        }
+        let mut n = Network::from(&g);
+
+        let probabilities = n.forward_pass_softmax(vec![1000.]);
+        assert!((probabilities.iter().sum::<f64>() - 1.).abs() < 1e-9);
+        assert!((probabilities[1] - 1.).abs() < 1e-9);
+
+        let probabilities = n.forward_pass_softmax(vec![1.]);
+        let total = 1f64.exp() + 2f64.exp() + (-1f64).exp();
+        assert!((probabilities[0] - 1f64.exp() / total).abs() < 1e-9);
+        assert!((probabilities[1] - 2f64.exp() / total).abs() < 1e-9);
+        assert!((probabilities[2] - (-1f64).exp() / total).abs() < 1e-9);
+        assert!((n.nodes[2].value.unwrap() - probabilities[1]).abs() < f64::EPSILON);
+
+        // The default path still uses the activation of every output
+        assert_eq!(n.forward_pass(vec![1.]), vec![1., 1., 0.]);
+    }
+
+    #[test]
+    fn actions_are_sampled_by_probability() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut g = Genome::new(1, 3);
+        for (index, weight) in [0., 1., 2.].iter().enumerate() {
+            g.connection_mut(index).unwrap().weight = *weight;
+            g.node_mut(index + 1).unwrap().bias = 0.;
+            g.node_mut(index + 1).unwrap().aggregation = Aggregation::Sum;
+        }
+        let mut n = Network::from(&g);
+        let probabilities = n.forward_pass_softmax(vec![1.]);
+
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut counts = [0; 3];
+        (0..10000).for_each(|_| counts[n.sample_action_with_rng(vec![1.], &mut rng)] += 1);
+
+        counts
+            .iter()
+            .zip(&probabilities)
+            .for_each(|(count, probability)| {
+                assert!((*count as f64 / 10000. - probability).abs() < 0.02);
+            });
+
+        let first: Vec<usize> = {
+            let mut rng = StdRng::seed_from_u64(3);
+            (0..20)
+                .map(|_| n.sample_action_with_rng(vec![1.], &mut rng))
+                .collect()
+        };
+        let mut rng = StdRng::seed_from_u64(3);
+        let second: Vec<usize> = (0..20)
+            .map(|_| n.sample_action_with_rng(vec![1.], &mut rng))
+            .collect();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    #[cfg(feature = "network-serde")]
+    fn loaded_networks_rebuild_the_incoming_connections() {
+        let mut g = Genome::new(2, 2);
+        crate::mutations::add_node(&mut g, &Default::default(), &mut rand::thread_rng());
+        let mut n = Network::from(&g);
+
+        let json = serde_json::to_string(&n).unwrap();
+        assert!(!json.contains("incoming_connections"));
+
+        let mut loaded: Network = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            loaded.forward_pass(vec![0.3, 0.7]),
+            n.forward_pass(vec![0.3, 0.7])
+        );
+    }
+
+    #[test]
+    fn outputs_can_be_labeled() {
+        let g = Genome::new(2, 2);
+        let mut n = Network::from(&g);
+        let outputs = n.forward_pass(vec![0.5, 1.]);
+
+        let unlabeled = n.forward_pass_labeled(vec![0.5, 1.]);
+        assert_eq!(unlabeled["0"], outputs[0]);
+        assert_eq!(unlabeled["1"], outputs[1]);
+
+        n.set_output_labels(vec!["force".to_owned(), "brake".to_owned()]);
+        let labeled = n.forward_pass_labeled(vec![0.5, 1.]);
+        assert_eq!(labeled.len(), 2);
+        assert_eq!(labeled["force"], outputs[0]);
+        assert_eq!(labeled["brake"], outputs[1]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Network has 2 outputs but 1 labels were given")]
+    fn output_labels_must_cover_every_output() {
+        let mut n = Network::from(&Genome::new(1, 2));
+
+        n.set_output_labels(vec!["force".to_owned()]);
+    }
+
+    #[test]
+    fn counts_skip_disabled_connections() {
+        let mut g = Genome::new(2, 1);
+        g.disable_connection(0);
+        let n = Network::from(&g);
+
+        assert_eq!(n.node_count(), 3);
+        assert_eq!(n.connection_count(), 1);
+    }
+
+    #[test]
+    fn outputs_are_clamped_into_the_range() {
+        assert_eq!(
+            clamp_outputs(&[-3., 0.5, 7., f64::NAN, f64::INFINITY], -1., 1.),
+            vec![-1., 0.5, 1., -1., 1.]
+        );
+
+        let mut g = Genome::new(1, 1);
+        g.connection_mut(0).unwrap().weight = 1.;
+        g.node_mut(1).unwrap().bias = 0.;
+        g.node_mut(1).unwrap().activation = ActivationKind::Identity;
+        g.node_mut(1).unwrap().aggregation = Aggregation::Sum;
+        let mut n = Network::from(&g);
+
+        assert_eq!(n.forward_pass_clamped(vec![5.], -2., 2.), vec![2.]);
+        assert_eq!(n.forward_pass_clamped(vec![-0.5], -2., 2.), vec![-0.5]);
+    }
+
+    #[test]
+    fn bias_input_is_supplied_by_the_network() {
+        use crate::Configuration;
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let configuration = Configuration {
+            add_bias_input: true,
+            ..Default::default()
+        };
+        let g = Genome::new_with_configuration(2, 1, &configuration, &mut StdRng::seed_from_u64(3));
+
+        assert!(g.has_bias_input());
+        assert_eq!(g.input_count(), 3);
+        assert!(g.connections().iter().any(|c| c.from == 2));
+
+        let mut with_bias = Network::from(&g);
+        let mut without_bias = Network::from(&g);
+        without_bias.bias_input = false;
+
+        assert_eq!(
+            with_bias.forward_pass(vec![0.5, -0.5]),
+            without_bias.forward_pass(vec![0.5, -0.5, 1.])
+        );
+    }
+
+    #[test]
+    fn recurrent_connection_reads_previous_pass() {
+        use crate::activation::ActivationKind;
+        use crate::aggregations::Aggregation;
+
+        let mut g = Genome::new(1, 1);
+
+        let output = g.node_mut(1).unwrap();
+        output.activation = ActivationKind::Identity;
+        output.aggregation = Aggregation::Sum;
+        output.bias = 0.;
+        g.connection_mut(0).unwrap().weight = 1.;
+
+        let index = g.add_recurrent_connection(1, 1).unwrap();
+        g.connection_mut(index).unwrap().weight = 1.;
+
+        let mut n = Network::from(&g);
+
+        assert_eq!(n.forward_pass(vec![1.]), vec![1.]);
+        assert_eq!(n.forward_pass(vec![1.]), vec![2.]);
+        assert_eq!(n.forward_pass(vec![1.]), vec![3.]);
+
+        n.reset();
+        assert_eq!(n.forward_pass(vec![1.]), vec![1.]);
+    }
+    #[test]
+    fn forward_matches_forward_pass() {
+        let g = Genome::new(2, 2);
+        let mut n = Network::from(&g);
+
+        let inputs: Vec<Vec<f64>> = vec![vec![0., 0.], vec![0., 1.], vec![1., 0.], vec![1., 1.]];
+
+        for i in inputs {
+            let shared_output = n.forward(&i);
+            let output = n.forward_pass(i);
+
+            assert_eq!(shared_output, output);
+        }
+    }
+    #[test]
+    fn node_values_after_forward_pass() {
+        let mut g = Genome::new(2, 1);
+        crate::mutations::add_node(&mut g, &Default::default(), &mut rand::thread_rng());
+        let mut n = Network::from(&g);
+
+        assert!(n.node_values().iter().all(|(_, value)| value.is_none()));
+
+        let outputs = n.forward_pass(vec![0.5, -0.5]);
+        let values = n.node_values();
+
+        assert_eq!(values.len(), n.nodes.len());
+        assert_eq!(values[0], (&NodeKind::Input, Some(0.5)));
+        assert_eq!(values[1], (&NodeKind::Input, Some(-0.5)));
+        assert_eq!(values[2], (&NodeKind::Output, Some(outputs[0])));
+        assert!(matches!(values[3], (NodeKind::Hidden, Some(_))));
+    }
+
+    #[test]
+    fn constant_node_outputs_its_value() {
+        let mut builder = Genome::builder(1, 1);
+        let constant = builder.add_constant_node(0.5);
+        builder.connect(0, 1, 0.).connect(constant, 1, 1.);
+
+        let mut genome = builder.build().unwrap();
+        let output = genome.node_mut(1).unwrap();
+        output.bias = 0.;
+        output.activation = ActivationKind::Identity;
+        output.aggregation = Aggregation::Sum;
+
+        let mut network = Network::from(&genome);
+
+        assert_eq!(network.forward_pass(vec![10.]), vec![0.5]);
+        assert_eq!(network.forward_pass(vec![-3.]), vec![0.5]);
+    }
+
+    #[test]
+    fn disconnected_genome_outputs_its_bias() {
+        let mut genome = Genome::new_with_connectivity(
+            2,
+            1,
+            &crate::InitialConnectivity::Unconnected,
+            &mut rand::thread_rng(),
+        );
+        let output = genome.node_mut(2).unwrap();
+        output.bias = 0.25;
+        output.activation = ActivationKind::Identity;
+        output.aggregation = Aggregation::Sum;
+
+        assert_eq!(genome.node_order(), Some(vec![0, 1, 2]));
+
+        let mut network = Network::from(&genome);
+
+        assert_eq!(network.forward_pass(vec![1., -1.]), vec![0.25]);
+    }
+
+    #[test]
+    fn genome_network_round_trip() {
+        let mut g = Genome::new(2, 2);
+        let mut rng = rand::thread_rng();
+        (0..3).for_each(|_| crate::mutations::add_node(&mut g, &Default::default(), &mut rng));
+        g.add_constant_node();
+
+        let mut network = Network::from(&g);
+        let imported = Genome::from(&network);
+        let mut round_tripped = Network::from(&imported);
+
+        assert_eq!(imported.input_count(), 2);
+        assert_eq!(imported.output_count(), 2);
+        assert_eq!(imported.nodes(), g.nodes());
+        assert!(imported.validate().is_ok());
+
+        for inputs in [vec![0., 0.], vec![0.3, -0.7], vec![1., 1.]] {
+            assert_eq!(
+                network.forward_pass(inputs.clone()),
+                round_tripped.forward_pass(inputs)
+            );
+        }
+    }
+
+    #[test]
+    fn nonfinite_outputs_are_detected_and_sanitized() {
+        // Two huge constants of opposite signs sum up to infinity minus infinity
+        let mut builder = Genome::builder(1, 1);
+        let positive = builder.add_constant_node(f64::MAX);
+        let negative = builder.add_constant_node(-f64::MAX);
+        builder
+            .connect(0, 1, 1.)
+            .connect(positive, 1, 10.)
+            .connect(negative, 1, 10.);
+
+        let mut genome = builder.build().unwrap();
+        let output = genome.node_mut(1).unwrap();
+        output.bias = 0.;
+        output.activation = ActivationKind::Identity;
+        output.aggregation = Aggregation::Sum;
+
+        let mut network = Network::from(&genome);
+
+        assert!(network.forward_pass(vec![1.])[0].is_nan());
+        assert!(network.has_nonfinite_output());
+
+        network.sanitize_outputs = true;
+
+        assert_eq!(network.forward_pass(vec![1.]), vec![0.]);
+        assert_eq!(network.forward(&[1.]), vec![0.]);
+        assert!(!network.has_nonfinite_output());
+    }
+
+    #[test]
+    fn f32_network_matches_f64() {
+        let mut g = Genome::new(2, 2);
+        crate::mutations::add_node(&mut g, &Default::default(), &mut rand::thread_rng());
+
+        let network_f64: Network = Network::from(&g);
+        let network_f32: Network<f32> = Network::from_genome(&g);
+
+        let outputs_f64 = network_f64.forward(&[0.3, 0.7]);
+        let outputs_f32 = network_f32.forward(&[0.3, 0.7]);
+
+        outputs_f64
+            .iter()
+            .zip(outputs_f32)
+            .for_each(|(a, b)| assert!((*a as f32 - b).abs() < 1e-4));
+    }
+
+    #[test]
+    fn forward_pass_batch_matches_single_passes() {
+        let mut g = Genome::new(2, 2);
+        crate::mutations::add_node(&mut g, &Default::default(), &mut rand::thread_rng());
+        let hidden = g.nodes().len() - 1;
+        let output = g
+            .connections()
+            .iter()
+            .find(|c| c.from == hidden)
+            .unwrap()
+            .to;
+        g.add_recurrent_connection(output, hidden).unwrap();
+
+        let inputs: Vec<Vec<f64>> = vec![vec![0., 0.], vec![0., 1.], vec![1., 0.], vec![1., 1.]];
+
+        let mut single = Network::from(&g);
+        let single_outputs: Vec<Vec<f64>> = inputs
+            .iter()
+            .map(|i| single.forward_pass(i.clone()))
+            .collect();
+
+        let mut batched = Network::from(&g);
+        let batched_outputs = batched.forward_pass_batch(&inputs);
+
+        assert_eq!(
+            single_outputs
+                .iter()
+                .flatten()
+                .map(|o| o.to_bits())
+                .collect::<Vec<u64>>(),
+            batched_outputs
+                .iter()
+                .flatten()
+                .map(|o| o.to_bits())
+                .collect::<Vec<u64>>()
+        );
+        assert_eq!(
+            single
+                .node_values()
+                .iter()
+                .map(|(_, v)| v.map(f64::to_bits))
+                .collect::<Vec<_>>(),
+            batched
+                .node_values()
+                .iter()
+                .map(|(_, v)| v.map(f64::to_bits))
+                .collect::<Vec<_>>()
+        );
+    }
 }
@@ -1,6 +1,8 @@
+use num_traits::Float;
+
 use crate::activation::ActivationKind;
 use crate::aggregations::Aggregation;
-use crate::genome::node::NodeGene;
+use crate::genome::NodeGene;
 
 #[derive(Debug, Clone, PartialEq, Hash)]
 #[cfg_attr(
@@ -14,27 +16,83 @@ pub enum NodeKind {
     Constant,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[cfg_attr(
     feature = "network-serde",
     derive(serde::Serialize, serde::Deserialize)
 )]
-pub struct Node {
+pub struct Node<T = f64> {
     pub kind: NodeKind,
     pub aggregation: Aggregation,
     pub activation: ActivationKind,
-    pub bias: f64,
-    pub value: Option<f64>,
+    pub bias: T,
+    pub value: Option<T>,
 }
 
-impl From<&NodeGene> for Node {
+impl<T: Float> From<&NodeGene> for Node<T> {
     fn from(g: &NodeGene) -> Self {
         Node {
             kind: g.kind.clone(),
             activation: g.activation.clone(),
-            bias: g.bias,
+            bias: T::from(g.bias).unwrap(),
             value: None,
             aggregation: g.aggregation.clone(),
         }
     }
 }
+
+impl<T: Float> From<&Node<T>> for NodeGene {
+    fn from(n: &Node<T>) -> Self {
+        NodeGene {
+            kind: n.kind.clone(),
+            activation: n.activation.clone(),
+            bias: n.bias.to_f64().unwrap(),
+            aggregation: n.aggregation.clone(),
+            frozen: false,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "network-serde"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn node_serde_round_trip() {
+        let activations = vec![
+            ActivationKind::Input,
+            ActivationKind::Tanh,
+            ActivationKind::Relu,
+            ActivationKind::Step,
+            ActivationKind::Logistic,
+            ActivationKind::Identity,
+            ActivationKind::Softsign,
+            ActivationKind::Sinusoid,
+            ActivationKind::Gaussian,
+            ActivationKind::BentIdentity,
+            ActivationKind::Bipolar,
+            ActivationKind::Inverse,
+            ActivationKind::SELU,
+            ActivationKind::Swish,
+            ActivationKind::Mish,
+            ActivationKind::GELU,
+        ];
+
+        for activation in activations {
+            let node = Node {
+                kind: NodeKind::Hidden,
+                aggregation: Aggregation::Sum,
+                activation,
+                bias: 0.5,
+                value: None,
+            };
+
+            let json = serde_json::to_string(&node).unwrap();
+            let imported_node: Node = serde_json::from_str(&json).unwrap();
+
+            assert_eq!(imported_node.activation, node.activation);
+            assert_eq!(imported_node.kind, node.kind);
+            assert_eq!(imported_node.bias, node.bias);
+        }
+    }
+}
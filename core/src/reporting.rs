@@ -1,37 +1,65 @@
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
 use std::time::Instant;
 
-use crate::genome::Genome;
+use crate::genome::{Genome, GenomeId};
+use crate::speciation::SpeciesStat;
 
-// TODO
-pub type Species = usize;
 pub struct Generation {
-    number: usize,
-    started: Instant,
+    pub number: usize,
+    pub started: Instant,
 }
+
 pub struct Population<'system> {
-    genomes: Vec<Genome>,
-    fitnesses: HashMap<&'system Genome, f64>,
+    pub genomes: &'system BTreeMap<GenomeId, Genome>,
+    pub fitnesses: &'system BTreeMap<GenomeId, f64>,
 }
 
+/// Structured callbacks for the lifecycle of the evolution process, every method does nothing by
+/// default so implementors only pick the events they care about
+#[allow(unused_variables)]
 pub trait Reporter {
-    fn on_generation_start(generation: Generation) {}
-    fn on_generation_end(generation: Generation, population: Population, species: &[Species]) {}
-    fn on_evaluation_end(population: Population, species: &[Species], best_genome: &Genome) {}
-    fn on_reproduction_end(population: Population, species: &[Species]) {}
-    fn on_extinction() {}
-    fn on_solution_found(generation: Generation, population: Population, best_genome: &Genome) {}
-    fn on_species_stagnant(species_id: usize, species: &[Species]) {}
+    fn on_generation_start(&mut self, generation: &Generation) {}
+    fn on_generation_end(
+        &mut self,
+        generation: &Generation,
+        population: &Population,
+        species: &[SpeciesStat],
+    ) {
+    }
+    fn on_evaluation_end(
+        &mut self,
+        population: &Population,
+        species: &[SpeciesStat],
+        best_genome: &Genome,
+    ) {
+    }
+    fn on_reproduction_end(&mut self, population: &Population, species: &[SpeciesStat]) {}
+    fn on_extinction(&mut self) {}
+    fn on_solution_found(
+        &mut self,
+        generation: &Generation,
+        population: &Population,
+        best_genome: &Genome,
+    ) {
+    }
+    fn on_species_stagnant(&mut self, species_id: usize, species: &[SpeciesStat]) {}
+    fn on_species_hypermutation(&mut self, species_id: usize, species: &[SpeciesStat]) {}
 }
 
-struct StdoutReporter;
+pub struct StdoutReporter;
 
 impl Reporter for StdoutReporter {
-    fn on_generation_start(generation: Generation) {
+    fn on_generation_start(&mut self, generation: &Generation) {
         println!("Running generation {}", generation.number);
     }
 
-    fn on_generation_end(generation: Generation, population: Population, species: &[Species]) {
+    fn on_generation_end(
+        &mut self,
+        generation: &Generation,
+        population: &Population,
+        species: &[SpeciesStat],
+    ) {
         println!(
             "Generation {} done in {} seconds with {} members in {} species",
             generation.number,
@@ -41,7 +69,12 @@ impl Reporter for StdoutReporter {
         );
     }
 
-    fn on_evaluation_end(population: Population, species: &[Species], best_genome: &Genome) {
+    fn on_evaluation_end(
+        &mut self,
+        population: &Population,
+        _species: &[SpeciesStat],
+        best_genome: &Genome,
+    ) {
         let average_fitness = population
             .fitnesses
             .iter()
@@ -52,23 +85,179 @@ impl Reporter for StdoutReporter {
         println!(
             "Evaluated members have an average fitness of {}, best genome has {}",
             average_fitness,
-            population.fitnesses.get(best_genome).unwrap()
+            population.fitnesses.get(&best_genome.id()).unwrap()
         );
     }
 
-    fn on_extinction() {
+    fn on_extinction(&mut self) {
         println!("All species are extinct");
     }
 
-    fn on_solution_found(generation: Generation, population: Population, best_genome: &Genome) {
+    fn on_solution_found(
+        &mut self,
+        generation: &Generation,
+        population: &Population,
+        best_genome: &Genome,
+    ) {
         println!(
             "Best genome found in generation {} and has fitness {}",
             generation.number,
-            population.fitnesses.get(best_genome).unwrap()
+            population.fitnesses.get(&best_genome.id()).unwrap()
         );
     }
 
-    fn on_species_stagnant(species_id: usize, species: &[Species]) {
+    fn on_species_stagnant(&mut self, species_id: usize, _species: &[SpeciesStat]) {
         println!("Removing stagnant species {}", species_id);
     }
+
+    fn on_species_hypermutation(&mut self, species_id: usize, _species: &[SpeciesStat]) {
+        println!("Raising the mutation rate of species {}", species_id);
+    }
+}
+
+/// Writes one CSV row of statistics per generation into the given sink, the first write error is
+/// kept and stops any further writing
+pub struct CsvReporter<W: Write> {
+    sink: W,
+    header_written: bool,
+    error: Option<io::Error>,
+}
+
+impl<W: Write> CsvReporter<W> {
+    pub fn new(sink: W) -> Self {
+        CsvReporter {
+            sink,
+            header_written: false,
+            error: None,
+        }
+    }
+
+    /// Returns the first error that happened while writing
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.sink
+    }
+
+    fn write_row(
+        &mut self,
+        generation: &Generation,
+        population: &Population,
+        species: &[SpeciesStat],
+    ) -> io::Result<()> {
+        if !self.header_written {
+            writeln!(
+                self.sink,
+                "generation,best_fitness,mean_fitness,std_fitness,num_species,largest_species_size,total_connections_of_best"
+            )?;
+            self.header_written = true;
+        }
+
+        let fitnesses: Vec<f64> = population.fitnesses.values().cloned().collect();
+        let count = fitnesses.len() as f64;
+        let mean_fitness = fitnesses.iter().sum::<f64>() / count;
+        let std_fitness = (fitnesses
+            .iter()
+            .map(|fitness| (fitness - mean_fitness).powi(2))
+            .sum::<f64>()
+            / count)
+            .sqrt();
+
+        let (best_genome_id, best_fitness) = population
+            .fitnesses
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(genome_id, fitness)| (Some(*genome_id), *fitness))
+            .unwrap_or((None, f64::NAN));
+        let total_connections_of_best = best_genome_id
+            .and_then(|genome_id| population.genomes.get(&genome_id))
+            .map(|genome| genome.connections().iter().filter(|c| !c.disabled).count())
+            .unwrap_or(0);
+
+        let largest_species_size = species
+            .iter()
+            .map(|stat| stat.member_count)
+            .max()
+            .unwrap_or(0);
+
+        writeln!(
+            self.sink,
+            "{},{},{},{},{},{},{}",
+            generation.number,
+            best_fitness,
+            mean_fitness,
+            std_fitness,
+            species.len(),
+            largest_species_size,
+            total_connections_of_best
+        )?;
+        self.sink.flush()
+    }
+}
+
+impl<W: Write> Reporter for CsvReporter<W> {
+    fn on_generation_end(
+        &mut self,
+        generation: &Generation,
+        population: &Population,
+        species: &[SpeciesStat],
+    ) {
+        if self.error.is_none() {
+            if let Err(e) = self.write_row(generation, population, species) {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_reporter_writes_rows() {
+        let genome = Genome::new(2, 1);
+        let other_genome = Genome::new(2, 1);
+
+        let genomes: BTreeMap<GenomeId, Genome> = vec![
+            (genome.id(), genome.clone()),
+            (other_genome.id(), other_genome.clone()),
+        ]
+        .into_iter()
+        .collect();
+        let fitnesses: BTreeMap<GenomeId, f64> = vec![(genome.id(), 3.), (other_genome.id(), 1.)]
+            .into_iter()
+            .collect();
+        let population = Population {
+            genomes: &genomes,
+            fitnesses: &fitnesses,
+        };
+        let species = vec![SpeciesStat {
+            id: 1,
+            member_count: 2,
+            mean_fitness: Some(2.),
+            adjusted_fitness: Some(1.),
+            generations_since_improvement: 0,
+        }];
+
+        let mut reporter = CsvReporter::new(vec![]);
+
+        for number in 1..=2 {
+            let generation = Generation {
+                number,
+                started: Instant::now(),
+            };
+            reporter.on_generation_end(&generation, &population, &species);
+        }
+
+        let csv = String::from_utf8(reporter.into_inner()).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("generation,best_fitness"));
+        assert_eq!(lines[1], "1,3,2,1,1,2,2");
+        assert_eq!(lines[2], "2,3,2,1,1,2,2");
+    }
 }
@@ -1,58 +1,103 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
 
 use crate::Configuration;
 use crate::{ConnectionGene, Genome};
 
-type DistanceKey = String;
+/// Measures how different two genomes are, speciation groups genomes closer than the
+/// compatibility threshold
+pub trait DistanceMetric {
+    fn distance(&self, a: &Genome, b: &Genome) -> f64;
+}
+
+/// The default metric, it combines disjoint connections with weight, disabled, bias, activation
+/// and aggregation differences using the configured coefficients
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenomicDistance {
+    pub connection_disjoint_coefficient: f64,
+    pub connection_weight_coefficient: f64,
+    pub connection_disabled_coefficient: f64,
+    pub node_bias_coefficient: f64,
+    pub node_activation_coefficient: f64,
+    pub node_aggregation_coefficient: f64,
+}
+
+impl From<&Configuration> for GenomicDistance {
+    fn from(configuration: &Configuration) -> Self {
+        GenomicDistance {
+            connection_disjoint_coefficient: configuration.distance_connection_disjoint_coefficient,
+            connection_weight_coefficient: configuration.distance_connection_weight_coeficcient,
+            connection_disabled_coefficient: configuration.distance_connection_disabled_coefficient,
+            node_bias_coefficient: configuration.distance_node_bias_coefficient,
+            node_activation_coefficient: configuration.distance_node_activation_coefficient,
+            node_aggregation_coefficient: configuration.distance_node_aggregation_coefficient,
+        }
+    }
+}
+
+type DistanceKey = (u64, u64);
+
+/// Distances between genomes keyed by their hashes, it's kept between speciations so genomes
+/// that survive a generation unchanged aren't measured again
+#[derive(Default)]
 pub struct GenomicDistanceCache {
-    configuration: Rc<RefCell<Configuration>>,
     cache: HashMap<DistanceKey, f64>,
 }
 
 impl GenomicDistanceCache {
-    pub fn new(configuration: Rc<RefCell<Configuration>>) -> Self {
-        GenomicDistanceCache {
-            configuration,
-            cache: HashMap::new(),
-        }
+    pub fn new() -> Self {
+        GenomicDistanceCache::default()
     }
 
-    pub fn get(&mut self, a: &Genome, b: &Genome) -> f64 {
+    pub fn get(&mut self, metric: &dyn DistanceMetric, a: &Genome, b: &Genome) -> f64 {
         let distance_key = GenomicDistanceCache::make_key(a, b);
 
-        if let Some(distance) = self.cache.get(&distance_key) {
-            *distance
-        } else {
-            let distance = self.distance(a, b);
-            self.cache.insert(distance_key, distance);
+        *self
+            .cache
+            .entry(distance_key)
+            .or_insert_with(|| metric.distance(a, b))
+    }
 
-            distance
-        }
+    pub fn mean(&self) -> f64 {
+        self.cache.values().sum::<f64>() / self.cache.len() as f64
     }
 
-    fn distance(&self, a: &Genome, b: &Genome) -> f64 {
-        let (
-            distance_connection_disjoint_coefficient,
-            distance_connection_weight_coeficcient,
-            distance_connection_disabled_coefficient,
-            distance_node_bias_coefficient,
-            distance_node_activation_coefficient,
-            distance_node_aggregation_coefficient,
-        ) = {
-            let conf = self.configuration.borrow();
-
-            (
-                conf.distance_connection_disjoint_coefficient,
-                conf.distance_connection_weight_coeficcient,
-                conf.distance_connection_disabled_coefficient,
-                conf.distance_node_bias_coefficient,
-                conf.distance_node_activation_coefficient,
-                conf.distance_node_aggregation_coefficient,
-            )
-        };
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    /// Forgets the distances that involve a genome other than the given ones, a changed genome
+    /// hashes differently so its old distances go as well
+    pub fn retain<'g>(&mut self, genomes: impl Iterator<Item = &'g Genome>) {
+        let hashes: HashSet<u64> = genomes.map(GenomicDistanceCache::hash).collect();
 
+        self.cache
+            .retain(|(hash_a, hash_b), _| hashes.contains(hash_a) && hashes.contains(hash_b));
+    }
+
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    fn hash(genome: &Genome) -> u64 {
+        use std::collections::hash_map::DefaultHasher;
+        use std::hash::{Hash, Hasher};
+
+        let mut hasher = DefaultHasher::new();
+        genome.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn make_key(a: &Genome, b: &Genome) -> DistanceKey {
+        let hash_a = GenomicDistanceCache::hash(a);
+        let hash_b = GenomicDistanceCache::hash(b);
+
+        (u64::max(hash_a, hash_b), u64::min(hash_a, hash_b))
+    }
+}
+
+impl DistanceMetric for GenomicDistance {
+    fn distance(&self, a: &Genome, b: &Genome) -> f64 {
         let mut distance = 0.;
 
         let max_connection_genes = usize::max(a.connections().len(), b.connections().len());
@@ -103,7 +148,7 @@ impl GenomicDistanceCache {
             });
 
         let disjoint_factor =
-            disjoint_connections.len() as f64 * distance_connection_disjoint_coefficient;
+            disjoint_connections.len() as f64 * self.connection_disjoint_coefficient;
 
         let connections_difference_factor: f64 = common_connections
             .iter()
@@ -111,11 +156,11 @@ impl GenomicDistanceCache {
                 let mut connection_distance = 0.;
 
                 if connection_a.disabled != connection_b.disabled {
-                    connection_distance += 1. * distance_connection_disabled_coefficient;
+                    connection_distance += 1. * self.connection_disabled_coefficient;
                 }
 
                 connection_distance += (connection_a.weight - connection_b.weight).abs()
-                    * distance_connection_weight_coeficcient;
+                    * self.connection_weight_coefficient;
 
                 connection_distance
             })
@@ -129,51 +174,26 @@ impl GenomicDistanceCache {
                 let mut node_distance = 0.;
 
                 if node_a.activation != node_b.activation {
-                    node_distance += 1. * distance_node_activation_coefficient;
+                    node_distance += 1. * self.node_activation_coefficient;
                 }
 
                 if node_a.aggregation != node_b.aggregation {
-                    node_distance += 1. * distance_node_aggregation_coefficient;
+                    node_distance += 1. * self.node_aggregation_coefficient;
                 }
 
-                node_distance += (node_a.bias - node_b.bias).abs() * distance_node_bias_coefficient;
+                node_distance += (node_a.bias - node_b.bias).abs() * self.node_bias_coefficient;
 
                 node_distance
             })
             .sum();
 
         distance += nodes_difference_factor;
-        distance += (connections_difference_factor + disjoint_factor) / max_connection_genes as f64;
+        // Genomes without any connections only differ in their nodes
+        if max_connection_genes > 0 {
+            distance +=
+                (connections_difference_factor + disjoint_factor) / max_connection_genes as f64;
+        }
 
         distance
     }
-
-    pub fn mean(&self) -> f64 {
-        self.cache.values().sum::<f64>() / self.cache.len() as f64
-    }
-
-    fn make_key<'o>(a: &'o Genome, b: &'o Genome) -> String {
-        use std::collections::hash_map::DefaultHasher;
-        use std::hash::{Hash, Hasher};
-
-        let hash_a = {
-            let mut hasher = DefaultHasher::new();
-            a.hash(&mut hasher);
-            hasher.finish()
-        };
-
-        let hash_b = {
-            let mut hasher = DefaultHasher::new();
-            b.hash(&mut hasher);
-            hasher.finish()
-        };
-
-        hash_a.to_string();
-
-        if hash_a > hash_b {
-            hash_a.to_string() + &hash_b.to_string()
-        } else {
-            hash_b.to_string() + &hash_a.to_string()
-        }
-    }
 }
@@ -1,19 +1,26 @@
 use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::rc::Rc;
 
 use crate::Configuration;
 use crate::{Genome, GenomeId};
 
 use distance::GenomicDistanceCache;
+pub use distance::{DistanceMetric, GenomicDistance};
 
 mod distance;
 
 pub struct SpeciesSet {
     configuration: Rc<RefCell<Configuration>>,
     last_index: Option<usize>,
-    species: HashMap<usize, Species>,
+    species: BTreeMap<usize, Species>,
+    compatibility_threshold: Option<f64>,
+    distance_metric: Option<Box<dyn DistanceMetric>>,
+    distances: GenomicDistanceCache,
+    /// The configured metric the cached distances were measured with
+    cached_metric: Option<GenomicDistance>,
 }
 
 impl SpeciesSet {
@@ -21,45 +28,111 @@ impl SpeciesSet {
         SpeciesSet {
             configuration,
             last_index: None,
-            species: HashMap::new(),
+            species: BTreeMap::new(),
+            compatibility_threshold: None,
+            distance_metric: None,
+            distances: GenomicDistanceCache::new(),
+            cached_metric: None,
         }
     }
 
-    pub fn species(&self) -> &HashMap<usize, Species> {
+    /// Replaces the configured `GenomicDistance` with a custom metric
+    pub fn set_distance_metric(&mut self, metric: Box<dyn DistanceMetric>) {
+        self.distance_metric = Some(metric);
+        self.distances.clear();
+    }
+
+    pub fn species(&self) -> &BTreeMap<usize, Species> {
         &self.species
     }
 
+    /// The threshold currently used for speciation, it differs from the configured one only when
+    /// a target species count is set
+    pub fn compatibility_threshold(&self) -> f64 {
+        self.compatibility_threshold
+            .unwrap_or_else(|| self.configuration.borrow().compatibility_threshold)
+    }
+
+    /// Checks if every species has gone without improvement for too long, that is also the case
+    /// when all of them were removed for stagnating
+    pub fn is_stagnant(&self, generation: usize) -> bool {
+        let stagnation_after = self.configuration.borrow().stagnation_after;
+
+        self.species
+            .values()
+            .all(|species| generation - species.last_improved >= stagnation_after)
+    }
+
+    /// Forgets every species so the next speciation starts from scratch
+    pub fn clear(&mut self) {
+        self.species.clear();
+    }
+
+    /// Summarizes every species as of the given generation
+    pub fn stats(&self, generation: usize) -> Vec<SpeciesStat> {
+        self.species
+            .iter()
+            .map(|(species_id, species)| SpeciesStat {
+                id: *species_id,
+                member_count: species.members.len(),
+                mean_fitness: species.fitness,
+                adjusted_fitness: species.adjusted_fitness,
+                generations_since_improvement: generation.saturating_sub(species.last_improved),
+            })
+            .collect()
+    }
+
+    /// Splits the current genomes into species and returns the ids of removed stagnant species
     pub fn speciate(
         &mut self,
         generation: usize,
         current_genomes: &[GenomeId],
         all_genomes: &HashMap<GenomeId, Genome>,
-        fitnesses: &HashMap<GenomeId, f64>,
-    ) {
-        let (compatibility_threshold, stagnation_after, elitism_species) = {
+        fitnesses: &BTreeMap<GenomeId, f64>,
+    ) -> Vec<usize> {
+        let compatibility_threshold = self.compatibility_threshold();
+        let (stagnation_after, elitism_species, target_species, compatibility_threshold_step) = {
             let config = self.configuration.borrow();
 
             (
-                config.compatibility_threshold,
                 config.stagnation_after,
                 config.elitism_species,
+                config.target_species,
+                config.compatibility_threshold_step,
             )
         };
 
-        let mut distances = GenomicDistanceCache::new(self.configuration.clone());
+        // Distances measured with different coefficients are stale
+        let configured_metric = GenomicDistance::from(&*self.configuration.borrow());
+        if self.distance_metric.is_none() && self.cached_metric.as_ref() != Some(&configured_metric)
+        {
+            self.distances.clear();
+            self.cached_metric = Some(configured_metric.clone());
+        }
 
-        let mut unspeciated_genomes: HashSet<GenomeId> = current_genomes.iter().cloned().collect();
-        let mut new_species: HashMap<usize, Species> = self.species.clone();
+        let metric = self
+            .distance_metric
+            .as_deref()
+            .unwrap_or(&configured_metric);
+        let distances = &mut self.distances;
+
+        let mut unspeciated_genomes: BTreeSet<GenomeId> = current_genomes.iter().cloned().collect();
+        let mut new_species: BTreeMap<usize, Species> = self.species.clone();
 
         // Find new representatives for existing species
         self.species.iter().for_each(|(species_id, species)| {
             let genome_representative = all_genomes.get(&species.representative).unwrap();
 
+            // A genome already representing another species can't be shared
             let (maybe_new_representative_id, _) = current_genomes
                 .iter()
+                .filter(|genome_id| unspeciated_genomes.contains(genome_id))
                 .map(|genome_id| {
                     let genome = all_genomes.get(genome_id).unwrap();
-                    (genome_id, distances.get(genome, genome_representative))
+                    (
+                        genome_id,
+                        distances.get(metric, genome, genome_representative),
+                    )
                 })
                 .filter(|(_, distance)| *distance < compatibility_threshold)
                 .fold(
@@ -102,7 +175,7 @@ impl SpeciesSet {
 
                         (
                             species_id,
-                            distances.get(genome, species_representative_genome),
+                            distances.get(metric, genome, species_representative_genome),
                         )
                     })
                     .filter(|(_, distance)| *distance < compatibility_threshold)
@@ -139,6 +212,13 @@ impl SpeciesSet {
             }
         });
 
+        // Later generations only compare against genomes of this one
+        distances.retain(
+            current_genomes
+                .iter()
+                .map(|id| all_genomes.get(id).unwrap()),
+        );
+
         // Calculate fitness for every species
         new_species.iter_mut().for_each(|(_, mut species)| {
             let member_fitnesses: Vec<f64> = species
@@ -163,20 +243,25 @@ impl SpeciesSet {
             species.fitness_history.push(species_mean_fitness);
         });
 
-        // Calculate adjusted fitness for every species
+        // Calculate adjusted fitness for every species, the species fitness is already the sum of
+        // member fitnesses shared by the species size
+        let min_fitness = new_species
+            .values()
+            .flat_map(|species| species.members.iter())
+            .map(|member_genome_id| *fitnesses.get(member_genome_id).unwrap())
+            .fold(f64::MAX, f64::min);
         let species_fitnesses: Vec<f64> = new_species
-            .iter()
-            .map(|(_, species)| species.fitness.unwrap())
+            .values()
+            .map(|species| species.fitness.unwrap())
             .collect();
+        let adjusted_fitnesses = share_fitness(&species_fitnesses, min_fitness);
 
-        new_species.iter_mut().for_each(|(_, mut species)| {
-            let own_exp = species.fitness.unwrap().exp();
-            let exp_sum: f64 = species_fitnesses.iter().map(|fitness| fitness.exp()).sum();
-
-            let adjusted_fitness = own_exp / exp_sum;
-
-            species.adjusted_fitness = Some(adjusted_fitness);
-        });
+        new_species
+            .values_mut()
+            .zip(adjusted_fitnesses)
+            .for_each(|(species, adjusted_fitness)| {
+                species.adjusted_fitness = Some(adjusted_fitness);
+            });
 
         // Remove stagnated species
         let mut stagnated_ids_and_adjusted_fitnesses: Vec<(usize, f64)> = new_species
@@ -185,26 +270,111 @@ impl SpeciesSet {
             .map(|(id, species)| (*id, species.adjusted_fitness.unwrap()))
             .collect();
 
-        stagnated_ids_and_adjusted_fitnesses.sort_by(|a, b| {
-            use std::cmp::Ordering::*;
+        stagnated_ids_and_adjusted_fitnesses.sort_by(|a, b| b.1.total_cmp(&a.1));
 
-            if a.1 > b.1 {
-                Less
-            } else {
-                Greater
-            }
+        let removed_species_ids: Vec<usize> = stagnated_ids_and_adjusted_fitnesses
+            .iter()
+            .take(new_species.len().saturating_sub(elitism_species))
+            .map(|(id, _)| *id)
+            .collect();
+
+        removed_species_ids.iter().for_each(|id| {
+            new_species.remove(id).unwrap();
         });
 
-        stagnated_ids_and_adjusted_fitnesses
-            .iter()
-            .take(usize::max(new_species.len() - elitism_species, 0))
-            .for_each(|(id, _)| {
-                new_species.remove(id).unwrap();
-            });
+        // Nudge the threshold towards the target species count
+        if let Some(target_species) = target_species {
+            if new_species.len() < target_species {
+                self.compatibility_threshold = Some(f64::max(
+                    compatibility_threshold - compatibility_threshold_step,
+                    compatibility_threshold_step,
+                ));
+            } else if new_species.len() > target_species {
+                self.compatibility_threshold =
+                    Some(compatibility_threshold + compatibility_threshold_step);
+            }
+        }
 
         // Finally replace old species
         self.species = new_species;
+
+        removed_species_ids
+    }
+}
+
+/// A snapshot of a species meant for reporting
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeciesStat {
+    pub id: usize,
+    pub member_count: usize,
+    pub mean_fitness: Option<f64>,
+    pub adjusted_fitness: Option<f64>,
+    pub generations_since_improvement: usize,
+}
+
+/// Normalizes the shared species fitnesses against their total so they can be used as offspring
+/// ratios, fitnesses are shifted by the minimum so negative values still work
+fn share_fitness(species_fitnesses: &[f64], min_fitness: f64) -> Vec<f64> {
+    let shifted: Vec<f64> = species_fitnesses
+        .iter()
+        .map(|fitness| fitness - min_fitness)
+        .collect();
+    let total: f64 = shifted.iter().sum();
+
+    if total > 0. {
+        shifted.iter().map(|fitness| fitness / total).collect()
+    } else {
+        vec![1. / species_fitnesses.len() as f64; species_fitnesses.len()]
+    }
+}
+
+/// Splits `total` seats proportionally to the shares with the largest remainder method so the
+/// counts always add up to `total`, every count is then raised to its minimum with seats taken
+/// from the largest counts
+pub fn apportion(shares: &[f64], minimums: &[usize], total: usize) -> Vec<usize> {
+    if shares.is_empty() {
+        return vec![];
     }
+
+    let share_sum: f64 = shares.iter().sum();
+    let quotas: Vec<f64> = if share_sum > 0. && share_sum.is_finite() {
+        shares
+            .iter()
+            .map(|share| share / share_sum * total as f64)
+            .collect()
+    } else {
+        vec![total as f64 / shares.len() as f64; shares.len()]
+    };
+
+    let mut counts: Vec<usize> = quotas.iter().map(|quota| quota.floor() as usize).collect();
+    let remaining = total.saturating_sub(counts.iter().sum());
+
+    let mut by_remainder: Vec<usize> = (0..quotas.len()).collect();
+    by_remainder.sort_by(|a, b| {
+        let remainder = |i: usize| quotas[i] - quotas[i].floor();
+        remainder(*b).total_cmp(&remainder(*a)).then(a.cmp(b))
+    });
+    by_remainder
+        .iter()
+        .cycle()
+        .take(remaining)
+        .for_each(|i| counts[*i] += 1);
+
+    (0..counts.len()).for_each(|i| {
+        while counts[i] < minimums[i] {
+            let largest = (0..counts.len())
+                .filter(|j| counts[*j] > minimums[*j])
+                .max_by_key(|j| counts[*j]);
+
+            match largest {
+                Some(j) => counts[j] -= 1,
+                None => break,
+            }
+            counts[i] += 1;
+        }
+    });
+
+    counts
 }
 
 #[derive(Debug, Clone)]
@@ -232,6 +402,15 @@ impl Species {
             fitness_history: vec![],
         }
     }
+
+    /// The genome new members are compared against, it can be from an earlier generation
+    pub fn representative_id(&self) -> GenomeId {
+        self.representative
+    }
+
+    pub fn size(&self) -> usize {
+        self.members.len()
+    }
 }
 
 #[cfg(test)]
@@ -264,4 +443,126 @@ mod tests {
 
         assert_eq!(first_hash, second_hash);
     }
+
+    #[test]
+    fn share_fitness_is_proportional() {
+        let adjusted = share_fitness(&[1., 3.], 0.);
+
+        assert!((adjusted[0] - 0.25).abs() < f64::EPSILON);
+        assert!((adjusted[1] - 0.75).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn share_fitness_handles_negative_and_equal_fitness() {
+        let adjusted = share_fitness(&[-3., -1.], -4.);
+
+        assert!((adjusted[0] - 0.25).abs() < f64::EPSILON);
+        assert!((adjusted[1] - 0.75).abs() < f64::EPSILON);
+
+        let adjusted = share_fitness(&[2., 2., 2.], 2.);
+
+        assert!(adjusted.iter().all(|a| (a - 1. / 3.).abs() < f64::EPSILON));
+    }
+
+    #[test]
+    fn apportion_adds_up_to_the_total() {
+        let counts = apportion(&[0.2, 0.2, 0.6], &[0, 0, 0], 11);
+
+        assert_eq!(counts, vec![2, 2, 7]);
+
+        let counts = apportion(&[1., 1., 1.], &[0, 0, 0], 10);
+
+        assert_eq!(counts.iter().sum::<usize>(), 10);
+        assert_eq!(counts, vec![4, 3, 3]);
+    }
+
+    #[test]
+    fn apportion_respects_minimums() {
+        let counts = apportion(&[0.01, 0.99], &[1, 0], 10);
+
+        assert_eq!(counts, vec![1, 9]);
+
+        let counts = apportion(&[0., 0.], &[0, 0], 5);
+
+        assert_eq!(counts.iter().sum::<usize>(), 5);
+    }
+
+    #[test]
+    fn compatibility_threshold_moves_towards_target_species() {
+        let speciate_with = |compatibility_threshold: f64, target_species: usize| {
+            let configuration = Rc::new(RefCell::new(Configuration {
+                compatibility_threshold,
+                target_species: Some(target_species),
+                compatibility_threshold_step: 0.5,
+                ..Default::default()
+            }));
+            let mut species_set = SpeciesSet::new(configuration);
+
+            let genomes: HashMap<GenomeId, Genome> = (0..10)
+                .map(|_| Genome::new(2, 1))
+                .map(|genome| (genome.id(), genome))
+                .collect();
+            let genome_ids: Vec<GenomeId> = genomes.keys().cloned().collect();
+            let fitnesses: BTreeMap<GenomeId, f64> =
+                genome_ids.iter().map(|id| (*id, 1.)).collect();
+
+            species_set.speciate(1, &genome_ids, &genomes, &fitnesses);
+
+            (
+                species_set.species().len(),
+                species_set.compatibility_threshold(),
+            )
+        };
+
+        // Every genome is its own species, so the threshold grows
+        let (species_count, threshold) = speciate_with(0.001, 1);
+        assert_eq!(species_count, 10);
+        assert!((threshold - 0.501).abs() < f64::EPSILON);
+
+        // Less species than wanted, so the threshold shrinks
+        let (species_count, threshold) = speciate_with(100., 20);
+        assert_eq!(species_count, 1);
+        assert!((threshold - 99.5).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn distances_are_reused_between_speciations() {
+        use std::cell::Cell;
+
+        struct Counting(GenomicDistance, Rc<Cell<usize>>);
+
+        impl DistanceMetric for Counting {
+            fn distance(&self, a: &Genome, b: &Genome) -> f64 {
+                self.1.set(self.1.get() + 1);
+                self.0.distance(a, b)
+            }
+        }
+
+        let configuration = Configuration {
+            compatibility_threshold: 0.001,
+            ..Default::default()
+        };
+        let calls = Rc::new(Cell::new(0));
+        let metric = Counting(GenomicDistance::from(&configuration), calls.clone());
+        let mut species_set = SpeciesSet::new(Rc::new(RefCell::new(configuration)));
+        species_set.set_distance_metric(Box::new(metric));
+
+        let genomes: HashMap<GenomeId, Genome> = (0..10)
+            .map(|_| Genome::new(2, 1))
+            .map(|genome| (genome.id(), genome))
+            .collect();
+        let genome_ids: Vec<GenomeId> = genomes.keys().cloned().collect();
+        let fitnesses: BTreeMap<GenomeId, f64> = genome_ids.iter().map(|id| (*id, 1.)).collect();
+
+        species_set.speciate(1, &genome_ids, &genomes, &fitnesses);
+        species_set.speciate(2, &genome_ids, &genomes, &fitnesses);
+        let calls_after_two = calls.get();
+
+        species_set.speciate(3, &genome_ids, &genomes, &fitnesses);
+        assert_eq!(calls.get(), calls_after_two);
+
+        // Distances to genomes that are gone are forgotten
+        species_set.speciate(4, &genome_ids[..4], &genomes, &fitnesses);
+        assert!(species_set.distances.len() <= 4 * 5 / 2);
+    }
 }
@@ -1,14 +1,253 @@
+use neat_core::Network;
+
 pub trait Environment {
     type State;
     type Input;
 
-    fn state(&self) -> Self::State;
+    /// Borrows the current state so large states are not copied on every step
+    fn state(&self) -> &Self::State;
+    /// Number of values the state is turned into when fed to a network
+    fn observation_size(&self) -> usize;
+    /// Number of network outputs needed to pick an input
+    fn action_size(&self) -> usize;
+    /// Range continuous actions should be clamped to, `None` when the actions are discrete
+    fn action_bounds(&self) -> Option<(f64, f64)> {
+        None
+    }
+
     fn step(&mut self, input: Self::Input) -> Result<(), ()>;
 
     fn done(&self) -> bool;
     fn reset(&mut self);
 
+    /// Resets into a starting state derived from the seed, environments without randomness can
+    /// rely on the default which ignores the seed
+    #[allow(unused_variables)]
+    fn reset_with_seed(&mut self, seed: u64) {
+        self.reset();
+    }
+
     fn render(&self);
 
     fn fitness(&self) -> f64;
 }
+
+/// Environment whose input is one of `action_size` choices, some of which may be invalid in the
+/// current state
+pub trait DiscreteActionEnv: Environment<Input = usize> {
+    fn is_valid_action(&self, action: usize) -> bool;
+
+    /// Orders all actions from the highest output to the lowest, NaN outputs go last
+    fn ranked_actions(&self, outputs: &[f64]) -> Vec<usize> {
+        let score = |action: &usize| {
+            let output = outputs[*action];
+
+            if output.is_nan() {
+                f64::NEG_INFINITY
+            } else {
+                output
+            }
+        };
+
+        let mut actions: Vec<usize> = (0..usize::min(outputs.len(), self.action_size())).collect();
+        // The sort is stable so ties go to the lowest action
+        actions.sort_by(|a, b| score(b).total_cmp(&score(a)));
+
+        actions
+    }
+
+    /// Picks the valid action with the highest output, lower outputs are tried when the best
+    /// action is invalid
+    fn action_from_outputs(&self, outputs: &[f64]) -> Option<usize> {
+        self.ranked_actions(outputs)
+            .into_iter()
+            .find(|action| self.is_valid_action(*action))
+    }
+
+    /// Steps with the best valid action for the outputs, fails if no action is valid
+    #[allow(clippy::result_unit_err)]
+    fn step_with_outputs(&mut self, outputs: &[f64]) -> Result<(), ()> {
+        let action = self.action_from_outputs(outputs).ok_or(())?;
+
+        self.step(action)
+    }
+}
+
+/// Resets the environment and lets the network act in it until it is done, a step fails or
+/// `max_steps` steps were taken, returns the fitness of the episode
+pub fn evaluate<E: Environment>(
+    network: &mut Network,
+    env: &mut E,
+    max_steps: usize,
+    to_inputs: fn(&E::State) -> Vec<f64>,
+    to_action: fn(&[f64]) -> E::Input,
+) -> f64 {
+    env.reset();
+
+    for _ in 0..max_steps {
+        if env.done() {
+            break;
+        }
+
+        let inputs = to_inputs(env.state());
+        let outputs = network.forward_pass(inputs);
+
+        if env.step(to_action(&outputs)).is_err() {
+            break;
+        }
+    }
+
+    env.fitness()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neat_core::Genome;
+
+    struct Counter {
+        steps: usize,
+        limit: usize,
+        fail_at: Option<usize>,
+    }
+
+    impl Environment for Counter {
+        type State = usize;
+        type Input = f64;
+
+        fn state(&self) -> &Self::State {
+            &self.steps
+        }
+
+        fn observation_size(&self) -> usize {
+            1
+        }
+
+        fn action_size(&self) -> usize {
+            1
+        }
+
+        fn step(&mut self, _input: Self::Input) -> Result<(), ()> {
+            if Some(self.steps) == self.fail_at {
+                return Err(());
+            }
+
+            self.steps += 1;
+            Ok(())
+        }
+
+        fn done(&self) -> bool {
+            self.steps >= self.limit
+        }
+
+        fn reset(&mut self) {
+            self.steps = 0;
+        }
+
+        fn render(&self) {}
+
+        fn fitness(&self) -> f64 {
+            self.steps as f64
+        }
+    }
+
+    /// Odd actions are invalid and even actions add to the state
+    struct Picker {
+        picked: usize,
+    }
+
+    impl Environment for Picker {
+        type State = usize;
+        type Input = usize;
+
+        fn state(&self) -> &Self::State {
+            &self.picked
+        }
+
+        fn observation_size(&self) -> usize {
+            1
+        }
+
+        fn action_size(&self) -> usize {
+            4
+        }
+
+        fn step(&mut self, input: Self::Input) -> Result<(), ()> {
+            if !self.is_valid_action(input) {
+                return Err(());
+            }
+
+            self.picked = input;
+            Ok(())
+        }
+
+        fn done(&self) -> bool {
+            false
+        }
+
+        fn reset(&mut self) {
+            self.picked = 0;
+        }
+
+        fn render(&self) {}
+
+        fn fitness(&self) -> f64 {
+            self.picked as f64
+        }
+    }
+
+    impl DiscreteActionEnv for Picker {
+        fn is_valid_action(&self, action: usize) -> bool {
+            action.is_multiple_of(2)
+        }
+    }
+
+    fn to_inputs(state: &usize) -> Vec<f64> {
+        vec![*state as f64]
+    }
+
+    fn to_action(outputs: &[f64]) -> f64 {
+        outputs[0]
+    }
+
+    #[test]
+    fn evaluate_stops_at_termination() {
+        let mut network = Network::from(&Genome::new(1, 1));
+        let mut env = Counter {
+            steps: 7,
+            limit: 5,
+            fail_at: None,
+        };
+
+        assert_eq!(
+            evaluate(&mut network, &mut env, 100, to_inputs, to_action),
+            5.
+        );
+        assert_eq!(
+            evaluate(&mut network, &mut env, 3, to_inputs, to_action),
+            3.
+        );
+
+        env.fail_at = Some(2);
+        assert_eq!(
+            evaluate(&mut network, &mut env, 100, to_inputs, to_action),
+            2.
+        );
+    }
+
+    #[test]
+    fn discrete_actions_fall_back_to_valid_ones() {
+        let mut env = Picker { picked: 0 };
+
+        assert_eq!(env.action_from_outputs(&[0., 0.9, 0.5, 0.1]), Some(2));
+        assert_eq!(env.action_from_outputs(&[0.3, 0.9, 0.3, 0.1]), Some(0));
+        assert_eq!(env.action_from_outputs(&[0., 0.9, f64::NAN, -1.]), Some(0));
+        assert_eq!(env.action_from_outputs(&[0.2, 0.9, 0.5, 0.1, 7.]), Some(2));
+        assert_eq!(env.action_from_outputs(&[0.2]), Some(0));
+        assert_eq!(env.action_from_outputs(&[]), None);
+
+        env.step_with_outputs(&[0., 0.9, 0.5, 0.1]).unwrap();
+        assert_eq!(*env.state(), 2);
+        assert!(env.step_with_outputs(&[]).is_err());
+    }
+}
@@ -1,4 +1,6 @@
-use rand::{random, thread_rng, Rng};
+use rand::rngs::StdRng;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
 
 pub use neat_environment::Environment;
 use utils::*;
@@ -10,10 +12,16 @@ pub struct CartPoleConfiguration {
     pub mass_cart: f64,
     pub mass_pole: f64,
     pub length_pole: f64,
+    pub mass_pole2: f64,
+    pub length_pole2: f64,
     pub time_step: f64,
 
     pub limit_position: f64,
     pub limit_angle_radians: f64,
+
+    /// Adds zero mean normal noise to the actuator input so evolved controllers have to be robust,
+    /// the noise comes from the generator the episode started with
+    pub noisy: bool,
 }
 
 impl Default for CartPoleConfiguration {
@@ -23,14 +31,21 @@ impl Default for CartPoleConfiguration {
             mass_cart: 1.0,
             mass_pole: 0.1,
             length_pole: 0.5,
+            mass_pole2: 0.01,
+            length_pole2: 0.05,
             time_step: 1. / 60.,
 
             limit_position: 2.4,
             limit_angle_radians: to_radians(45.),
+
+            noisy: false,
         }
     }
 }
 
+/// Standard deviation of the noise added to the actuator input in noisy mode
+const ACTUATOR_NOISE: f64 = 0.25;
+
 pub struct CartPole {
     pub configuration: CartPoleConfiguration,
 
@@ -41,23 +56,39 @@ pub struct CartPole {
     t: f64,
     xacc: f64,
     tacc: f64,
+    theta2: f64,
+    dtheta2: f64,
+    tacc2: f64,
     fitness: f64,
+    state: Vec<f64>,
+    rng: StdRng,
 
+    double: bool,
     finished: bool,
 }
 
 impl CartPole {
     pub fn new() -> Self {
+        CartPole::new_single()
+    }
+
+    pub fn new_single() -> Self {
+        CartPole::new_single_with_rng(&mut thread_rng())
+    }
+
+    pub fn new_single_with_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
         let configuration: CartPoleConfiguration = Default::default();
-        let mut rng = thread_rng();
 
-        let x =
-            rng.gen_range(-0.5 * configuration.limit_position..0.5 * configuration.limit_position);
+        let x = rng.gen_range(
+            -0.5 * configuration.limit_position,
+            0.5 * configuration.limit_position,
+        );
         let theta = rng.gen_range(
-            -0.5 * configuration.limit_angle_radians..0.5 * configuration.limit_angle_radians,
+            -0.5 * configuration.limit_angle_radians,
+            0.5 * configuration.limit_angle_radians,
         );
-        let dx = rng.gen_range(-1f64..1f64);
-        let dtheta = rng.gen_range(-1f64..1f64);
+        let dx = rng.gen_range(-1f64, 1f64);
+        let dtheta = rng.gen_range(-1f64, 1f64);
 
         CartPole {
             configuration,
@@ -69,25 +100,79 @@ impl CartPole {
             t: 0.,
             xacc: 0.,
             tacc: 0.,
+            theta2: 0.,
+            dtheta2: 0.,
+            tacc2: 0.,
             fitness: 0.,
+            state: vec![x, dx, theta, dtheta],
+            rng: StdRng::seed_from_u64(rng.gen()),
 
+            double: false,
             finished: false,
         }
     }
 
+    /// Creates the double pole variant, a second shorter pole is hinged on the same cart and both
+    /// have to stay balanced
+    pub fn new_double() -> Self {
+        CartPole::new_double_with_rng(&mut thread_rng())
+    }
+
+    pub fn new_double_with_rng<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        let mut env = CartPole::new_single_with_rng(rng);
+
+        env.theta2 = rng.gen_range(
+            -0.5 * env.configuration.limit_angle_radians,
+            0.5 * env.configuration.limit_angle_radians,
+        );
+        env.dtheta2 = rng.gen_range(-1f64, 1f64);
+        env.double = true;
+        env.update_state();
+
+        env
+    }
+
+    pub fn is_double(&self) -> bool {
+        self.double
+    }
+
+    /// State of the double pole variant as `[x, dx, theta, dtheta, theta2, dtheta2]`
+    pub fn double_pole_state(&self) -> [f64; 6] {
+        [
+            self.x,
+            self.dx,
+            self.theta,
+            self.dtheta,
+            self.theta2,
+            self.dtheta2,
+        ]
+    }
+
     fn continuous_actuator_force(input: f64) -> f64 {
         input * 10.
     }
 
-    fn continuous_noisy_actuator_force(input: f64) -> f64 {
-        (input + random::<f64>() * 0.75) * 10.
+    fn continuous_noisy_actuator_force(&mut self, input: f64) -> f64 {
+        let noise = Normal::new(0., ACTUATOR_NOISE)
+            .unwrap()
+            .sample(&mut self.rng);
+
+        CartPole::continuous_actuator_force(input + noise)
+    }
+
+    fn max_theta(&self) -> f64 {
+        if self.double {
+            f64::max(self.theta.abs(), self.theta2.abs())
+        } else {
+            self.theta.abs()
+        }
     }
 
     fn measure_fitness(&mut self) {
         let x_component = f64::max(0., self.configuration.limit_position - self.x.abs());
         let theta_component = f64::max(
             0.,
-            self.configuration.limit_angle_radians - self.theta.abs(),
+            self.configuration.limit_angle_radians - self.max_theta(),
         );
 
         let step_fitness = 1. - x_component * theta_component;
@@ -97,7 +182,7 @@ impl CartPole {
 
     fn check_finished(&mut self) {
         if self.x.abs() > self.configuration.limit_position
-            || self.theta.abs() > self.configuration.limit_angle_radians
+            || self.max_theta() > self.configuration.limit_angle_radians
         {
             self.finished = true;
         }
@@ -105,26 +190,20 @@ impl CartPole {
 
     pub fn apply_force_to_pole(&mut self, force: f64) {
         self.dtheta += force;
+        self.update_state();
     }
-}
-
-impl Environment for CartPole {
-    type State = [f64; 4];
-    type Input = f64;
 
-    fn state(&self) -> Self::State {
-        [self.x, self.dx, self.theta, self.dtheta]
-    }
-
-    fn step(&mut self, input: Self::Input) -> Result<(), ()> {
-        if input > 1. || input < -1. {
-            panic!("Input must be between 1 and -1");
-        }
-        if self.done() {
-            return Err(());
+    /// Copies the fields into the state `state` borrows, every change to them has to end with it
+    fn update_state(&mut self) {
+        self.state.clear();
+        self.state
+            .extend_from_slice(&[self.x, self.dx, self.theta, self.dtheta]);
+        if self.double {
+            self.state.extend_from_slice(&[self.theta2, self.dtheta2]);
         }
+    }
 
-        let force = CartPole::continuous_actuator_force(input);
+    fn step_single_pole(&mut self, force: f64) {
         let xacc_current = self.xacc;
         let tacc_current = self.tacc;
         let mass_all = self.configuration.mass_pole + self.configuration.mass_cart;
@@ -155,8 +234,184 @@ impl Environment for CartPole {
 
         self.dx += 0.5 * (xacc_current + self.xacc) * self.configuration.time_step;
         self.dtheta += 0.5 * (tacc_current + self.tacc) * self.configuration.time_step;
+    }
+
+    /// Integrates the coupled equations of motion for two independent poles hinged on one cart,
+    /// pole lengths are half lengths like in the single pole variant
+    fn step_double_pole(&mut self, force: f64) {
+        let gravity = self.configuration.gravity;
+        let time_step = self.configuration.time_step;
+        let xacc_current = self.xacc;
+        let tacc_current = self.tacc;
+        let tacc2_current = self.tacc2;
+
+        self.x += time_step * self.dx + 0.5 * xacc_current * time_step.powi(2);
+        self.theta += time_step * self.dtheta + 0.5 * tacc_current * time_step.powi(2);
+        self.theta2 += time_step * self.dtheta2 + 0.5 * tacc2_current * time_step.powi(2);
+
+        let poles = [
+            (
+                self.configuration.mass_pole,
+                self.configuration.length_pole,
+                self.theta,
+                self.dtheta,
+            ),
+            (
+                self.configuration.mass_pole2,
+                self.configuration.length_pole2,
+                self.theta2,
+                self.dtheta2,
+            ),
+        ];
+
+        // Effective force and mass each pole contributes to the cart
+        let (effective_force, effective_mass) = poles.iter().fold(
+            (0., 0.),
+            |(effective_force, effective_mass), (mass, length, theta, dtheta)| {
+                let theta_sin = theta.sin();
+                let theta_cos = theta.cos();
+
+                (
+                    effective_force + mass * length * dtheta.powi(2) * theta_sin
+                        - 0.75 * mass * gravity * theta_cos * theta_sin,
+                    effective_mass + mass * (1. - 0.75 * theta_cos.powi(2)),
+                )
+            },
+        );
+
+        self.xacc = (force + effective_force) / (self.configuration.mass_cart + effective_mass);
+
+        let xacc = self.xacc;
+        let pole_acceleration =
+            |length: f64, theta: f64| 0.75 * (gravity * theta.sin() - xacc * theta.cos()) / length;
+        self.tacc = pole_acceleration(self.configuration.length_pole, self.theta);
+        self.tacc2 = pole_acceleration(self.configuration.length_pole2, self.theta2);
+
+        self.dx += 0.5 * (xacc_current + self.xacc) * time_step;
+        self.dtheta += 0.5 * (tacc_current + self.tacc) * time_step;
+        self.dtheta2 += 0.5 * (tacc2_current + self.tacc2) * time_step;
+    }
+
+    /// Draws the current state into an RGB buffer of `width * height * 3` bytes, row by row from
+    /// the top, with the same geometry the example GUI uses so frames can be encoded into videos
+    pub fn render_to_buffer(&self, width: usize, height: usize) -> Vec<u8> {
+        // The track is in the middle of the frame and one unit of length spans 100 pixels
+        let scale = 100.;
+        let cart_x = self.x * scale;
+        let (cart_width, cart_height) = (20., 10.);
+        let pole_height = self.configuration.length_pole * scale * 2.;
+        let (theta_sin, theta_cos) = self.theta.sin_cos();
+        let pole_center = (
+            cart_x + theta_sin * pole_height / 2.,
+            theta_cos * pole_height / 2.,
+        );
+
+        let is_cart = |x: f64, y: f64| {
+            (x - cart_x).abs() <= cart_width / 2.
+                && (y + cart_height / 2.).abs() <= cart_height / 2.
+        };
+        let is_pole = |x: f64, y: f64| {
+            let (dx, dy) = (x - pole_center.0, y - pole_center.1);
+            let along = dx * theta_sin + dy * theta_cos;
+            let across = dx * theta_cos - dy * theta_sin;
+
+            along.abs() <= pole_height / 2. && across.abs() <= 0.5
+        };
+
+        let mut buffer = Vec::with_capacity(width * height * 3);
+
+        for row in 0..height {
+            for column in 0..width {
+                let x = column as f64 + 0.5 - width as f64 / 2.;
+                let y = height as f64 / 2. - row as f64 - 0.5;
+                let value = if is_cart(x, y) || is_pole(x, y) {
+                    255
+                } else {
+                    0
+                };
+
+                buffer.extend_from_slice(&[value; 3]);
+            }
+        }
+
+        buffer
+    }
+
+    fn render_to_string(&self) -> String {
+        let track_width = 41;
+        let limit_position = self.configuration.limit_position;
+
+        let relative_position = ((self.x / limit_position + 1.) / 2.).clamp(0., 1.);
+        let cart_index = (relative_position * (track_width - 1) as f64).round() as usize;
+
+        let pole = if self.theta > to_radians(5.) {
+            '/'
+        } else if self.theta < -to_radians(5.) {
+            '\\'
+        } else {
+            '|'
+        };
+
+        let pole_line: String = (0..track_width)
+            .map(|i| if i == cart_index { pole } else { ' ' })
+            .collect();
+        let track_line: String = (0..track_width)
+            .map(|i| if i == cart_index { '#' } else { '=' })
+            .collect();
+
+        format!(
+            "{}\n{}\nx: {:.3}, theta: {:.3}\n",
+            pole_line.trim_end(),
+            track_line,
+            self.x,
+            self.theta
+        )
+    }
+}
+
+impl Environment for CartPole {
+    /// `[x, dx, theta, dtheta]` followed by `theta2, dtheta2` in the double pole variant
+    type State = Vec<f64>;
+    type Input = f64;
+
+    fn state(&self) -> &Self::State {
+        &self.state
+    }
+
+    fn observation_size(&self) -> usize {
+        self.state.len()
+    }
+
+    fn action_size(&self) -> usize {
+        1
+    }
+
+    fn action_bounds(&self) -> Option<(f64, f64)> {
+        Some((-1., 1.))
+    }
+
+    fn step(&mut self, input: Self::Input) -> Result<(), ()> {
+        if input > 1. || input < -1. {
+            panic!("Input must be between 1 and -1");
+        }
+        if self.done() {
+            return Err(());
+        }
+
+        let force = if self.configuration.noisy {
+            self.continuous_noisy_actuator_force(input)
+        } else {
+            CartPole::continuous_actuator_force(input)
+        };
+
+        if self.double {
+            self.step_double_pole(force);
+        } else {
+            self.step_single_pole(force);
+        }
 
         self.t += self.configuration.time_step;
+        self.update_state();
 
         self.measure_fitness();
         self.check_finished();
@@ -173,11 +428,30 @@ impl Environment for CartPole {
     }
 
     fn reset(&mut self) {
-        *self = CartPole::new();
+        let configuration = std::mem::take(&mut self.configuration);
+
+        *self = if self.double {
+            CartPole::new_double()
+        } else {
+            CartPole::new_single()
+        };
+        self.configuration = configuration;
+    }
+
+    fn reset_with_seed(&mut self, seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let configuration = std::mem::take(&mut self.configuration);
+
+        *self = if self.double {
+            CartPole::new_double_with_rng(&mut rng)
+        } else {
+            CartPole::new_single_with_rng(&mut rng)
+        };
+        self.configuration = configuration;
     }
 
     fn render(&self) {
-        unimplemented!();
+        println!("{}", self.render_to_string());
     }
 }
 
@@ -200,4 +474,177 @@ mod tests {
 
         dbg!(fitness);
     }
+
+    #[test]
+    fn render_places_cart_on_track() {
+        let mut env = CartPole::new();
+
+        env.x = 0.;
+        env.theta = 0.;
+        let rendered = env.render_to_string();
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines[0].trim(), "|");
+        assert_eq!(lines[1].find('#'), Some(20));
+
+        for (x, theta) in &[(-10., -10.), (10., 10.), (2.4, 0.5), (-2.4, -0.5)] {
+            env.x = *x;
+            env.theta = *theta;
+
+            env.render();
+        }
+    }
+
+    #[test]
+    fn render_to_buffer_draws_cart_and_pole() {
+        let mut env = CartPole::new();
+
+        env.x = 0.;
+        env.theta = 0.;
+        let (width, height) = (480, 320);
+        let buffer = env.render_to_buffer(width, height);
+        let pixel = |column: usize, row: usize| {
+            let offset = (row * width + column) * 3;
+            &buffer[offset..offset + 3]
+        };
+
+        assert_eq!(buffer.len(), width * height * 3);
+        // Cart just below the middle, pole straight above it and an empty corner
+        assert_eq!(pixel(240, 165), [255; 3]);
+        assert_eq!(pixel(240, 100), [255; 3]);
+        assert_eq!(pixel(260, 100), [0; 3]);
+        assert_eq!(pixel(0, 0), [0; 3]);
+
+        // Leaning to the right moves the top of the pole to the right
+        env.theta = to_radians(30.);
+        let buffer = env.render_to_buffer(width, height);
+        let lit_in_row = |row: usize| {
+            (0..width)
+                .filter(|column| buffer[(row * width + column) * 3] == 255)
+                .collect::<Vec<_>>()
+        };
+
+        assert!(lit_in_row(100).iter().all(|column| *column > 260));
+        assert!(!lit_in_row(100).is_empty());
+    }
+
+    #[test]
+    fn double_pole_matches_single_pole_with_massless_second_pole() {
+        let mut single = CartPole::new_single();
+        let mut double = CartPole::new_double();
+
+        // Only the first pole is compared, the short one is allowed to fall freely
+        single.configuration.limit_angle_radians = 100.;
+        double.configuration.limit_angle_radians = 100.;
+        double.configuration.mass_pole2 = 0.;
+        double.x = single.x;
+        double.dx = single.dx;
+        double.theta = single.theta;
+        double.dtheta = single.dtheta;
+
+        for _ in 0..10 {
+            single.step(0.5).unwrap();
+            double.step(0.5).unwrap();
+        }
+
+        let single_state = single.state();
+        let double_state = double.double_pole_state();
+
+        for i in 0..4 {
+            assert!((single_state[i] - double_state[i]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn double_pole_falls_over() {
+        let mut env = CartPole::new_double();
+
+        env.x = 0.;
+        env.dx = 0.;
+        env.theta = 0.;
+        env.dtheta = 0.;
+        env.theta2 = 0.1;
+        env.dtheta2 = 0.;
+
+        while env.step(0.).is_ok() {}
+
+        assert!(env.done());
+        assert!(env.double_pole_state()[4] > env.configuration.limit_angle_radians);
+    }
+
+    #[test]
+    fn reset_with_seed_is_reproducible() {
+        let mut first = CartPole::new_double();
+        let mut second = CartPole::new_double();
+
+        first.reset_with_seed(42);
+        second.reset_with_seed(42);
+        assert_eq!(first.double_pole_state(), second.double_pole_state());
+
+        second.reset_with_seed(43);
+        assert_ne!(first.double_pole_state(), second.double_pole_state());
+        assert!(second.is_double());
+    }
+
+    #[test]
+    fn double_pole_state_includes_the_second_pole() {
+        let mut env = CartPole::new_double();
+
+        assert_eq!(env.state().len(), 6);
+        assert_eq!(env.observation_size(), 6);
+        assert_eq!(env.state()[..], env.double_pole_state()[..]);
+
+        env.step(0.5).unwrap();
+        env.reset_with_seed(5);
+        assert_eq!(env.state()[..], env.double_pole_state()[..]);
+
+        assert_eq!(CartPole::new_single().observation_size(), 4);
+    }
+
+    #[test]
+    fn pushing_the_pole_updates_the_state() {
+        let mut env = CartPole::new_single();
+        let dtheta = env.state()[3];
+
+        env.apply_force_to_pole(0.5);
+
+        assert_eq!(env.state()[3], dtheta + 0.5);
+    }
+
+    #[test]
+    fn noisy_episodes_are_reproducible() {
+        let mut first = CartPole::new_single();
+        let mut second = CartPole::new_single();
+
+        first.configuration.noisy = true;
+        second.configuration.noisy = true;
+        first.reset_with_seed(7);
+        second.reset_with_seed(7);
+
+        for _ in 0..5 {
+            first.step(0.).unwrap();
+            second.step(0.).unwrap();
+        }
+
+        assert_eq!(first.state(), second.state());
+    }
+
+    #[test]
+    fn noise_perturbs_the_trajectory() {
+        let mut exact = CartPole::new_single();
+        let mut noisy = CartPole::new_single();
+
+        noisy.configuration.noisy = true;
+        noisy.reset_with_seed(7);
+        exact.reset_with_seed(7);
+        assert!(noisy.configuration.noisy);
+        assert_eq!(exact.state(), noisy.state());
+
+        for _ in 0..5 {
+            exact.step(0.).unwrap();
+            noisy.step(0.).unwrap();
+        }
+
+        assert_ne!(exact.state(), noisy.state());
+    }
 }
@@ -0,0 +1,333 @@
+use rand::random;
+
+pub use neat_environment::{DiscreteActionEnv, Environment};
+
+#[derive(Clone, Copy, Debug)]
+pub enum Mark {
+    X,
+    O,
+    Empty,
+}
+
+#[derive(Clone, Debug)]
+pub enum Player {
+    External,
+    Internal,
+}
+
+pub type Field = [Mark; 9];
+
+#[derive(Debug)]
+pub struct TicTacToe {
+    field: Field,
+    first_player: Player,
+    turn: Player,
+    illegal_moves: usize,
+
+    /// Fitness subtracted for every attempt to mark an occupied cell
+    pub illegal_move_weight: f64,
+}
+
+impl Default for TicTacToe {
+    fn default() -> Self {
+        TicTacToe::new()
+    }
+}
+
+impl TicTacToe {
+    pub fn new() -> Self {
+        let first_player: Player = if random::<f64>() < 0.5 {
+            Player::External
+        } else {
+            Player::Internal
+        };
+
+        let mut ttt = TicTacToe {
+            field: [Mark::Empty; 9],
+            first_player: first_player.clone(),
+            turn: first_player.clone(),
+            illegal_moves: 0,
+            illegal_move_weight: 0.1,
+        };
+
+        if let Player::Internal = first_player {
+            ttt.step_internal();
+        }
+
+        ttt
+    }
+
+    fn step_internal(&mut self) {
+        if self.game_over() || self.is_external_turn() {
+            return;
+        }
+
+        let empty_indexes: Vec<usize> = self
+            .field
+            .iter()
+            .enumerate()
+            .filter(|(_, mark)| matches!(mark, Mark::Empty))
+            .map(|(index, _)| index)
+            .collect();
+
+        let random_index = empty_indexes
+            .get(random::<usize>() % empty_indexes.len())
+            .unwrap();
+
+        let mark_to_place = if matches!(self.first_player, Player::Internal) {
+            Mark::X
+        } else {
+            Mark::O
+        };
+
+        *self.field.get_mut(*random_index).unwrap() = mark_to_place;
+        self.turn = Player::External;
+    }
+
+    pub fn is_external_first(&self) -> bool {
+        matches!(self.first_player, Player::External)
+    }
+
+    pub fn is_external_turn(&self) -> bool {
+        matches!(self.turn, Player::External)
+    }
+
+    pub fn external_mark(&self) -> Mark {
+        match self.first_player {
+            Player::External => Mark::X,
+            Player::Internal => Mark::O,
+        }
+    }
+
+    pub fn game_over(&self) -> bool {
+        let fields_full = self.field.iter().all(|mark| !matches!(mark, Mark::Empty));
+
+        fields_full || self.did_external_win() || self.did_internal_win()
+    }
+
+    pub fn did_external_win(&self) -> bool {
+        self.did_mark_win(self.external_mark())
+    }
+
+    pub fn did_internal_win(&self) -> bool {
+        let internal_mark = {
+            match self.first_player {
+                Player::Internal => Mark::X,
+                Player::External => Mark::O,
+            }
+        };
+
+        self.did_mark_win(internal_mark)
+    }
+
+    /// Number of times an occupied cell was picked since the last reset
+    pub fn illegal_moves(&self) -> usize {
+        self.illegal_moves
+    }
+
+    /// Fitness lost to illegal moves since the last reset
+    pub fn illegal_move_penalty(&self) -> f64 {
+        self.illegal_moves as f64 * self.illegal_move_weight
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.game_over() && !self.did_external_win() && !self.did_internal_win()
+    }
+
+    fn did_mark_win(&self, check_mark: Mark) -> bool {
+        let winning_lines = [
+            [0, 1, 2],
+            [3, 4, 5],
+            [6, 7, 8],
+            [0, 3, 6],
+            [1, 4, 7],
+            [2, 5, 8],
+            [0, 4, 8],
+            [2, 4, 6],
+        ];
+
+        let external_won = winning_lines.iter().any(|line| {
+            line.iter()
+                .map(|mark_index| self.field.get(*mark_index).unwrap())
+                .all(|mark| match (mark, check_mark) {
+                    (Mark::X, Mark::X) => true,
+                    (Mark::O, Mark::O) => true,
+                    _ => false,
+                })
+        });
+
+        external_won
+    }
+}
+
+impl Environment for TicTacToe {
+    type State = Field;
+    type Input = usize;
+
+    fn state(&self) -> &Self::State {
+        &self.field
+    }
+
+    fn observation_size(&self) -> usize {
+        9
+    }
+
+    fn action_size(&self) -> usize {
+        9
+    }
+
+    fn step(&mut self, input: Self::Input) -> Result<(), ()> {
+        if input >= 9 {
+            panic!("Field index out of bounds");
+        }
+
+        if self.game_over() || !self.is_external_turn() {
+            return Err(());
+        }
+
+        let mark_to_place = if matches!(self.first_player, Player::External) {
+            Mark::X
+        } else {
+            Mark::O
+        };
+        let mark = self.field.get_mut(input).unwrap();
+
+        if matches!(mark, Mark::Empty) {
+            *mark = mark_to_place;
+        } else {
+            self.illegal_moves += 1;
+            return Err(());
+        }
+
+        self.turn = Player::Internal;
+        self.step_internal();
+
+        Ok(())
+    }
+
+    fn done(&self) -> bool {
+        self.game_over()
+    }
+
+    fn reset(&mut self) {
+        let illegal_move_weight = self.illegal_move_weight;
+
+        *self = TicTacToe::new();
+        self.illegal_move_weight = illegal_move_weight;
+    }
+
+    fn render(&self) {
+        self.field.iter().enumerate().for_each(|(index, mark)| {
+            let character: String = match mark {
+                Mark::X => "X".to_owned(),
+                Mark::O => "O".to_owned(),
+                Mark::Empty => "_".to_owned(),
+            };
+
+            if index % 3 == 0 {
+                print!("\n");
+            }
+            print!("{} ", character);
+        });
+
+        print!("\n\n");
+    }
+
+    fn fitness(&self) -> f64 {
+        let outcome = if self.did_external_win() { 1. } else { 0. };
+
+        outcome - self.illegal_move_penalty()
+    }
+}
+
+impl DiscreteActionEnv for TicTacToe {
+    /// Only empty cells can be marked
+    fn is_valid_action(&self, action: usize) -> bool {
+        matches!(self.field.get(action), Some(Mark::Empty))
+    }
+
+    /// Tries the outputs from best to worst so every occupied cell picked before a legal move is
+    /// counted as an illegal move
+    fn step_with_outputs(&mut self, outputs: &[f64]) -> Result<(), ()> {
+        if self.game_over() {
+            return Err(());
+        }
+
+        for action in self.ranked_actions(outputs) {
+            if self.step(action).is_ok() {
+                return Ok(());
+            }
+        }
+
+        Err(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_run() {
+        let mut env = TicTacToe::new();
+
+        if env.is_external_first() {
+            println!("I am X");
+        } else {
+            println!("I am O");
+        }
+
+        loop {
+            if env.game_over() {
+                break;
+            }
+
+            while env.step(random::<usize>() % 9).is_err() {}
+        }
+
+        println!("I WON: {}", env.did_external_win());
+        env.render();
+        env.reset();
+    }
+
+    #[test]
+    fn occupied_cells_fall_back_to_the_next_best_output() {
+        let mut env = TicTacToe::new();
+        env.field = [Mark::Empty; 9];
+        env.field[4] = Mark::O;
+
+        let mut outputs = vec![0.; 9];
+        outputs[4] = 1.;
+        outputs[7] = 0.5;
+
+        assert_eq!(env.action_from_outputs(&outputs), Some(7));
+
+        env.field = [Mark::O; 9];
+        assert_eq!(env.action_from_outputs(&outputs), None);
+    }
+
+    #[test]
+    fn illegal_moves_are_penalized() {
+        let mut env = TicTacToe::new();
+        env.illegal_move_weight = 0.25;
+        env.field = [Mark::Empty; 9];
+        env.field[4] = Mark::O;
+        env.field[7] = Mark::O;
+        env.turn = Player::External;
+
+        let mut outputs = vec![0.; 9];
+        outputs[4] = 1.;
+        outputs[7] = 0.5;
+        outputs[2] = 0.25;
+
+        env.step_with_outputs(&outputs).unwrap();
+
+        assert!(matches!(env.field[2], Mark::X | Mark::O));
+        assert_eq!(env.illegal_moves(), 2);
+        assert!((env.fitness() - if env.did_external_win() { 0.5 } else { -0.5 }).abs() < 1e-9);
+
+        env.reset();
+        assert_eq!(env.illegal_moves(), 0);
+        assert!((env.illegal_move_weight - 0.25).abs() < f64::EPSILON);
+    }
+}
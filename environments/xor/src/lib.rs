@@ -0,0 +1,108 @@
+pub use neat_environment::Environment;
+
+/// The XOR truth table, the inputs of every case and the expected output
+pub const CASES: [([f64; 2], f64); 4] = [
+    ([0., 0.], 0.),
+    ([0., 1.], 1.),
+    ([1., 0.], 1.),
+    ([1., 1.], 0.),
+];
+
+/// Goes through the XOR truth table one case per step, the state is the index of the current
+/// case and the input is the answer to it, which counts as 1 when it's above 0.5
+#[derive(Debug, Default)]
+pub struct Xor {
+    case: usize,
+    correct: usize,
+}
+
+impl Xor {
+    pub fn new() -> Self {
+        Xor::default()
+    }
+
+    /// Turns the state into the inputs of the case for a network
+    pub fn inputs(case: &usize) -> Vec<f64> {
+        CASES[*case].0.to_vec()
+    }
+}
+
+impl Environment for Xor {
+    type State = usize;
+    type Input = f64;
+
+    fn state(&self) -> &usize {
+        &self.case
+    }
+
+    fn observation_size(&self) -> usize {
+        2
+    }
+
+    fn action_size(&self) -> usize {
+        1
+    }
+
+    fn action_bounds(&self) -> Option<(f64, f64)> {
+        Some((0., 1.))
+    }
+
+    fn step(&mut self, answer: f64) -> Result<(), ()> {
+        if self.done() {
+            return Err(());
+        }
+
+        let (_, expected) = CASES[self.case];
+        if (answer > 0.5) == (expected > 0.5) {
+            self.correct += 1;
+        }
+        self.case += 1;
+
+        Ok(())
+    }
+
+    fn done(&self) -> bool {
+        self.case == CASES.len()
+    }
+
+    fn reset(&mut self) {
+        *self = Xor::new();
+    }
+
+    fn render(&self) {
+        println!(
+            "{} of {} cases answered, {} correctly",
+            self.case,
+            CASES.len(),
+            self.correct
+        );
+    }
+
+    /// The share of all cases answered correctly
+    fn fitness(&self) -> f64 {
+        self.correct as f64 / CASES.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fitness_is_the_accuracy() {
+        let mut env = Xor::new();
+
+        for answer in [0., 1., 0., 0.] {
+            assert_eq!(Xor::inputs(env.state()), CASES[*env.state()].0.to_vec());
+            env.step(answer).unwrap();
+        }
+
+        assert!(env.done());
+        assert!(env.step(0.).is_err());
+        assert!((env.fitness() - 0.75).abs() < f64::EPSILON);
+
+        env.reset();
+        assert!(!env.done());
+        assert!(env.fitness().abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,43 @@
+use neat_core::{Configuration, NEAT};
+use neat_environment::evaluate;
+use xor::{Environment, Xor, CASES};
+
+fn main() {
+    let sample_env = Xor::new();
+    let mut system = NEAT::new(
+        sample_env.observation_size(),
+        sample_env.action_size(),
+        |network| {
+            evaluate(
+                network,
+                &mut Xor::new(),
+                CASES.len(),
+                Xor::inputs,
+                |outputs| outputs[0],
+            )
+        },
+    );
+
+    system.set_configuration(
+        Configuration::builder()
+            .population_size(150)
+            .max_generations(300)
+            .fitness_goal(1.)
+            .build()
+            .unwrap(),
+    );
+    system.add_hook(10, |i, system| {
+        let (_, _, fitness) = system.get_best();
+
+        println!("Generation {}, best fitness is {}", i, fitness);
+    });
+
+    let (mut network, fitness) = system.start();
+    println!("Found network with {}, of fitness {}", network, fitness);
+
+    for (inputs, expected) in CASES.iter() {
+        let output = network.forward_pass(inputs.to_vec())[0];
+
+        println!("{:?} -> {:.3}, expected {}", inputs, output, expected);
+    }
+}
@@ -31,8 +31,7 @@ fn model(app: &App) -> Model {
 fn update(_app: &App, model: &mut Model, update: Update) {
     if let Some(ref mut network) = model.network {
         let state = model.env.state();
-        let network_output = network.forward_pass(state.to_vec());
-        let env_input = f64::max(-1., f64::min(1., *network_output.first().unwrap()));
+        let env_input = network.forward_pass_clamped(state.to_vec(), -1., 1.)[0];
 
         if model.env.step(env_input).is_err() {
             model.env.reset();
@@ -42,7 +41,7 @@ fn update(_app: &App, model: &mut Model, update: Update) {
 
 fn view(app: &App, model: &Model, frame: Frame) {
     let CartPoleConfiguration { length_pole, .. } = model.env.configuration;
-    let [x, _, theta, _] = model.env.state();
+    let (x, theta) = (model.env.state()[0], model.env.state()[2]);
 
     let cart_x = 0. + x as f32 * 100.;
     let cart_width = 20.;
@@ -70,7 +69,10 @@ fn view(app: &App, model: &Model, frame: Frame) {
 }
 
 fn dropped_file(_app: &App, model: &mut Model, path: std::path::PathBuf) {
-    model.network = Some(from_file(path));
+    match from_file(&path) {
+        Ok(network) => model.network = Some(network),
+        Err(e) => eprintln!("Could not load network from {}: {}", path.display(), e),
+    }
 }
 
 fn key_released(_app: &App, model: &mut Model, key: Key) {
@@ -1,49 +1,40 @@
-use neat_core::{Configuration, NEAT};
+use neat_core::{clamp_outputs, fitness::average_over, Configuration, Network, NEAT};
+use neat_environment::evaluate;
 use neat_environment_cart_pole::{CartPole, Environment};
 use neat_export::to_file;
 
 mod gui;
 
 fn train() {
-    let mut system = NEAT::new(4, 1, |network| {
-        let num_simulations = 10;
-        let max_steps = 1000;
-        let mut env = CartPole::new();
-
-        let mut steps_done = 0;
-        let mut fitness = 0.;
-
-        for _ in 0..num_simulations {
-            env.reset();
-
-            for _ in 0..max_steps {
-                if env.done() {
-                    break;
-                }
-
-                let state = env.state();
-                let network_output = network.forward_pass(state.to_vec());
-                let env_input = f64::max(-1., f64::min(1., *network_output.first().unwrap()));
-
-                env.step(env_input).unwrap();
-                steps_done += 1;
-            }
-
-            fitness += env.fitness();
-        }
-
-        fitness / num_simulations as f64
-    });
-
-    system.set_configuration(Configuration {
-        population_size: 100,
-        max_generations: 500,
-        stagnation_after: 50,
-        node_cost: 1.,
-        connection_cost: 1.,
-        compatibility_threshold: 2.,
-        ..Default::default()
-    });
+    let sample_env = CartPole::new();
+    let mut system = NEAT::new(
+        sample_env.observation_size(),
+        sample_env.action_size(),
+        average_over(10, |network| {
+            let max_steps = 1000;
+            let mut env = CartPole::new();
+
+            evaluate(
+                network,
+                &mut env,
+                max_steps,
+                |state| state.to_vec(),
+                |outputs| clamp_outputs(outputs, -1., 1.)[0],
+            )
+        }),
+    );
+
+    system.set_configuration(
+        Configuration::builder()
+            .population_size(100)
+            .max_generations(500)
+            .stagnation_after(50)
+            .node_cost(1.)
+            .connection_cost(1.)
+            .compatibility_threshold(2.)
+            .build()
+            .unwrap(),
+    );
 
     system.add_hook(10, |generation, system| {
         println!(
@@ -54,16 +45,15 @@ fn train() {
         );
     });
 
-    let (network, fitness) = system.start();
+    let (_, fitness) = system.start();
+
+    let mut best_genome = system.get_best().1.clone();
+    best_genome.prune();
+    let network = Network::from(&best_genome);
 
-    // println!(
-    //     "Found network with {} nodes and {} connections, fitness is {}",
-    //     network.nodes.len(),
-    //     network.connections.len(),
-    //     fitness
-    // );
+    // println!("Found network with {}, fitness is {}", network, fitness);
 
-    to_file("network.bin", &network);
+    to_file("network.bin", &network).expect("Could not save the network");
 }
 
 fn main() {
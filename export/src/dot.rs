@@ -0,0 +1,160 @@
+use neat_core::{Genome, Network, NodeKind};
+use std::fmt::Write;
+
+struct DotNode<'a> {
+    kind: &'a NodeKind,
+    activation: String,
+}
+
+struct DotEdge {
+    from: usize,
+    to: usize,
+    weight: f64,
+    disabled: bool,
+    recurrent: bool,
+}
+
+/// Renders the network as a GraphViz digraph, inputs are ranked at the source and outputs at the
+/// sink
+pub fn to_dot(network: &Network) -> String {
+    let nodes: Vec<DotNode> = network
+        .nodes
+        .iter()
+        .map(|n| DotNode {
+            kind: &n.kind,
+            activation: format!("{:?}", n.activation),
+        })
+        .collect();
+    let edges: Vec<DotEdge> = network
+        .connections()
+        .iter()
+        .map(|c| DotEdge {
+            from: c.from,
+            to: c.to,
+            weight: c.weight,
+            disabled: false,
+            recurrent: c.recurrent,
+        })
+        .collect();
+
+    render(&nodes, &edges)
+}
+
+/// Renders the genome as a GraphViz digraph, disabled connection genes are drawn dashed and grey
+pub fn genome_to_dot(genome: &Genome) -> String {
+    let nodes: Vec<DotNode> = genome
+        .nodes()
+        .iter()
+        .map(|n| DotNode {
+            kind: &n.kind,
+            activation: format!("{:?}", n.activation),
+        })
+        .collect();
+    let edges: Vec<DotEdge> = genome
+        .connections()
+        .iter()
+        .map(|c| DotEdge {
+            from: c.from,
+            to: c.to,
+            weight: c.weight,
+            disabled: c.disabled,
+            recurrent: c.recurrent,
+        })
+        .collect();
+
+    render(&nodes, &edges)
+}
+
+fn render(nodes: &[DotNode], edges: &[DotEdge]) -> String {
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph {{").unwrap();
+    writeln!(dot, "    rankdir=LR;").unwrap();
+    writeln!(dot, "    node [style=filled];").unwrap();
+
+    nodes.iter().enumerate().for_each(|(i, n)| {
+        let color = match n.kind {
+            NodeKind::Input => "lightblue",
+            NodeKind::Hidden => "lightgrey",
+            NodeKind::Output => "lightgreen",
+            NodeKind::Constant => "khaki",
+        };
+
+        writeln!(
+            dot,
+            "    {} [label=\"{}\\n{}\", fillcolor=\"{}\"];",
+            i, i, n.activation, color
+        )
+        .unwrap();
+    });
+
+    let rank = |kind: NodeKind| {
+        nodes
+            .iter()
+            .enumerate()
+            .filter(|(_, n)| *n.kind == kind)
+            .map(|(i, _)| format!("{};", i))
+            .collect::<Vec<String>>()
+            .join(" ")
+    };
+
+    writeln!(dot, "    {{ rank=source; {} }}", rank(NodeKind::Input)).unwrap();
+    writeln!(dot, "    {{ rank=sink; {} }}", rank(NodeKind::Output)).unwrap();
+
+    edges.iter().for_each(|e| {
+        let mut attributes = vec![format!("label=\"{:.3}\"", e.weight)];
+
+        if e.recurrent {
+            attributes.push("constraint=false".to_owned());
+        }
+        if e.disabled {
+            attributes.push("style=dashed, color=grey, fontcolor=grey".to_owned());
+        }
+
+        writeln!(
+            dot,
+            "    {} -> {} [{}];",
+            e.from,
+            e.to,
+            attributes.join(", ")
+        )
+        .unwrap();
+    });
+
+    writeln!(dot, "}}").unwrap();
+
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_dot_works() {
+        let network: Network = (&Genome::new(2, 1)).into();
+
+        let dot = to_dot(&network);
+
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("{ rank=source; 0; 1; }"));
+        assert!(dot.contains("{ rank=sink; 2; }"));
+        assert!(dot.contains("0 -> 2"));
+        assert!(dot.contains("1 -> 2"));
+        assert!(!dot.contains("dashed"));
+    }
+
+    #[test]
+    fn genome_to_dot_draws_disabled_connections_dashed() {
+        let mut genome = Genome::new(2, 1);
+        genome.connection_mut(0).unwrap().disabled = true;
+
+        let dot = genome_to_dot(&genome);
+
+        let disabled_edge = dot.lines().find(|l| l.contains("0 -> 2")).unwrap();
+        let enabled_edge = dot.lines().find(|l| l.contains("1 -> 2")).unwrap();
+
+        assert!(disabled_edge.contains("style=dashed"));
+        assert!(!enabled_edge.contains("style=dashed"));
+    }
+}
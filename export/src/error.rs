@@ -0,0 +1,51 @@
+use std::fmt;
+
+/// Error returned when a network can't be read or written
+#[derive(Debug)]
+pub enum ExportError {
+    Io(std::io::Error),
+    Bincode(bincode::Error),
+    Json(serde_json::Error),
+    /// The network uses something the target format can't express
+    Unsupported(String),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExportError::Io(e) => write!(f, "io error: {}", e),
+            ExportError::Bincode(e) => write!(f, "bincode error: {}", e),
+            ExportError::Json(e) => write!(f, "json error: {}", e),
+            ExportError::Unsupported(message) => write!(f, "unsupported network: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for ExportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ExportError::Io(e) => Some(e),
+            ExportError::Bincode(e) => Some(e),
+            ExportError::Json(e) => Some(e),
+            ExportError::Unsupported(_) => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ExportError {
+    fn from(e: std::io::Error) -> Self {
+        ExportError::Io(e)
+    }
+}
+
+impl From<bincode::Error> for ExportError {
+    fn from(e: bincode::Error) -> Self {
+        ExportError::Bincode(e)
+    }
+}
+
+impl From<serde_json::Error> for ExportError {
+    fn from(e: serde_json::Error) -> Self {
+        ExportError::Json(e)
+    }
+}
@@ -1,21 +1,45 @@
+mod dot;
+mod error;
+mod onnx;
+
+pub use dot::*;
+pub use error::ExportError;
+pub use onnx::*;
+
 use neat_core::Network;
-use std::fs::{read, write};
+use std::fs::{read, read_to_string, write};
 use std::path::Path;
 
 pub fn to_bytes(network: &Network) -> Vec<u8> {
     bincode::serialize(network).unwrap()
 }
 
-pub fn from_bytes(bytes: &[u8]) -> Network {
-    bincode::deserialize(bytes).unwrap()
+pub fn from_bytes(bytes: &[u8]) -> Result<Network, ExportError> {
+    Ok(bincode::deserialize(bytes)?)
+}
+
+pub fn to_file<S: AsRef<Path>>(path: S, network: &Network) -> Result<(), ExportError> {
+    Ok(write(path, to_bytes(network))?)
+}
+
+pub fn from_file<S: AsRef<Path>>(path: S) -> Result<Network, ExportError> {
+    from_bytes(&read(path)?)
+}
+
+pub fn to_json(network: &Network) -> String {
+    serde_json::to_string_pretty(network).unwrap()
+}
+
+pub fn from_json(json: &str) -> Result<Network, ExportError> {
+    Ok(serde_json::from_str(json)?)
 }
 
-pub fn to_file<S: AsRef<Path>>(path: S, network: &Network) {
-    write(path, to_bytes(&network)).unwrap();
+pub fn to_json_file<S: AsRef<Path>>(path: S, network: &Network) -> Result<(), ExportError> {
+    Ok(write(path, to_json(network))?)
 }
 
-pub fn from_file<S: AsRef<Path>>(path: S) -> Network {
-    from_bytes(&read(path).unwrap())
+pub fn from_json_file<S: AsRef<Path>>(path: S) -> Result<Network, ExportError> {
+    from_json(&read_to_string(path)?)
 }
 
 #[cfg(test)]
@@ -36,7 +60,7 @@ mod tests {
         let output_before = network.forward_pass(vec![1., 2., 3.]);
 
         let bytes = to_bytes(&network);
-        let mut imported_network = from_bytes(&bytes);
+        let mut imported_network = from_bytes(&bytes).unwrap();
 
         let output_after = imported_network.forward_pass(vec![1., 2., 3.]);
 
@@ -50,8 +74,8 @@ mod tests {
         let mut network: Network = (&Genome::new(3, 1)).into();
         let output_before = network.forward_pass(vec![1., 2., 3.]);
 
-        to_file(filename, &network);
-        let mut imported_network = from_file(filename);
+        to_file(filename, &network).unwrap();
+        let mut imported_network = from_file(filename).unwrap();
 
         let output_after = imported_network.forward_pass(vec![1., 2., 3.]);
 
@@ -59,4 +83,55 @@ mod tests {
 
         std::fs::remove_file(filename).unwrap();
     }
+
+    #[test]
+    fn from_json_works() {
+        let mut network: Network = (&Genome::new(3, 1)).into();
+        let output_before = network.forward_pass(vec![1., 2., 3.]);
+
+        let json = to_json(&network);
+        let mut imported_network = from_json(&json).unwrap();
+
+        let output_after = imported_network.forward_pass(vec![1., 2., 3.]);
+
+        assert_eq!(output_before, output_after);
+    }
+
+    #[test]
+    fn json_file_import_export_works() {
+        let filename = "network.json";
+
+        let mut network: Network = (&Genome::new(3, 1)).into();
+        let output_before = network.forward_pass(vec![1., 2., 3.]);
+
+        to_json_file(filename, &network).unwrap();
+        let mut imported_network = from_json_file(filename).unwrap();
+
+        let output_after = imported_network.forward_pass(vec![1., 2., 3.]);
+
+        assert_eq!(output_before, output_after);
+
+        std::fs::remove_file(filename).unwrap();
+    }
+
+    #[test]
+    fn corrupt_input_returns_error() {
+        assert!(matches!(
+            from_bytes(&[1, 2, 3]),
+            Err(ExportError::Bincode(_))
+        ));
+        assert!(matches!(from_json("{"), Err(ExportError::Json(_))));
+    }
+
+    #[test]
+    fn missing_file_returns_error() {
+        assert!(matches!(
+            from_file("missing-network.bin"),
+            Err(ExportError::Io(_))
+        ));
+        assert!(matches!(
+            from_json_file("missing-network.json"),
+            Err(ExportError::Io(_))
+        ));
+    }
 }
@@ -0,0 +1,485 @@
+use neat_core::{ActivationKind, Aggregation, Network, NodeKind};
+use std::fs::write;
+use std::path::Path;
+
+use crate::ExportError;
+
+/// ONNX IR version that goes with opset 13
+const IR_VERSION: u64 = 7;
+const OPSET_VERSION: u64 = 13;
+/// The `FLOAT` element type of ONNX tensors
+const FLOAT: u64 = 1;
+
+/// Converts a feed forward network to an ONNX model that maps a `[batch, inputs]` tensor named
+/// `input` to a `[batch, outputs]` tensor named `output`
+///
+/// Every node becomes a Gemm followed by its activation, so only the `Sum` aggregation and
+/// activations with an ONNX counterpart can be exported. Values are stored as 32 bit floats.
+pub fn to_onnx(network: &Network) -> Result<Vec<u8>, ExportError> {
+    if network.connections().iter().any(|c| c.recurrent) {
+        return Err(ExportError::Unsupported(
+            "recurrent connections can't be exported to ONNX".to_owned(),
+        ));
+    }
+    if network.input_count == 0 {
+        return Err(ExportError::Unsupported(
+            "networks without inputs can't be exported to ONNX".to_owned(),
+        ));
+    }
+
+    let mut graph = Graph::default();
+
+    let input_names: Vec<String> = (0..network.input_count).map(value_name).collect();
+    graph.node(
+        "Split",
+        &["input"],
+        &input_names,
+        vec![Attribute::int("axis", 1)],
+    );
+    graph.initializer("zero", &[], &[0.]);
+
+    for i in network.node_order() {
+        let node = &network.nodes[*i];
+
+        if matches!(node.kind, NodeKind::Input | NodeKind::Constant) {
+            continue;
+        }
+        if !matches!(
+            node.aggregation,
+            Aggregation::Sum | Aggregation::WeightedSum
+        ) {
+            return Err(ExportError::Unsupported(format!(
+                "node {} uses the {:?} aggregation, only sums can be exported to ONNX",
+                i, node.aggregation
+            )));
+        }
+
+        // Constant nodes always output their bias so they are folded into the bias of the node
+        let mut bias = node.bias;
+        let mut sources = vec![];
+        let mut weights = vec![];
+        network
+            .connections()
+            .iter()
+            .filter(|c| c.to == *i)
+            .for_each(|c| {
+                let from = &network.nodes[c.from];
+
+                if matches!(from.kind, NodeKind::Constant) {
+                    bias += from.bias * c.weight;
+                } else {
+                    sources.push(value_name(c.from));
+                    weights.push(c.weight as f32);
+                }
+            });
+
+        // Nodes without incoming values still need a value for every row of the batch
+        if sources.is_empty() {
+            sources.push(value_name(0));
+            weights.push(0.);
+        }
+
+        let gemm_input = if sources.len() == 1 {
+            sources[0].clone()
+        } else {
+            let name = format!("incoming_{}", i);
+            graph.node(
+                "Concat",
+                &sources,
+                &[&name],
+                vec![Attribute::int("axis", 1)],
+            );
+            name
+        };
+
+        let weights_name = format!("weights_{}", i);
+        let bias_name = format!("bias_{}", i);
+        let sum_name = format!("sum_{}", i);
+        graph.initializer(&weights_name, &[weights.len() as u64, 1], &weights);
+        graph.initializer(&bias_name, &[1], &[bias as f32]);
+        graph.node(
+            "Gemm",
+            &[&gemm_input, &weights_name, &bias_name],
+            &[&sum_name],
+            vec![],
+        );
+
+        activation(&mut graph, &node.activation, &sum_name, &value_name(*i)).ok_or_else(|| {
+            ExportError::Unsupported(format!(
+                "node {} uses the {:?} activation which has no ONNX counterpart",
+                i, node.activation
+            ))
+        })?;
+    }
+
+    let output_names: Vec<String> = network
+        .nodes
+        .iter()
+        .enumerate()
+        .filter(|(_, n)| matches!(n.kind, NodeKind::Output))
+        .map(|(i, _)| value_name(i))
+        .collect();
+    graph.node(
+        "Concat",
+        &output_names,
+        &["output"],
+        vec![Attribute::int("axis", 1)],
+    );
+
+    Ok(graph.into_model(network.input_count, network.output_count))
+}
+
+/// Writes the network to a file as an ONNX model, see `to_onnx`
+pub fn to_onnx_file<S: AsRef<Path>>(path: S, network: &Network) -> Result<(), ExportError> {
+    Ok(write(path, to_onnx(network)?)?)
+}
+
+fn value_name(node: usize) -> String {
+    format!("node_{}", node)
+}
+
+/// Adds the ops computing the activation of `input` into `output`, returns `None` if the
+/// activation has no ONNX counterpart
+fn activation(graph: &mut Graph, kind: &ActivationKind, input: &str, output: &str) -> Option<()> {
+    let temporary = |suffix: &str| format!("{}_{}", output, suffix);
+
+    match kind {
+        ActivationKind::Input | ActivationKind::Identity => {
+            graph.node("Identity", &[input], &[output], vec![])
+        }
+        ActivationKind::Tanh => graph.node("Tanh", &[input], &[output], vec![]),
+        ActivationKind::Relu => graph.node(
+            "LeakyRelu",
+            &[input],
+            &[output],
+            vec![Attribute::float("alpha", 0.01)],
+        ),
+        ActivationKind::Logistic => graph.node("Sigmoid", &[input], &[output], vec![]),
+        ActivationKind::Softsign => graph.node("Softsign", &[input], &[output], vec![]),
+        ActivationKind::Sinusoid => graph.node("Sin", &[input], &[output], vec![]),
+        ActivationKind::SELU => graph.node(
+            "Selu",
+            &[input],
+            &[output],
+            vec![
+                Attribute::float("alpha", 1.673_263_2),
+                Attribute::float("gamma", 1.050_701),
+            ],
+        ),
+        ActivationKind::Step => {
+            let greater = temporary("greater");
+            graph.node("Greater", &[input, "zero"], &[&greater], vec![]);
+            graph.node(
+                "Cast",
+                &[&greater],
+                &[output],
+                vec![Attribute::int("to", FLOAT as i64)],
+            );
+        }
+        ActivationKind::Gaussian => {
+            let square = temporary("square");
+            let negated = temporary("negated");
+            graph.node("Mul", &[input, input], &[&square], vec![]);
+            graph.node("Neg", &[&square], &[&negated], vec![]);
+            graph.node("Exp", &[&negated], &[output], vec![]);
+        }
+        ActivationKind::Inverse => {
+            graph.initializer("one", &[], &[1.]);
+            graph.node("Sub", &["one", input], &[output], vec![]);
+        }
+        ActivationKind::Swish => {
+            let sigmoid = temporary("sigmoid");
+            graph.node("Sigmoid", &[input], &[&sigmoid], vec![]);
+            graph.node("Mul", &[input, &sigmoid], &[output], vec![]);
+        }
+        ActivationKind::Mish => {
+            let softplus = temporary("softplus");
+            let tanh = temporary("tanh");
+            graph.node("Softplus", &[input], &[&softplus], vec![]);
+            graph.node("Tanh", &[&softplus], &[&tanh], vec![]);
+            graph.node("Mul", &[input, &tanh], &[output], vec![]);
+        }
+        ActivationKind::BentIdentity | ActivationKind::Bipolar | ActivationKind::GELU => {
+            return None
+        }
+    }
+
+    Some(())
+}
+
+enum AttributeValue {
+    Float(f32),
+    Int(i64),
+}
+
+struct Attribute {
+    name: &'static str,
+    value: AttributeValue,
+}
+
+impl Attribute {
+    fn float(name: &'static str, value: f32) -> Self {
+        Attribute {
+            name,
+            value: AttributeValue::Float(value),
+        }
+    }
+
+    fn int(name: &'static str, value: i64) -> Self {
+        Attribute {
+            name,
+            value: AttributeValue::Int(value),
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut message = Message::default();
+        message.string(1, self.name);
+
+        match self.value {
+            AttributeValue::Float(value) => {
+                message.fixed32(2, value.to_bits());
+                message.varint(20, 1);
+            }
+            AttributeValue::Int(value) => {
+                message.varint(3, value as u64);
+                message.varint(20, 2);
+            }
+        }
+
+        message.bytes
+    }
+}
+
+/// Collects the ops and weights of the model as encoded protobuf messages
+#[derive(Default)]
+struct Graph {
+    nodes: Vec<Vec<u8>>,
+    initializers: Vec<Vec<u8>>,
+    initializer_names: Vec<String>,
+}
+
+impl Graph {
+    fn node<I: AsRef<str>, O: AsRef<str>>(
+        &mut self,
+        op_type: &str,
+        inputs: &[I],
+        outputs: &[O],
+        attributes: Vec<Attribute>,
+    ) {
+        let mut message = Message::default();
+        inputs.iter().for_each(|i| message.string(1, i.as_ref()));
+        outputs.iter().for_each(|o| message.string(2, o.as_ref()));
+        message.string(3, &format!("{}_{}", op_type, self.nodes.len()));
+        message.string(4, op_type);
+        attributes
+            .iter()
+            .for_each(|a| message.message(5, &a.encode()));
+
+        self.nodes.push(message.bytes);
+    }
+
+    /// Adds a float tensor the ops can read by name, tensors that already exist are kept
+    fn initializer(&mut self, name: &str, dims: &[u64], values: &[f32]) {
+        if self.initializer_names.iter().any(|n| n == name) {
+            return;
+        }
+
+        let mut message = Message::default();
+        dims.iter().for_each(|d| message.varint(1, *d));
+        message.varint(2, FLOAT);
+        message.packed_floats(4, values);
+        message.string(8, name);
+
+        self.initializers.push(message.bytes);
+        self.initializer_names.push(name.to_owned());
+    }
+
+    fn into_model(self, inputs: usize, outputs: usize) -> Vec<u8> {
+        let mut graph = Message::default();
+        self.nodes.iter().for_each(|n| graph.message(1, n));
+        graph.string(2, "neat");
+        self.initializers.iter().for_each(|i| graph.message(5, i));
+        graph.message(11, &value_info("input", inputs));
+        graph.message(12, &value_info("output", outputs));
+
+        let mut opset = Message::default();
+        opset.string(1, "");
+        opset.varint(2, OPSET_VERSION);
+
+        let mut model = Message::default();
+        model.varint(1, IR_VERSION);
+        model.string(2, "neat-rs");
+        model.message(7, &graph.bytes);
+        model.message(8, &opset.bytes);
+
+        model.bytes
+    }
+}
+
+/// Describes a float tensor of shape `[batch, width]`
+fn value_info(name: &str, width: usize) -> Vec<u8> {
+    let mut batch = Message::default();
+    batch.string(2, "batch");
+    let mut features = Message::default();
+    features.varint(1, width as u64);
+
+    let mut shape = Message::default();
+    shape.message(1, &batch.bytes);
+    shape.message(1, &features.bytes);
+
+    let mut tensor_type = Message::default();
+    tensor_type.varint(1, FLOAT);
+    tensor_type.message(2, &shape.bytes);
+
+    let mut type_proto = Message::default();
+    type_proto.message(1, &tensor_type.bytes);
+
+    let mut value_info = Message::default();
+    value_info.string(1, name);
+    value_info.message(2, &type_proto.bytes);
+
+    value_info.bytes
+}
+
+/// Writes fields in the protobuf wire format
+#[derive(Default)]
+struct Message {
+    bytes: Vec<u8>,
+}
+
+impl Message {
+    fn key(&mut self, field: u64, wire_type: u64) {
+        self.raw_varint((field << 3) | wire_type);
+    }
+
+    fn raw_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn varint(&mut self, field: u64, value: u64) {
+        self.key(field, 0);
+        self.raw_varint(value);
+    }
+
+    fn fixed32(&mut self, field: u64, value: u32) {
+        self.key(field, 5);
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn message(&mut self, field: u64, bytes: &[u8]) {
+        self.key(field, 2);
+        self.raw_varint(bytes.len() as u64);
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    fn string(&mut self, field: u64, value: &str) {
+        self.message(field, value.as_bytes());
+    }
+
+    fn packed_floats(&mut self, field: u64, values: &[f32]) {
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        self.message(field, &bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use neat_core::Genome;
+
+    /// Reads the length delimited fields of a protobuf message, other fields are skipped
+    fn fields(mut bytes: &[u8]) -> Vec<(u64, &[u8])> {
+        let mut fields = vec![];
+
+        let read_varint = |bytes: &mut &[u8]| {
+            let mut value = 0;
+            let mut shift = 0;
+            loop {
+                let byte = bytes[0];
+                *bytes = &bytes[1..];
+                value |= ((byte & 0x7f) as u64) << shift;
+                shift += 7;
+                if byte < 0x80 {
+                    return value;
+                }
+            }
+        };
+
+        while !bytes.is_empty() {
+            let key = read_varint(&mut bytes);
+            match key & 7 {
+                0 => {
+                    read_varint(&mut bytes);
+                }
+                5 => bytes = &bytes[4..],
+                _ => {
+                    let length = read_varint(&mut bytes) as usize;
+                    fields.push((key >> 3, &bytes[..length]));
+                    bytes = &bytes[length..];
+                }
+            }
+        }
+
+        fields
+    }
+
+    fn op_types(model: &[u8]) -> Vec<String> {
+        let graph = fields(model).into_iter().find(|(f, _)| *f == 7).unwrap().1;
+
+        fields(graph)
+            .into_iter()
+            .filter(|(f, _)| *f == 1)
+            .map(|(_, node)| {
+                let op_type = fields(node).into_iter().find(|(f, _)| *f == 4).unwrap().1;
+                String::from_utf8(op_type.to_vec()).unwrap()
+            })
+            .collect()
+    }
+
+    fn network(activation: ActivationKind, aggregation: Aggregation) -> Network {
+        let mut builder = Genome::builder(2, 1);
+        let hidden = builder.add_hidden_node();
+        builder
+            .connect(0, hidden, 0.5)
+            .connect(1, hidden, -0.5)
+            .connect(hidden, 2, 1.);
+        let mut genome = builder.build().unwrap();
+
+        (0..genome.nodes().len()).for_each(|i| {
+            let node = genome.node_mut(i).unwrap();
+            if !matches!(node.kind, NodeKind::Input) {
+                node.activation = activation.clone();
+                node.aggregation = aggregation.clone();
+            }
+        });
+
+        Network::from(&genome)
+    }
+
+    #[test]
+    fn feed_forward_network_is_exported() {
+        let model = to_onnx(&network(ActivationKind::Tanh, Aggregation::Sum)).unwrap();
+
+        assert_eq!(
+            op_types(&model),
+            vec!["Split", "Concat", "Gemm", "Tanh", "Gemm", "Tanh", "Concat"]
+        );
+    }
+
+    #[test]
+    fn unsupported_networks_are_rejected() {
+        assert!(matches!(
+            to_onnx(&network(ActivationKind::Tanh, Aggregation::Max)),
+            Err(ExportError::Unsupported(_))
+        ));
+        assert!(matches!(
+            to_onnx(&network(ActivationKind::GELU, Aggregation::Sum)),
+            Err(ExportError::Unsupported(_))
+        ));
+    }
+}